@@ -0,0 +1,7 @@
+fn main() {
+    // Features of later libfido2 releases, as detected by the build script of libfido2_sys
+    println!("cargo:rustc-check-cfg=cfg(fido2_1_14)");
+    if std::env::var_os("DEP_FIDO2_VERSION_1_14").is_some() {
+        println!("cargo:rustc-cfg=fido2_1_14");
+    }
+}
@@ -11,9 +11,9 @@ const USER_ID: [u8; 32] = [
     0xe7, 0xa4, 0x2b, 0x44, 0x89, 0x29, 0x39, 0xc5, 0x56, 0x64, 0x01, 0x27, 0x0d, 0xbb, 0xc4, 0x49,
 ];
 
-const USER_NAME: &'static str = "John Doe";
-const RELYING_PARTY_ID: &'static str = "localhost";
-const RELYING_PARTY_NAME: &'static str = "Oost West, Thuis Best";
+const USER_NAME: &str = "John Doe";
+const RELYING_PARTY_ID: &str = "localhost";
+const RELYING_PARTY_NAME: &str = "Oost West, Thuis Best";
 
 pub fn main() {
     // Prepare CStrings
@@ -2,3 +2,6 @@
 #include "fido/es256.h"
 #include "fido/rs256.h"
 #include "fido/eddsa.h"
+#include "fido/credman.h"
+#include "fido/bio.h"
+#include "fido/config.h"
@@ -1,3 +1,5 @@
+use std::{env, process::Command};
+
 fn main() {
     if cfg!(target_os = "windows") {
         let lib_dir = std::env::var("FIDO2_LIB_DIR")
@@ -11,4 +13,34 @@ fn main() {
     } else {
         panic!("Unsupported platform");
     }
+
+    // Declarations of later releases are only compiled if the linked libfido2 provides them.
+    // Dependents receive this as DEP_FIDO2_VERSION_1_14.
+    println!("cargo:rustc-check-cfg=cfg(fido2_1_14)");
+    println!("cargo:rerun-if-env-changed=FIDO2_VERSION");
+    if version_at_least("1.14") {
+        println!("cargo:rustc-cfg=fido2_1_14");
+        println!("cargo:version_1_14=1");
+    }
+}
+
+/// Returns whether the linked libfido2 is at least `required`, according to the `FIDO2_VERSION`
+/// environment variable if set, or pkg-config otherwise.
+fn version_at_least(required: &str) -> bool {
+    match env::var("FIDO2_VERSION") {
+        Ok(version) => parse_version(&version) >= parse_version(required),
+        Err(_) => Command::new("pkg-config")
+            .args(["--atleast-version", required, "libfido2"])
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false),
+    }
+}
+
+fn parse_version(version: &str) -> Vec<u32> {
+    version
+        .trim()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
 }
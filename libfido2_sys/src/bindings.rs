@@ -14,6 +14,7 @@ pub const FIDO_ERR_INVALID_CBOR: u32 = 18;
 pub const FIDO_ERR_MISSING_PARAMETER: u32 = 20;
 pub const FIDO_ERR_LIMIT_EXCEEDED: u32 = 21;
 pub const FIDO_ERR_UNSUPPORTED_EXTENSION: u32 = 22;
+pub const FIDO_ERR_LARGEBLOB_STORAGE_FULL: u32 = 24;
 pub const FIDO_ERR_CREDENTIAL_EXCLUDED: u32 = 25;
 pub const FIDO_ERR_PROCESSING: u32 = 33;
 pub const FIDO_ERR_INVALID_CREDENTIAL: u32 = 34;
@@ -54,6 +55,7 @@ pub const FIDO_ERR_INVALID_SIG: i32 = -6;
 pub const FIDO_ERR_INVALID_ARGUMENT: i32 = -7;
 pub const FIDO_ERR_USER_PRESENCE_REQUIRED: i32 = -8;
 pub const FIDO_ERR_INTERNAL: i32 = -9;
+pub const FIDO_ERR_NOT_FOUND: i32 = -10;
 pub const CTAP_AUTHDATA_USER_PRESENT: u32 = 1;
 pub const CTAP_AUTHDATA_USER_VERIFIED: u32 = 4;
 pub const CTAP_AUTHDATA_ATT_CRED: u32 = 64;
@@ -72,6 +74,11 @@ pub const CTAP_CBOR_GETINFO: u32 = 4;
 pub const CTAP_CBOR_CLIENT_PIN: u32 = 6;
 pub const CTAP_CBOR_RESET: u32 = 7;
 pub const CTAP_CBOR_NEXT_ASSERT: u32 = 8;
+pub const CTAP_CBOR_BIO_ENROLL: u32 = 9;
+pub const CTAP_CBOR_CRED_MGMT: u32 = 10;
+pub const CTAP_CBOR_LARGEBLOB: u32 = 12;
+pub const CTAP_CBOR_CONFIG: u32 = 13;
+pub const CTAP_CBOR_BIO_ENROLL_PRE: u32 = 64;
 pub const CTAP_CBOR_CRED_MGMT_PRE: u32 = 65;
 pub const U2F_CMD_REGISTER: u32 = 1;
 pub const U2F_CMD_AUTH: u32 = 2;
@@ -79,7 +86,7 @@ pub const U2F_AUTH_SIGN: u32 = 3;
 pub const U2F_AUTH_CHECK: u32 = 7;
 pub const CTAP_CID_BROADCAST: u32 = 4294967295;
 pub const CTAP_RPT_SIZE: u32 = 64;
-pub const FIDO_RANDOM_DEV: &'static [u8; 13usize] = b"/dev/urandom\0";
+pub const FIDO_RANDOM_DEV: &[u8; 13usize] = b"/dev/urandom\0";
 pub const FIDO_CAP_WINK: u32 = 1;
 pub const FIDO_CAP_CBOR: u32 = 4;
 pub const FIDO_CAP_NMSG: u32 = 8;
@@ -92,6 +99,13 @@ pub const COSE_KTY_RSA: u32 = 3;
 pub const COSE_P256: u32 = 1;
 pub const COSE_ED25519: u32 = 6;
 pub const FIDO_EXT_HMAC_SECRET: u32 = 1;
+pub const FIDO_EXT_CRED_PROTECT: u32 = 2;
+pub const FIDO_EXT_LARGEBLOB_KEY: u32 = 4;
+pub const FIDO_EXT_CRED_BLOB: u32 = 8;
+pub const FIDO_EXT_MINPINLEN: u32 = 16;
+pub const FIDO_CRED_PROT_UV_OPTIONAL: u32 = 1;
+pub const FIDO_CRED_PROT_UV_OPTIONAL_WITH_ID: u32 = 2;
+pub const FIDO_CRED_PROT_UV_REQUIRED: u32 = 3;
 pub const FIDO_DEBUG: u32 = 1;
 pub type __uint8_t = ::std::os::raw::c_uchar;
 pub type __int16_t = ::std::os::raw::c_short;
@@ -144,6 +158,8 @@ pub struct fido_dev_io {
 }
 #[test]
 fn bindgen_test_layout_fido_dev_io() {
+    const UNINIT: ::std::mem::MaybeUninit<fido_dev_io> = ::std::mem::MaybeUninit::uninit();
+    let ptr = UNINIT.as_ptr();
     assert_eq!(
         ::std::mem::size_of::<fido_dev_io>(),
         32usize,
@@ -155,7 +171,7 @@ fn bindgen_test_layout_fido_dev_io() {
         concat!("Alignment of ", stringify!(fido_dev_io))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<fido_dev_io>())).open as *const _ as usize },
+        unsafe { ::std::ptr::addr_of!((*ptr).open) as usize - ptr as usize },
         0usize,
         concat!(
             "Offset of field: ",
@@ -165,7 +181,7 @@ fn bindgen_test_layout_fido_dev_io() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<fido_dev_io>())).close as *const _ as usize },
+        unsafe { ::std::ptr::addr_of!((*ptr).close) as usize - ptr as usize },
         8usize,
         concat!(
             "Offset of field: ",
@@ -175,7 +191,7 @@ fn bindgen_test_layout_fido_dev_io() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<fido_dev_io>())).read as *const _ as usize },
+        unsafe { ::std::ptr::addr_of!((*ptr).read) as usize - ptr as usize },
         16usize,
         concat!(
             "Offset of field: ",
@@ -185,7 +201,7 @@ fn bindgen_test_layout_fido_dev_io() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<fido_dev_io>())).write as *const _ as usize },
+        unsafe { ::std::ptr::addr_of!((*ptr).write) as usize - ptr as usize },
         24usize,
         concat!(
             "Offset of field: ",
@@ -532,6 +548,13 @@ extern "C" {
         arg2: *const ::std::os::raw::c_char,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn fido_cred_set_id(
+        arg1: *mut fido_cred_t,
+        arg2: *const ::std::os::raw::c_uchar,
+        arg3: usize,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn fido_cred_set_options(
         arg1: *mut fido_cred_t,
@@ -588,6 +611,12 @@ extern "C" {
 extern "C" {
     pub fn fido_cred_verify(arg1: *const fido_cred_t) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn fido_cred_verify_self(arg1: *const fido_cred_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_dev_cancel(arg1: *mut fido_dev_t) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn fido_dev_close(arg1: *mut fido_dev_t) -> ::std::os::raw::c_int;
 }
@@ -604,6 +633,16 @@ extern "C" {
         arg2: *mut fido_cbor_info_t,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn fido_dev_get_touch_begin(arg1: *mut fido_dev_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_dev_get_touch_status(
+        arg1: *mut fido_dev_t,
+        arg2: *mut ::std::os::raw::c_int,
+        arg3: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn fido_dev_get_retry_count(
         arg1: *mut fido_dev_t,
@@ -617,6 +656,9 @@ extern "C" {
         arg3: *mut usize,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn fido_dev_io_handle(arg1: *const fido_dev_t) -> *mut ::std::os::raw::c_void;
+}
 extern "C" {
     pub fn fido_dev_make_cred(
         arg1: *mut fido_dev_t,
@@ -639,6 +681,12 @@ extern "C" {
         arg2: *const fido_dev_io_t,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn fido_dev_set_timeout(
+        arg1: *mut fido_dev_t,
+        arg2: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn fido_dev_set_pin(
         arg1: *mut fido_dev_t,
@@ -799,3 +847,406 @@ extern "C" {
         arg3: usize,
     ) -> ::std::os::raw::c_int;
 }
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct fido_credman_metadata {
+    _unused: [u8; 0],
+}
+pub type fido_credman_metadata_t = fido_credman_metadata;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct fido_credman_rk {
+    _unused: [u8; 0],
+}
+pub type fido_credman_rk_t = fido_credman_rk;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct fido_credman_rp {
+    _unused: [u8; 0],
+}
+pub type fido_credman_rp_t = fido_credman_rp;
+extern "C" {
+    pub fn fido_credman_metadata_new() -> *mut fido_credman_metadata_t;
+}
+extern "C" {
+    pub fn fido_credman_rk_new() -> *mut fido_credman_rk_t;
+}
+extern "C" {
+    pub fn fido_credman_rp_new() -> *mut fido_credman_rp_t;
+}
+extern "C" {
+    pub fn fido_credman_metadata_free(arg1: *mut *mut fido_credman_metadata_t);
+}
+extern "C" {
+    pub fn fido_credman_rk_free(arg1: *mut *mut fido_credman_rk_t);
+}
+extern "C" {
+    pub fn fido_credman_rp_free(arg1: *mut *mut fido_credman_rp_t);
+}
+extern "C" {
+    pub fn fido_credman_rk(arg1: *const fido_credman_rk_t, arg2: usize) -> *const fido_cred_t;
+}
+extern "C" {
+    pub fn fido_credman_rk_count(arg1: *const fido_credman_rk_t) -> usize;
+}
+extern "C" {
+    pub fn fido_credman_rp_id(
+        arg1: *const fido_credman_rp_t,
+        arg2: usize,
+    ) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn fido_credman_rp_name(
+        arg1: *const fido_credman_rp_t,
+        arg2: usize,
+    ) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn fido_credman_rp_count(arg1: *const fido_credman_rp_t) -> usize;
+}
+extern "C" {
+    pub fn fido_credman_rp_id_hash_ptr(
+        arg1: *const fido_credman_rp_t,
+        arg2: usize,
+    ) -> *const ::std::os::raw::c_uchar;
+}
+extern "C" {
+    pub fn fido_credman_rp_id_hash_len(arg1: *const fido_credman_rp_t, arg2: usize) -> usize;
+}
+extern "C" {
+    pub fn fido_credman_rk_existing(arg1: *const fido_credman_metadata_t) -> u64;
+}
+extern "C" {
+    pub fn fido_credman_rk_remaining(arg1: *const fido_credman_metadata_t) -> u64;
+}
+extern "C" {
+    pub fn fido_credman_del_dev_rk(
+        arg1: *mut fido_dev_t,
+        arg2: *const ::std::os::raw::c_uchar,
+        arg3: usize,
+        arg4: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_credman_get_dev_metadata(
+        arg1: *mut fido_dev_t,
+        arg2: *mut fido_credman_metadata_t,
+        arg3: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_credman_get_dev_rk(
+        arg1: *mut fido_dev_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: *mut fido_credman_rk_t,
+        arg4: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_credman_get_dev_rp(
+        arg1: *mut fido_dev_t,
+        arg2: *mut fido_credman_rp_t,
+        arg3: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_credman_set_dev_rk(
+        arg1: *mut fido_dev_t,
+        arg2: *mut fido_cred_t,
+        arg3: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+pub const FIDO_BIO_ENROLL_FP_GOOD: u32 = 0;
+pub const FIDO_BIO_ENROLL_FP_TOO_HIGH: u32 = 1;
+pub const FIDO_BIO_ENROLL_FP_TOO_LOW: u32 = 2;
+pub const FIDO_BIO_ENROLL_FP_TOO_LEFT: u32 = 3;
+pub const FIDO_BIO_ENROLL_FP_TOO_RIGHT: u32 = 4;
+pub const FIDO_BIO_ENROLL_FP_TOO_FAST: u32 = 5;
+pub const FIDO_BIO_ENROLL_FP_TOO_SLOW: u32 = 6;
+pub const FIDO_BIO_ENROLL_FP_POOR_QUALITY: u32 = 7;
+pub const FIDO_BIO_ENROLL_FP_TOO_SKEWED: u32 = 8;
+pub const FIDO_BIO_ENROLL_FP_TOO_SHORT: u32 = 9;
+pub const FIDO_BIO_ENROLL_FP_MERGE_FAILURE: u32 = 10;
+pub const FIDO_BIO_ENROLL_FP_EXISTS: u32 = 11;
+pub const FIDO_BIO_ENROLL_FP_DATABASE_FULL: u32 = 12;
+pub const FIDO_BIO_ENROLL_NO_USER_ACTIVITY: u32 = 13;
+pub const FIDO_BIO_ENROLL_NO_USER_PRESENCE_TRANSITION: u32 = 14;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct fido_bio_template {
+    _unused: [u8; 0],
+}
+pub type fido_bio_template_t = fido_bio_template;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct fido_bio_template_array {
+    _unused: [u8; 0],
+}
+pub type fido_bio_template_array_t = fido_bio_template_array;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct fido_bio_enroll {
+    _unused: [u8; 0],
+}
+pub type fido_bio_enroll_t = fido_bio_enroll;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct fido_bio_info {
+    _unused: [u8; 0],
+}
+pub type fido_bio_info_t = fido_bio_info;
+extern "C" {
+    pub fn fido_bio_template_name(
+        arg1: *const fido_bio_template_t,
+    ) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn fido_bio_template(
+        arg1: *const fido_bio_template_array_t,
+        arg2: usize,
+    ) -> *const fido_bio_template_t;
+}
+extern "C" {
+    pub fn fido_bio_template_id_ptr(
+        arg1: *const fido_bio_template_t,
+    ) -> *const ::std::os::raw::c_uchar;
+}
+extern "C" {
+    pub fn fido_bio_enroll_new() -> *mut fido_bio_enroll_t;
+}
+extern "C" {
+    pub fn fido_bio_info_new() -> *mut fido_bio_info_t;
+}
+extern "C" {
+    pub fn fido_bio_template_array_new() -> *mut fido_bio_template_array_t;
+}
+extern "C" {
+    pub fn fido_bio_template_new() -> *mut fido_bio_template_t;
+}
+extern "C" {
+    pub fn fido_bio_dev_enroll_begin(
+        arg1: *mut fido_dev_t,
+        arg2: *mut fido_bio_template_t,
+        arg3: *mut fido_bio_enroll_t,
+        arg4: u32,
+        arg5: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_bio_dev_enroll_cancel(arg1: *mut fido_dev_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_bio_dev_enroll_continue(
+        arg1: *mut fido_dev_t,
+        arg2: *const fido_bio_template_t,
+        arg3: *mut fido_bio_enroll_t,
+        arg4: u32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_bio_dev_enroll_remove(
+        arg1: *mut fido_dev_t,
+        arg2: *const fido_bio_template_t,
+        arg3: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_bio_dev_get_info(
+        arg1: *mut fido_dev_t,
+        arg2: *mut fido_bio_info_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_bio_dev_get_template_array(
+        arg1: *mut fido_dev_t,
+        arg2: *mut fido_bio_template_array_t,
+        arg3: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_bio_dev_set_template_name(
+        arg1: *mut fido_dev_t,
+        arg2: *const fido_bio_template_t,
+        arg3: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_bio_template_set_id(
+        arg1: *mut fido_bio_template_t,
+        arg2: *const ::std::os::raw::c_uchar,
+        arg3: usize,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_bio_template_set_name(
+        arg1: *mut fido_bio_template_t,
+        arg2: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_bio_template_array_count(arg1: *const fido_bio_template_array_t) -> usize;
+}
+extern "C" {
+    pub fn fido_bio_template_id_len(arg1: *const fido_bio_template_t) -> usize;
+}
+extern "C" {
+    pub fn fido_bio_enroll_last_status(arg1: *const fido_bio_enroll_t) -> u8;
+}
+extern "C" {
+    pub fn fido_bio_enroll_remaining_samples(arg1: *const fido_bio_enroll_t) -> u8;
+}
+extern "C" {
+    pub fn fido_bio_info_max_samples(arg1: *const fido_bio_info_t) -> u8;
+}
+extern "C" {
+    pub fn fido_bio_info_type(arg1: *const fido_bio_info_t) -> u8;
+}
+extern "C" {
+    pub fn fido_bio_enroll_free(arg1: *mut *mut fido_bio_enroll_t);
+}
+extern "C" {
+    pub fn fido_bio_info_free(arg1: *mut *mut fido_bio_info_t);
+}
+extern "C" {
+    pub fn fido_bio_template_array_free(arg1: *mut *mut fido_bio_template_array_t);
+}
+extern "C" {
+    pub fn fido_bio_template_free(arg1: *mut *mut fido_bio_template_t);
+}
+extern "C" {
+    pub fn fido_dev_enable_entattest(
+        arg1: *mut fido_dev_t,
+        arg2: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_dev_force_pin_change(
+        arg1: *mut fido_dev_t,
+        arg2: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_dev_toggle_always_uv(
+        arg1: *mut fido_dev_t,
+        arg2: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_dev_set_pin_minlen(
+        arg1: *mut fido_dev_t,
+        arg2: usize,
+        arg3: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_dev_set_pin_minlen_rpid(
+        arg1: *mut fido_dev_t,
+        arg2: *const *const ::std::os::raw::c_char,
+        arg3: usize,
+        arg4: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_cred_set_prot(
+        arg1: *mut fido_cred_t,
+        arg2: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_cred_prot(arg1: *const fido_cred_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_cred_authdata_raw_ptr(arg1: *const fido_cred_t) -> *const ::std::os::raw::c_uchar;
+}
+extern "C" {
+    pub fn fido_cred_authdata_raw_len(arg1: *const fido_cred_t) -> usize;
+}
+extern "C" {
+    pub fn fido_dev_supports_cred_prot(arg1: *const fido_dev_t) -> bool;
+}
+extern "C" {
+    pub fn fido_cred_largeblob_key_ptr(arg1: *const fido_cred_t) -> *const ::std::os::raw::c_uchar;
+}
+extern "C" {
+    pub fn fido_cred_largeblob_key_len(arg1: *const fido_cred_t) -> usize;
+}
+extern "C" {
+    pub fn fido_assert_largeblob_key_ptr(
+        arg1: *const fido_assert_t,
+        arg2: usize,
+    ) -> *const ::std::os::raw::c_uchar;
+}
+extern "C" {
+    pub fn fido_assert_largeblob_key_len(arg1: *const fido_assert_t, arg2: usize) -> usize;
+}
+extern "C" {
+    pub fn fido_dev_largeblob_get(
+        arg1: *mut fido_dev_t,
+        arg2: *const ::std::os::raw::c_uchar,
+        arg3: usize,
+        arg4: *mut *mut ::std::os::raw::c_uchar,
+        arg5: *mut usize,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_dev_largeblob_set(
+        arg1: *mut fido_dev_t,
+        arg2: *const ::std::os::raw::c_uchar,
+        arg3: usize,
+        arg4: *const ::std::os::raw::c_uchar,
+        arg5: usize,
+        arg6: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_dev_largeblob_remove(
+        arg1: *mut fido_dev_t,
+        arg2: *const ::std::os::raw::c_uchar,
+        arg3: usize,
+        arg4: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_dev_largeblob_get_array(
+        arg1: *mut fido_dev_t,
+        arg2: *mut *mut ::std::os::raw::c_uchar,
+        arg3: *mut usize,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_dev_largeblob_set_array(
+        arg1: *mut fido_dev_t,
+        arg2: *const ::std::os::raw::c_uchar,
+        arg3: usize,
+        arg4: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_cred_set_blob(
+        arg1: *mut fido_cred_t,
+        arg2: *const ::std::os::raw::c_uchar,
+        arg3: usize,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_assert_blob_ptr(
+        arg1: *const fido_assert_t,
+        arg2: usize,
+    ) -> *const ::std::os::raw::c_uchar;
+}
+extern "C" {
+    pub fn fido_assert_blob_len(arg1: *const fido_assert_t, arg2: usize) -> usize;
+}
+extern "C" {
+    pub fn fido_cbor_info_maxcredbloblen(arg1: *const fido_cbor_info_t) -> u64;
+}
+extern "C" {
+    pub fn fido_cred_set_pin_minlen(arg1: *mut fido_cred_t, arg2: usize) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_cred_set_authdata_raw(
+        arg1: *mut fido_cred_t,
+        arg2: *const ::std::os::raw::c_uchar,
+        arg3: usize,
+    ) -> ::std::os::raw::c_int;
+}
@@ -0,0 +1,16 @@
+// Declarations which libfido2 1.14 added to fido.h and fido/param.h, in the form bindgen generates
+// them with the second command in bindgen.txt. Only compiled if the build script detects 1.14.
+
+use crate::fido_cred_t;
+
+pub const FIDO_ENTATTEST_VENDOR: u32 = 1;
+pub const FIDO_ENTATTEST_PLATFORM: u32 = 2;
+extern "C" {
+    pub fn fido_cred_set_entattest(
+        arg1: *mut fido_cred_t,
+        arg2: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn fido_cred_entattest(arg1: *const fido_cred_t) -> bool;
+}
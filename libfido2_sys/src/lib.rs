@@ -3,3 +3,8 @@
 
 mod bindings;
 pub use bindings::*;
+
+#[cfg(fido2_1_14)]
+mod bindings_1_14;
+#[cfg(fido2_1_14)]
+pub use bindings_1_14::*;
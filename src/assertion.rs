@@ -1,7 +1,9 @@
-use crate::{ffi::NonNull, FidoError, PublicKey, Result, FIDO_OK};
+use crate::{
+    ffi::NonNull, AuthenticatorData, AuthenticatorDataError, FidoError, PublicKey, Result, FIDO_OK,
+};
 use bitflags::bitflags;
 use libfido2_sys::*;
-use std::{ffi::CStr, os::raw, slice};
+use std::{ffi::CStr, os::raw, slice, time::Duration};
 
 // Raw assertion is initialized with NULL data
 // Only expose this type when it is properly initialized (returned from device)
@@ -10,17 +12,26 @@ pub struct Assertion {
 }
 
 // Wrapper type to safely initialize the assertion with enough information to pass to a device
-pub struct AssertionCreator(Assertion);
+pub struct AssertionCreator {
+    assertion: Assertion,
+    pub(crate) timeout: Option<Duration>,
+}
 
 /// Required information to verify an [`Assertion`] from a `Device`.
 ///
+/// # Remarks
+/// - `timeout`: overrides the [timeout] of the device for this request, if set.
+///
 /// [`Assertion`]: struct.Assertion.html
+/// [timeout]: struct.Device.html#method.set_timeout
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct AssertionCreationData<'a> {
     pub allowed_credential_ids: Option<&'a [&'a [u8]]>,
     pub client_data_hash: &'a [u8],
     pub relying_party_id: &'a CStr,
     pub options: AssertionOptions,
+    pub extensions: AssertionExtensions,
+    pub timeout: Option<Duration>,
 }
 
 impl<'a> AssertionCreationData<'a> {
@@ -38,6 +49,8 @@ impl<'a> AssertionCreationData<'a> {
             client_data_hash,
             relying_party_id,
             options: AssertionOptions::empty(),
+            extensions: AssertionExtensions::empty(),
+            timeout: None,
         }
     }
 }
@@ -55,6 +68,19 @@ pub struct Statement<'a> {
     pub user_name: Option<&'a CStr>,
     pub user_display_name: Option<&'a CStr>,
     pub user_image_uri: Option<&'a CStr>,
+    pub large_blob_key: Option<&'a [u8]>,
+    pub credential_blob: Option<&'a [u8]>,
+}
+
+impl<'a> Statement<'a> {
+    /// Parses the contained authenticator data as [`AuthenticatorData`].
+    ///
+    /// [`AuthenticatorData`]: struct.AuthenticatorData.html
+    pub fn authenticator_data(
+        &self,
+    ) -> std::result::Result<AuthenticatorData<'a>, AuthenticatorDataError> {
+        AuthenticatorData::parse_cbor(self.auth_data)
+    }
 }
 
 impl AssertionCreator {
@@ -69,20 +95,24 @@ impl AssertionCreator {
             }
         }
         assertion.set_options(data.options)?;
-        Ok(AssertionCreator(assertion))
+        assertion.set_extensions(data.extensions)?;
+        Ok(AssertionCreator {
+            assertion,
+            timeout: data.timeout,
+        })
     }
 
     pub(crate) fn raw(&self) -> &NonNull<fido_assert> {
-        &self.0.raw
+        &self.assertion.raw
     }
 
     pub(crate) fn raw_mut(&mut self) -> &mut NonNull<fido_assert> {
-        &mut self.0.raw
+        &mut self.assertion.raw
     }
 
     /// NB. Only call this after the assertion was returned from a device, or it will cause panics
     pub(crate) fn into_inner(self) -> Assertion {
-        self.0
+        self.assertion
     }
 }
 
@@ -130,6 +160,14 @@ impl Assertion {
                 .as_ref()
                 .map(|ptr| CStr::from_ptr(ptr));
 
+            let large_blob_key = fido_assert_largeblob_key_ptr(assertion, i)
+                .as_ref()
+                .map(|ptr| slice::from_raw_parts(ptr, fido_assert_largeblob_key_len(assertion, i)));
+
+            let credential_blob = fido_assert_blob_ptr(assertion, i)
+                .as_ref()
+                .map(|ptr| slice::from_raw_parts(ptr, fido_assert_blob_len(assertion, i)));
+
             Statement {
                 auth_data,
                 client_data_hash,
@@ -139,6 +177,8 @@ impl Assertion {
                 user_name,
                 user_display_name,
                 user_image_uri,
+                large_blob_key,
+                credential_blob,
             }
         })
     }
@@ -177,6 +217,11 @@ impl Assertion {
         unsafe { fido_assert_count(self.raw.as_ptr()) }
     }
 
+    /// Returns whether this assertion contains no statements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     /*
         Private FFI setters
     */
@@ -240,6 +285,15 @@ impl Assertion {
         }
     }
 
+    fn set_extensions(&mut self, extensions: AssertionExtensions) -> Result<()> {
+        unsafe {
+            match fido_assert_set_extensions(self.raw.as_ptr_mut(), extensions.bits()) {
+                FIDO_OK => Ok(()),
+                err => Err(FidoError(err)),
+            }
+        }
+    }
+
     fn set_hmac_salt(&mut self, hmac_salt: &[u8]) -> Result<()> {
         unsafe {
             match fido_assert_set_hmac_salt(
@@ -305,3 +359,15 @@ bitflags! {
         const USER_VERIFICATION = 2;
     }
 }
+
+bitflags! {
+    /// Extension flags for an [`Assertion`].
+    ///
+    /// [`Assertion`]: struct.Assertion.html
+    pub struct AssertionExtensions: raw::c_int {
+        /// Requests the key to encrypt the large blob of the credential with.
+        const LARGE_BLOB_KEY = FIDO_EXT_LARGEBLOB_KEY as raw::c_int;
+        /// Requests the blob which was stored with the credential through the `credBlob` extension.
+        const CREDENTIAL_BLOB = FIDO_EXT_CRED_BLOB as raw::c_int;
+    }
+}
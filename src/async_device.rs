@@ -0,0 +1,213 @@
+use crate::{
+    cbor_info::CBORData, Assertion, AssertionCreator, CancelHandle, Credential, CredentialCreator,
+    Device, FidoError, Result,
+};
+use libfido2_sys::*;
+use std::{
+    ffi::CStr,
+    os::raw,
+    panic,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+/// Asynchronous wrapper around a [`Device`], for use with the tokio runtime.
+///
+/// Requests run on tokio's blocking thread pool, so waiting for user presence does not stall the executor.
+/// libfido2 is initialized on each thread of the pool before it runs a request.
+/// Dropping a pending future cancels the request on the device, which can also be done explicitly
+/// through a [`CancelHandle`].
+///
+/// # Remarks
+/// - Requests are executed one at a time. A new request waits until the previous one has finished,
+///   which may take a moment if the previous one was just cancelled.
+/// - Dropping the future of a request which still waits for the device only removes it from the queue,
+///   and does not affect the request that is running.
+/// - Running requests are only cancelled for devices which support it, see [`CancelHandle::cancel`].
+///   Otherwise they keep the device until they finish or time out.
+///
+/// [`Device`]: struct.Device.html
+/// [`CancelHandle`]: struct.CancelHandle.html
+/// [`CancelHandle::cancel`]: struct.CancelHandle.html#method.cancel
+pub struct AsyncDevice {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    device: Mutex<Device>,
+    // Used to cancel requests without locking the device, which is held by the pending request
+    cancel: CancelHandle,
+}
+
+// State of a single request, which is shared between its future and its blocking task
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum RequestState {
+    // Waiting for the device, which is used by another request
+    Queued,
+    Started,
+    Finished,
+    Cancelled,
+}
+
+// Cancels the request when dropped, unless it has finished
+struct CancelOnDrop<'a> {
+    state: Arc<Mutex<RequestState>>,
+    cancel: &'a CancelHandle,
+}
+
+impl Drop for CancelOnDrop<'_> {
+    fn drop(&mut self) {
+        // Holding the state lock keeps the request from finishing, so the device cannot be
+        // handed to another request before it is cancelled
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        match *state {
+            // The blocking task skips the request once it gets the device
+            RequestState::Queued => *state = RequestState::Cancelled,
+            RequestState::Started => {
+                *state = RequestState::Cancelled;
+                // This can fail if the device has already answered
+                let _ = self.cancel.cancel();
+            }
+            RequestState::Finished | RequestState::Cancelled => {}
+        }
+    }
+}
+
+impl AsyncDevice {
+    /// Wraps given [`Device`].
+    ///
+    /// [`Device`]: struct.Device.html
+    pub fn new(device: Device) -> Self {
+        AsyncDevice {
+            shared: Arc::new(Shared {
+                cancel: device.cancel_handle(),
+                device: Mutex::new(device),
+            }),
+        }
+    }
+
+    /// Returns a [`CancelHandle`] which cancels the pending request of the device.
+    ///
+    /// [`CancelHandle`]: struct.CancelHandle.html
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.shared.cancel.clone()
+    }
+
+    /// Sets the maximum time requests may take.
+    ///
+    /// See [`Device::set_timeout`].
+    ///
+    /// [`Device::set_timeout`]: struct.Device.html#method.set_timeout
+    pub async fn set_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.run(move |device| device.set_timeout(timeout)).await
+    }
+
+    /// Requests the device to create a new Credential.
+    ///
+    /// See [`Device::request_credential_creation`].
+    ///
+    /// [`Device::request_credential_creation`]: struct.Device.html#method.request_credential_creation
+    pub async fn make_credential(
+        &self,
+        credential: CredentialCreator,
+        pin: Option<&CStr>,
+    ) -> Result<Credential> {
+        let pin = pin.map(CStr::to_owned);
+        self.run(move |device| device.request_credential_creation(credential, pin.as_deref()))
+            .await
+    }
+
+    /// Requests the device to verify an Assertion.
+    ///
+    /// See [`Device::request_assertion_verification`].
+    ///
+    /// [`Device::request_assertion_verification`]: struct.Device.html#method.request_assertion_verification
+    pub async fn get_assertion(
+        &self,
+        assertion: AssertionCreator,
+        pin: Option<&CStr>,
+    ) -> Result<Assertion> {
+        let pin = pin.map(CStr::to_owned);
+        self.run(move |device| device.request_assertion_verification(assertion, pin.as_deref()))
+            .await
+    }
+
+    /// Sets the PIN of the device.
+    ///
+    /// See [`Device::set_pin`].
+    ///
+    /// [`Device::set_pin`]: struct.Device.html#method.set_pin
+    pub async fn set_pin(&self, new_pin: &CStr, old_pin: Option<&CStr>) -> Result<()> {
+        let new_pin = new_pin.to_owned();
+        let old_pin = old_pin.map(CStr::to_owned);
+        self.run(move |device| device.set_pin(&new_pin, old_pin.as_deref()))
+            .await
+    }
+
+    /// Resets the device.
+    ///
+    /// See [`Device::reset`].
+    ///
+    /// [`Device::reset`]: struct.Device.html#method.reset
+    pub async fn reset(&self) -> Result<()> {
+        self.run(Device::reset).await
+    }
+
+    /// Returns the amount of PIN tries left before the device locks itself.
+    pub async fn retry_count(&self) -> Result<i32> {
+        self.run(Device::retry_count).await
+    }
+
+    /// Requests additional [data] stored as CBOR from the device.
+    ///
+    /// [data]: struct.CBORData.html
+    pub async fn cbor_data(&self) -> Result<CBORData> {
+        self.run(Device::request_cbor_data).await
+    }
+
+    /// Runs `f` on the blocking thread pool, cancelling the request if the returned future is dropped.
+    async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Device) -> Result<T> + Send + 'static,
+    {
+        let shared = self.shared.clone();
+        let state = Arc::new(Mutex::new(RequestState::Queued));
+        let guard = CancelOnDrop {
+            state: state.clone(),
+            cancel: &self.shared.cancel,
+        };
+
+        let result = tokio::task::spawn_blocking(move || {
+            crate::init_thread();
+            // A request which panicked leaves the device usable, since libfido2 calls cannot unwind
+            let mut device = shared.device.lock().unwrap_or_else(PoisonError::into_inner);
+            {
+                let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+                if *state == RequestState::Cancelled {
+                    return Err(FidoError(FIDO_ERR_KEEPALIVE_CANCEL as raw::c_int));
+                }
+                *state = RequestState::Started;
+            }
+            let result = f(&mut device);
+            // Finish before releasing the device, so it is not cancelled while the next request runs
+            *state.lock().unwrap_or_else(PoisonError::into_inner) = RequestState::Finished;
+            result
+        })
+        .await;
+        drop(guard);
+
+        match result {
+            Ok(result) => result,
+            Err(err) if err.is_panic() => panic::resume_unwind(err.into_panic()),
+            // Blocking tasks are only cancelled when the runtime shuts down
+            Err(err) => panic!("{}", err),
+        }
+    }
+}
+
+impl From<Device> for AsyncDevice {
+    fn from(device: Device) -> Self {
+        AsyncDevice::new(device)
+    }
+}
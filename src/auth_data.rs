@@ -0,0 +1,455 @@
+use crate::cbor::{self, CborValue};
+use bitflags::bitflags;
+use libfido2_sys::*;
+use std::{collections::BTreeMap, error, fmt};
+
+const RELYING_PARTY_ID_HASH_LEN: usize = 32;
+const AAGUID_LEN: usize = 16;
+
+/// Parsed authenticator data of a `Credential` or `Statement`.
+///
+/// # Remarks
+/// - `attested_credential`: only present if the `ATTESTED_CREDENTIAL_DATA` flag is set,
+///   which is the case for newly created credentials.
+/// - `extensions`: the extension outputs, keyed by extension identifier.
+///   Empty if the `EXTENSION_DATA` flag is not set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthenticatorData<'a> {
+    pub relying_party_id_hash: &'a [u8],
+    pub flags: AuthenticatorDataFlags,
+    pub sign_count: u32,
+    pub attested_credential: Option<AttestedCredentialData<'a>>,
+    pub extensions: BTreeMap<String, CborValue>,
+}
+
+/// Credential data which authenticators attest when creating a credential.
+///
+/// # Remarks
+/// - `public_key`: the public key of the credential in COSE_Key format.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AttestedCredentialData<'a> {
+    pub aag_uid: &'a [u8],
+    pub id: &'a [u8],
+    pub public_key: &'a [u8],
+}
+
+/// Error of parsing [`AuthenticatorData`].
+///
+/// [`AuthenticatorData`]: struct.AuthenticatorData.html
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AuthenticatorDataError {
+    /// The authenticator data is not wrapped in a CBOR byte string, as libfido2 stores it.
+    InvalidEncoding,
+    /// The authenticator data ends before the field with given name.
+    Truncated(&'static str),
+    /// The credential public key is not a valid CBOR map.
+    InvalidPublicKey,
+    /// The extension outputs are not a valid CBOR map with text keys.
+    InvalidExtensions,
+    /// Given amount of bytes follow the authenticator data.
+    TrailingData(usize),
+}
+
+impl error::Error for AuthenticatorDataError {}
+
+impl fmt::Display for AuthenticatorDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthenticatorDataError::InvalidEncoding => {
+                f.write_str("authenticator data is not a CBOR byte string")
+            }
+            AuthenticatorDataError::Truncated(field) => {
+                write!(f, "authenticator data is truncated at the {}", field)
+            }
+            AuthenticatorDataError::InvalidPublicKey => {
+                f.write_str("credential public key is not a valid CBOR map")
+            }
+            AuthenticatorDataError::InvalidExtensions => {
+                f.write_str("extension outputs are not a valid CBOR map with text keys")
+            }
+            AuthenticatorDataError::TrailingData(len) => {
+                write!(f, "authenticator data is followed by {} bytes", len)
+            }
+        }
+    }
+}
+
+impl<'a> AuthenticatorData<'a> {
+    /// Parses raw authenticator data, as defined by WebAuthn.
+    ///
+    /// # Remarks
+    /// - Parsing is strict: every field the flags announce must be present and well-formed,
+    ///   and no data may follow the last one.
+    pub fn parse(data: &'a [u8]) -> Result<Self, AuthenticatorDataError> {
+        let mut reader = Reader(data);
+        let relying_party_id_hash =
+            reader.take(RELYING_PARTY_ID_HASH_LEN, "relying party ID hash")?;
+        let flags = AuthenticatorDataFlags::from_bits_truncate(reader.take(1, "flags")?[0]);
+        let mut sign_count = [0; 4];
+        sign_count.copy_from_slice(reader.take(4, "sign count")?);
+        let sign_count = u32::from_be_bytes(sign_count);
+
+        let attested_credential =
+            if flags.contains(AuthenticatorDataFlags::ATTESTED_CREDENTIAL_DATA) {
+                let aag_uid = reader.take(AAGUID_LEN, "AAGUID")?;
+                let id_len = reader.take(2, "credential ID length")?;
+                let id = reader.take(
+                    usize::from(u16::from_be_bytes([id_len[0], id_len[1]])),
+                    "credential ID",
+                )?;
+                let public_key = match reader.cbor(
+                    "credential public key",
+                    AuthenticatorDataError::InvalidPublicKey,
+                )? {
+                    (CborValue::Map(_), public_key) => public_key,
+                    _ => return Err(AuthenticatorDataError::InvalidPublicKey),
+                };
+                Some(AttestedCredentialData {
+                    aag_uid,
+                    id,
+                    public_key,
+                })
+            } else {
+                None
+            };
+
+        let extensions = if flags.contains(AuthenticatorDataFlags::EXTENSION_DATA) {
+            match reader.cbor("extensions", AuthenticatorDataError::InvalidExtensions)? {
+                (CborValue::Map(entries), _) => entries
+                    .into_iter()
+                    .map(|entry| match entry {
+                        (CborValue::Text(key), value) => Ok((key, value)),
+                        _ => Err(AuthenticatorDataError::InvalidExtensions),
+                    })
+                    .collect::<Result<_, _>>()?,
+                _ => return Err(AuthenticatorDataError::InvalidExtensions),
+            }
+        } else {
+            BTreeMap::new()
+        };
+
+        if !reader.0.is_empty() {
+            return Err(AuthenticatorDataError::TrailingData(reader.0.len()));
+        }
+
+        Ok(AuthenticatorData {
+            relying_party_id_hash,
+            flags,
+            sign_count,
+            attested_credential,
+            extensions,
+        })
+    }
+
+    /// Parses authenticator data which is wrapped in a CBOR byte string,
+    /// which is how credentials and statements contain it.
+    pub fn parse_cbor(data: &'a [u8]) -> Result<Self, AuthenticatorDataError> {
+        Self::parse(cbor::byte_string(data).ok_or(AuthenticatorDataError::InvalidEncoding)?)
+    }
+}
+
+/// Reads the extension outputs of raw authenticator data, or returns an empty map if it has none.
+///
+/// # Remarks
+/// - Unlike [`AuthenticatorData::parse`], this tolerates trailing data and skips extensions
+///   with keys that are not text, so one malformed part does not hide the other outputs.
+///
+/// [`AuthenticatorData::parse`]: struct.AuthenticatorData.html#method.parse
+pub(crate) fn extension_outputs(data: &[u8]) -> BTreeMap<String, CborValue> {
+    let read = || {
+        let mut reader = Reader(data);
+        reader.take(RELYING_PARTY_ID_HASH_LEN, "relying party ID hash")?;
+        let flags = AuthenticatorDataFlags::from_bits_truncate(reader.take(1, "flags")?[0]);
+        reader.take(4, "sign count")?;
+        if !flags.contains(AuthenticatorDataFlags::EXTENSION_DATA) {
+            return Ok(BTreeMap::new());
+        }
+        if flags.contains(AuthenticatorDataFlags::ATTESTED_CREDENTIAL_DATA) {
+            reader.take(AAGUID_LEN, "AAGUID")?;
+            let id_len = reader.take(2, "credential ID length")?;
+            reader.take(
+                usize::from(u16::from_be_bytes([id_len[0], id_len[1]])),
+                "credential ID",
+            )?;
+            reader.cbor(
+                "credential public key",
+                AuthenticatorDataError::InvalidPublicKey,
+            )?;
+        }
+        match reader.cbor("extensions", AuthenticatorDataError::InvalidExtensions)? {
+            (CborValue::Map(entries), _) => Ok(entries
+                .into_iter()
+                .filter_map(|entry| match entry {
+                    (CborValue::Text(key), value) => Some((key, value)),
+                    _ => None,
+                })
+                .collect()),
+            _ => Err(AuthenticatorDataError::InvalidExtensions),
+        }
+    };
+    read().unwrap_or_default()
+}
+
+// Cursor which reports the field that is missing
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(
+        &mut self,
+        len: usize,
+        field: &'static str,
+    ) -> Result<&'a [u8], AuthenticatorDataError> {
+        if self.0.len() < len {
+            return Err(AuthenticatorDataError::Truncated(field));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    // Decodes one CBOR data item, and returns it together with its encoding
+    fn cbor(
+        &mut self,
+        field: &'static str,
+        invalid: AuthenticatorDataError,
+    ) -> Result<(CborValue, &'a [u8]), AuthenticatorDataError> {
+        if self.0.is_empty() {
+            return Err(AuthenticatorDataError::Truncated(field));
+        }
+        let mut decoder = cbor::Decoder::new(self.0);
+        let value = decoder.decode().ok_or(invalid)?;
+        let (encoded, rest) = self.0.split_at(decoder.position());
+        self.0 = rest;
+        Ok((value, encoded))
+    }
+}
+
+bitflags! {
+    /// Flags of [`AuthenticatorData`].
+    ///
+    /// [`AuthenticatorData`]: struct.AuthenticatorData.html
+    pub struct AuthenticatorDataFlags: u8 {
+        /// The user was present.
+        const USER_PRESENT = CTAP_AUTHDATA_USER_PRESENT as u8;
+        /// The user was verified, for example with a PIN.
+        const USER_VERIFIED = CTAP_AUTHDATA_USER_VERIFIED as u8;
+        /// The credential may be backed up, for example synchronized across devices.
+        const BACKUP_ELIGIBLE = 0x08;
+        /// The credential is currently backed up.
+        const BACKUP_STATE = 0x10;
+        /// Attested credential data is included.
+        const ATTESTED_CREDENTIAL_DATA = CTAP_AUTHDATA_ATT_CRED as u8;
+        /// Extension outputs are included.
+        const EXTENSION_DATA = CTAP_AUTHDATA_EXT_DATA as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: [u8; 3] = [0xc1, 0xc2, 0xc3];
+
+    fn public_key() -> Vec<u8> {
+        CborValue::Map(vec![
+            (CborValue::Integer(1), CborValue::Integer(2)),
+            (CborValue::Integer(3), CborValue::Integer(-7)),
+        ])
+        .encode()
+    }
+
+    fn extensions() -> Vec<u8> {
+        CborValue::Map(vec![
+            (
+                CborValue::Text("credProtect".to_owned()),
+                CborValue::Integer(2),
+            ),
+            (
+                CborValue::Text("hmac-secret".to_owned()),
+                CborValue::Bool(true),
+            ),
+        ])
+        .encode()
+    }
+
+    // Builds authenticator data, and sets the flags for the given parts
+    fn auth_data(public_key: Option<&[u8]>, extensions: Option<&[u8]>) -> Vec<u8> {
+        let mut flags = AuthenticatorDataFlags::USER_PRESENT;
+        flags.set(
+            AuthenticatorDataFlags::ATTESTED_CREDENTIAL_DATA,
+            public_key.is_some(),
+        );
+        flags.set(AuthenticatorDataFlags::EXTENSION_DATA, extensions.is_some());
+
+        let mut data = vec![0xaa; RELYING_PARTY_ID_HASH_LEN];
+        data.push(flags.bits());
+        data.extend_from_slice(&42u32.to_be_bytes());
+        if let Some(public_key) = public_key {
+            data.extend_from_slice(&[0xbb; AAGUID_LEN]);
+            data.extend_from_slice(&(ID.len() as u16).to_be_bytes());
+            data.extend_from_slice(&ID);
+            data.extend_from_slice(public_key);
+        }
+        if let Some(extensions) = extensions {
+            data.extend_from_slice(extensions);
+        }
+        data
+    }
+
+    #[test]
+    fn parses_every_field() {
+        let public_key = public_key();
+        let data = auth_data(Some(&public_key), Some(&extensions()));
+        let auth_data = AuthenticatorData::parse(&data).unwrap();
+        assert_eq!(auth_data.relying_party_id_hash, &[0xaa; 32][..]);
+        assert_eq!(
+            auth_data.flags,
+            AuthenticatorDataFlags::USER_PRESENT
+                | AuthenticatorDataFlags::ATTESTED_CREDENTIAL_DATA
+                | AuthenticatorDataFlags::EXTENSION_DATA
+        );
+        assert_eq!(auth_data.sign_count, 42);
+        assert_eq!(
+            auth_data.attested_credential,
+            Some(AttestedCredentialData {
+                aag_uid: &[0xbb; 16],
+                id: &ID,
+                public_key: &public_key,
+            })
+        );
+        assert_eq!(
+            auth_data.extensions.get("credProtect"),
+            Some(&CborValue::Integer(2))
+        );
+        assert_eq!(
+            auth_data.extensions.get("hmac-secret"),
+            Some(&CborValue::Bool(true))
+        );
+    }
+
+    #[test]
+    fn parses_assertion_data() {
+        let data = auth_data(None, None);
+        let auth_data = AuthenticatorData::parse(&data).unwrap();
+        assert_eq!(auth_data.flags, AuthenticatorDataFlags::USER_PRESENT);
+        assert_eq!(auth_data.attested_credential, None);
+        assert!(auth_data.extensions.is_empty());
+    }
+
+    #[test]
+    fn reports_truncated_fields() {
+        let public_key = public_key();
+        let extensions = extensions();
+        let data = auth_data(Some(&public_key), Some(&extensions));
+        let key_start = 32 + 1 + 4 + 16 + 2 + ID.len();
+        let extensions_start = key_start + public_key.len();
+
+        let expected = |len: usize| match len {
+            0..=31 => AuthenticatorDataError::Truncated("relying party ID hash"),
+            32 => AuthenticatorDataError::Truncated("flags"),
+            33..=36 => AuthenticatorDataError::Truncated("sign count"),
+            37..=52 => AuthenticatorDataError::Truncated("AAGUID"),
+            53..=54 => AuthenticatorDataError::Truncated("credential ID length"),
+            _ if len < key_start => AuthenticatorDataError::Truncated("credential ID"),
+            _ if len == key_start => AuthenticatorDataError::Truncated("credential public key"),
+            _ if len < extensions_start => AuthenticatorDataError::InvalidPublicKey,
+            _ if len == extensions_start => AuthenticatorDataError::Truncated("extensions"),
+            _ => AuthenticatorDataError::InvalidExtensions,
+        };
+        for len in 0..data.len() {
+            assert_eq!(
+                AuthenticatorData::parse(&data[..len]),
+                Err(expected(len)),
+                "{}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_trailing_data() {
+        let mut data = auth_data(Some(&public_key()), None);
+        data.extend_from_slice(&[0, 0]);
+        assert_eq!(
+            AuthenticatorData::parse(&data),
+            Err(AuthenticatorDataError::TrailingData(2))
+        );
+    }
+
+    #[test]
+    fn rejects_non_map_public_keys() {
+        let public_key = CborValue::Array(vec![CborValue::Integer(1)]).encode();
+        let data = auth_data(Some(&public_key), None);
+        assert_eq!(
+            AuthenticatorData::parse(&data),
+            Err(AuthenticatorDataError::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn rejects_non_text_extension_keys() {
+        let extensions = CborValue::Map(vec![
+            (CborValue::Integer(1), CborValue::Bool(true)),
+            (
+                CborValue::Text("credBlob".to_owned()),
+                CborValue::Bool(true),
+            ),
+        ])
+        .encode();
+        let data = auth_data(None, Some(&extensions));
+        assert_eq!(
+            AuthenticatorData::parse(&data),
+            Err(AuthenticatorDataError::InvalidExtensions)
+        );
+
+        let data = auth_data(None, Some(&CborValue::Bool(true).encode()));
+        assert_eq!(
+            AuthenticatorData::parse(&data),
+            Err(AuthenticatorDataError::InvalidExtensions)
+        );
+    }
+
+    #[test]
+    fn rejects_oversized_credential_ids() {
+        let mut data = auth_data(Some(&public_key()), None);
+        data[53..55].copy_from_slice(&u16::MAX.to_be_bytes());
+        assert_eq!(
+            AuthenticatorData::parse(&data),
+            Err(AuthenticatorDataError::Truncated("credential ID"))
+        );
+    }
+
+    #[test]
+    fn unwraps_cbor_byte_strings() {
+        let data = auth_data(None, Some(&extensions()));
+        let wrapped = CborValue::Bytes(data.clone()).encode();
+        assert_eq!(
+            AuthenticatorData::parse_cbor(&wrapped),
+            AuthenticatorData::parse(&data)
+        );
+        assert_eq!(
+            AuthenticatorData::parse_cbor(&data),
+            Err(AuthenticatorDataError::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn reads_extension_outputs_leniently() {
+        let extensions = CborValue::Map(vec![
+            (CborValue::Integer(1), CborValue::Bool(true)),
+            (
+                CborValue::Text("credBlob".to_owned()),
+                CborValue::Bool(true),
+            ),
+        ])
+        .encode();
+        let mut data = auth_data(Some(&public_key()), Some(&extensions));
+        data.push(0);
+        let outputs = extension_outputs(&data);
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs.get("credBlob"), Some(&CborValue::Bool(true)));
+
+        assert!(extension_outputs(&auth_data(Some(&public_key()), None)).is_empty());
+        assert!(extension_outputs(&data[..40]).is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{ffi::optional_ptr, Device, DeviceMode, FidoError, Result};
+use libfido2_sys::*;
+use std::{ffi::CStr, os::raw};
+
+// Options which devices advertise in their CBOR data
+const OPTION_CONFIG: &str = "authnrCfg";
+const OPTION_ALWAYS_UV: &str = "alwaysUv";
+const OPTION_MIN_PIN_LENGTH: &str = "setMinPINLength";
+const OPTION_ENTERPRISE_ATTESTATION: &str = "ep";
+
+impl Device {
+    /// Toggles whether the device requires user verification for every request.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Configuration requires the PIN, if one is set.
+    /// - Requires the `alwaysUv` option of the [CBOR data], whose value is the current setting,
+    ///   and fails with `FIDO_ERR_UNSUPPORTED_OPTION` without it.
+    ///
+    /// [CBOR data]: struct.CBORData.html
+    pub fn toggle_always_uv(&mut self, pin: Option<&CStr>) -> Result<()> {
+        self.require_option(OPTION_ALWAYS_UV, false)?;
+        unsafe {
+            self.request(None, |device| {
+                fido_dev_toggle_always_uv(device, optional_ptr(pin))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Sets the minimum length of the PIN.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Configuration requires the PIN, if one is set.
+    /// - Requires the `setMinPINLength` option of the [CBOR data],
+    ///   and fails with `FIDO_ERR_UNSUPPORTED_OPTION` without it.
+    /// - The minimum length can only be increased. If the current PIN is shorter,
+    ///   the device requires it to be changed before it can be used again.
+    ///
+    /// [CBOR data]: struct.CBORData.html
+    pub fn set_min_pin_length(&mut self, length: usize, pin: Option<&CStr>) -> Result<()> {
+        self.require_option(OPTION_MIN_PIN_LENGTH, true)?;
+        unsafe {
+            self.request(None, |device| {
+                fido_dev_set_pin_minlen(device, length, optional_ptr(pin))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Requires the PIN to be changed before it can be used again.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Configuration requires the PIN, if one is set.
+    /// - Requires the `setMinPINLength` option of the [CBOR data],
+    ///   and fails with `FIDO_ERR_UNSUPPORTED_OPTION` without it.
+    ///
+    /// [CBOR data]: struct.CBORData.html
+    pub fn force_pin_change(&mut self, pin: Option<&CStr>) -> Result<()> {
+        self.require_option(OPTION_MIN_PIN_LENGTH, true)?;
+        unsafe {
+            self.request(None, |device| {
+                fido_dev_force_pin_change(device, optional_ptr(pin))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Sets the relying parties which may read the minimum PIN length,
+    /// through the `minPinLength` extension.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Configuration requires the PIN, if one is set.
+    /// - Requires the `setMinPINLength` option of the [CBOR data],
+    ///   and fails with `FIDO_ERR_UNSUPPORTED_OPTION` without it.
+    /// - Devices may limit the amount of relying parties, and fail with `FIDO_ERR_KEY_STORE_FULL`.
+    ///
+    /// [CBOR data]: struct.CBORData.html
+    pub fn set_min_pin_length_relying_parties(
+        &mut self,
+        relying_party_ids: &[&CStr],
+        pin: Option<&CStr>,
+    ) -> Result<()> {
+        self.require_option(OPTION_MIN_PIN_LENGTH, true)?;
+        let relying_party_ids = relying_party_ids
+            .iter()
+            .map(|id| id.as_ptr())
+            .collect::<Vec<*const raw::c_char>>();
+        unsafe {
+            self.request(None, |device| {
+                fido_dev_set_pin_minlen_rpid(
+                    device,
+                    relying_party_ids.as_ptr(),
+                    relying_party_ids.len(),
+                    optional_ptr(pin),
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Enables enterprise attestation, which credentials can request on creation.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Configuration requires the PIN, if one is set.
+    /// - Requires the `ep` option of the [CBOR data], whose value is whether it is enabled,
+    ///   and fails with `FIDO_ERR_UNSUPPORTED_OPTION` without it.
+    ///
+    /// [CBOR data]: struct.CBORData.html
+    pub fn enable_enterprise_attestation(&mut self, pin: Option<&CStr>) -> Result<()> {
+        self.require_option(OPTION_ENTERPRISE_ATTESTATION, false)?;
+        unsafe {
+            self.request(None, |device| {
+                fido_dev_enable_entattest(device, optional_ptr(pin))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the device supports configuration and advertises given option,
+    /// and fails with `FIDO_ERR_UNSUPPORTED_OPTION` otherwise.
+    ///
+    /// # Arguments
+    /// - `enabled`: Whether the option has to be enabled, rather than only present.
+    fn require_option(&mut self, option: &str, enabled: bool) -> Result<()> {
+        let unsupported = FidoError(FIDO_ERR_UNSUPPORTED_OPTION as raw::c_int);
+        // U2F devices do not have CBOR data at all
+        if self.mode() == DeviceMode::FidoU2F {
+            return Err(unsupported);
+        }
+        let cbor_data = self.request_cbor_data()?;
+        let options = cbor_data.as_ref().options;
+        if options.get(OPTION_CONFIG) != Some(&true) {
+            return Err(unsupported);
+        }
+        match options.get(option) {
+            Some(&value) if value || !enabled => Ok(()),
+            _ => Err(unsupported),
+        }
+    }
+}
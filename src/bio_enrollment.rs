@@ -0,0 +1,338 @@
+use crate::{
+    ffi::{bytes, string, Owned},
+    Device, FidoError, Result, FIDO_OK,
+};
+use libfido2_sys::*;
+use std::{convert::TryFrom, ffi::CStr, os::raw, slice, time::Duration};
+
+/// The fingerprint sensor of a biometric device, returned by [`Device::bio_sensor_info`].
+///
+/// [`Device::bio_sensor_info`]: struct.Device.html#method.bio_sensor_info
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BioSensorInfo {
+    /// The kind of sensor, which is `1` for touch sensors and `2` for swipe sensors.
+    pub sensor_type: u8,
+    /// The amount of good samples required to enroll a fingerprint.
+    pub max_samples: u8,
+}
+
+/// A fingerprint enrolled on a device.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BioTemplate {
+    pub id: Vec<u8>,
+    /// The friendly name of the fingerprint, if it has one.
+    pub name: Option<String>,
+}
+
+/// Feedback of a device about the last fingerprint sample taken during an enrollment.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BioSample {
+    pub status: BioSampleStatus,
+    /// The amount of good samples still required to complete the enrollment.
+    pub remaining_samples: u8,
+}
+
+/// Quality of a fingerprint sample, as reported by the device.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BioSampleStatus {
+    Good,
+    TooHigh,
+    TooLow,
+    TooLeft,
+    TooRight,
+    TooFast,
+    TooSlow,
+    PoorQuality,
+    TooSkewed,
+    TooShort,
+    MergeFailure,
+    /// The fingerprint is already enrolled.
+    Exists,
+    /// The device cannot store more fingerprints.
+    DatabaseFull,
+    /// The user did not touch the sensor in time.
+    NoUserActivity,
+    /// The user did not lift their finger between samples.
+    NoUserPresenceTransition,
+    /// A status which is not part of the specification.
+    Other(u8),
+}
+
+impl BioSampleStatus {
+    pub(crate) fn from_ffi(status: u8) -> Self {
+        match u32::from(status) {
+            FIDO_BIO_ENROLL_FP_GOOD => BioSampleStatus::Good,
+            FIDO_BIO_ENROLL_FP_TOO_HIGH => BioSampleStatus::TooHigh,
+            FIDO_BIO_ENROLL_FP_TOO_LOW => BioSampleStatus::TooLow,
+            FIDO_BIO_ENROLL_FP_TOO_LEFT => BioSampleStatus::TooLeft,
+            FIDO_BIO_ENROLL_FP_TOO_RIGHT => BioSampleStatus::TooRight,
+            FIDO_BIO_ENROLL_FP_TOO_FAST => BioSampleStatus::TooFast,
+            FIDO_BIO_ENROLL_FP_TOO_SLOW => BioSampleStatus::TooSlow,
+            FIDO_BIO_ENROLL_FP_POOR_QUALITY => BioSampleStatus::PoorQuality,
+            FIDO_BIO_ENROLL_FP_TOO_SKEWED => BioSampleStatus::TooSkewed,
+            FIDO_BIO_ENROLL_FP_TOO_SHORT => BioSampleStatus::TooShort,
+            FIDO_BIO_ENROLL_FP_MERGE_FAILURE => BioSampleStatus::MergeFailure,
+            FIDO_BIO_ENROLL_FP_EXISTS => BioSampleStatus::Exists,
+            FIDO_BIO_ENROLL_FP_DATABASE_FULL => BioSampleStatus::DatabaseFull,
+            FIDO_BIO_ENROLL_NO_USER_ACTIVITY => BioSampleStatus::NoUserActivity,
+            FIDO_BIO_ENROLL_NO_USER_PRESENCE_TRANSITION => {
+                BioSampleStatus::NoUserPresenceTransition
+            }
+            _ => BioSampleStatus::Other(status),
+        }
+    }
+}
+
+/// A fingerprint enrollment in progress, started by [`Device::begin_bio_enrollment`].
+///
+/// [`Device::begin_bio_enrollment`]: struct.Device.html#method.begin_bio_enrollment
+pub struct BioEnrollment {
+    template: Owned<fido_bio_template>,
+    enroll: Owned<fido_bio_enroll>,
+}
+
+// libfido2_sys guarantees this.
+unsafe impl Send for BioEnrollment {}
+unsafe impl Sync for BioEnrollment {}
+
+impl BioEnrollment {
+    /// Returns the ID the device assigned to the fingerprint being enrolled.
+    pub fn template_id(&self) -> &[u8] {
+        unsafe {
+            let template = self.template.as_ptr();
+            fido_bio_template_id_ptr(template)
+                .as_ref()
+                .map(|ptr| slice::from_raw_parts(ptr, fido_bio_template_id_len(template)))
+                .unwrap_or_default()
+        }
+    }
+
+    /// Returns the feedback about the last sample.
+    pub fn last_sample(&self) -> BioSample {
+        unsafe {
+            let enroll = self.enroll.as_ptr();
+            BioSample {
+                status: BioSampleStatus::from_ffi(fido_bio_enroll_last_status(enroll)),
+                remaining_samples: fido_bio_enroll_remaining_samples(enroll),
+            }
+        }
+    }
+
+    /// Returns whether enough good samples were taken to complete the enrollment.
+    pub fn is_complete(&self) -> bool {
+        self.last_sample().remaining_samples == 0
+    }
+}
+
+impl Device {
+    /// Requests information about the fingerprint sensor of the device.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Devices without biometric enrollment fail with `FIDO_ERR_INVALID_COMMAND`.
+    pub fn bio_sensor_info(&mut self) -> Result<BioSensorInfo> {
+        unsafe {
+            let mut info = Owned::new(fido_bio_info_new(), fido_bio_info_free);
+            self.request(None, |device| {
+                fido_bio_dev_get_info(device, info.as_ptr_mut())
+            })?;
+            Ok(BioSensorInfo {
+                sensor_type: fido_bio_info_type(info.as_ptr()),
+                max_samples: fido_bio_info_max_samples(info.as_ptr()),
+            })
+        }
+    }
+
+    /// Requests the fingerprints enrolled on the device.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Returns an empty list if no fingerprints are enrolled.
+    pub fn bio_templates(&mut self, pin: &CStr) -> Result<Vec<BioTemplate>> {
+        unsafe {
+            let mut templates =
+                Owned::new(fido_bio_template_array_new(), fido_bio_template_array_free);
+            match self.request(None, |device| {
+                fido_bio_dev_get_template_array(device, templates.as_ptr_mut(), pin.as_ptr())
+            }) {
+                Ok(()) => {}
+                // Devices report that no fingerprints are enrolled as an error
+                Err(FidoError(err)) if err == FIDO_ERR_INVALID_OPTION as raw::c_int => {
+                    return Ok(Vec::new())
+                }
+                Err(err) => return Err(err),
+            }
+
+            let templates = templates.as_ptr();
+            Ok((0..fido_bio_template_array_count(templates))
+                .map(|i| {
+                    let template = fido_bio_template(templates, i);
+                    BioTemplate {
+                        id: bytes(
+                            fido_bio_template_id_ptr(template),
+                            fido_bio_template_id_len(template),
+                        ),
+                        name: string(fido_bio_template_name(template)),
+                    }
+                })
+                .collect())
+        }
+    }
+
+    /// Starts enrolling a new fingerprint, and waits for the first sample.
+    ///
+    /// # Arguments
+    /// - `sample_timeout`: How long the device waits for the user to touch the sensor.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Take more samples with [`continue_bio_enrollment`] until the enrollment [is complete].
+    /// - Only one enrollment can be in progress on a device at a time.
+    ///
+    /// [`continue_bio_enrollment`]: #method.continue_bio_enrollment
+    /// [is complete]: struct.BioEnrollment.html#method.is_complete
+    pub fn begin_bio_enrollment(
+        &mut self,
+        sample_timeout: Duration,
+        pin: &CStr,
+    ) -> Result<BioEnrollment> {
+        unsafe {
+            let mut enrollment = BioEnrollment {
+                template: Owned::new(fido_bio_template_new(), fido_bio_template_free),
+                enroll: Owned::new(fido_bio_enroll_new(), fido_bio_enroll_free),
+            };
+            self.request(None, |device| {
+                fido_bio_dev_enroll_begin(
+                    device,
+                    enrollment.template.as_ptr_mut(),
+                    enrollment.enroll.as_ptr_mut(),
+                    milliseconds(sample_timeout),
+                    pin.as_ptr(),
+                )
+            })?;
+            Ok(enrollment)
+        }
+    }
+
+    /// Waits for the next sample of an enrollment, and returns the feedback about it.
+    ///
+    /// # Arguments
+    /// - `enrollment`: The enrollment started by [`begin_bio_enrollment`].
+    /// - `sample_timeout`: How long the device waits for the user to touch the sensor.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    ///
+    /// [`begin_bio_enrollment`]: #method.begin_bio_enrollment
+    pub fn continue_bio_enrollment(
+        &mut self,
+        enrollment: &mut BioEnrollment,
+        sample_timeout: Duration,
+    ) -> Result<BioSample> {
+        unsafe {
+            self.request(None, |device| {
+                fido_bio_dev_enroll_continue(
+                    device,
+                    enrollment.template.as_ptr(),
+                    enrollment.enroll.as_ptr_mut(),
+                    milliseconds(sample_timeout),
+                )
+            })?;
+            Ok(enrollment.last_sample())
+        }
+    }
+
+    /// Aborts the enrollment in progress on the device.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    pub fn cancel_bio_enrollment(&mut self) -> Result<()> {
+        unsafe { self.request(None, |device| fido_bio_dev_enroll_cancel(device)) }
+    }
+
+    /// Enrolls a new fingerprint, taking samples until the device has enough of them.
+    ///
+    /// # Arguments
+    /// - `name`: The friendly name of the fingerprint, if any.
+    /// - `sample_timeout`: How long the device waits for the user to touch the sensor.
+    /// - `feedback`: Called with the feedback about every sample, to guide the user.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - The enrollment is cancelled on the device if taking a sample fails.
+    pub fn enroll_fingerprint<F>(
+        &mut self,
+        name: Option<&CStr>,
+        sample_timeout: Duration,
+        pin: &CStr,
+        mut feedback: F,
+    ) -> Result<BioTemplate>
+    where
+        F: FnMut(BioSample),
+    {
+        let mut enrollment = self.begin_bio_enrollment(sample_timeout, pin)?;
+        feedback(enrollment.last_sample());
+        while !enrollment.is_complete() {
+            match self.continue_bio_enrollment(&mut enrollment, sample_timeout) {
+                Ok(sample) => feedback(sample),
+                Err(err) => {
+                    // The original error is more relevant than a failure to clean up
+                    let _ = self.cancel_bio_enrollment();
+                    return Err(err);
+                }
+            }
+        }
+
+        let id = enrollment.template_id().to_vec();
+        if let Some(name) = name {
+            self.rename_bio_template(&id, name, pin)?;
+        }
+        Ok(BioTemplate {
+            id,
+            name: name.map(|name| name.to_string_lossy().into_owned()),
+        })
+    }
+
+    /// Changes the friendly name of an enrolled fingerprint.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    pub fn rename_bio_template(&mut self, id: &[u8], name: &CStr, pin: &CStr) -> Result<()> {
+        unsafe {
+            let mut template = template(id)?;
+            match fido_bio_template_set_name(template.as_ptr_mut(), name.as_ptr()) {
+                FIDO_OK => {}
+                err => return Err(FidoError(err)),
+            }
+            self.request(None, |device| {
+                fido_bio_dev_set_template_name(device, template.as_ptr(), pin.as_ptr())
+            })
+        }
+    }
+
+    /// Deletes an enrolled fingerprint.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    pub fn delete_bio_template(&mut self, id: &[u8], pin: &CStr) -> Result<()> {
+        unsafe {
+            let template = template(id)?;
+            self.request(None, |device| {
+                fido_bio_dev_enroll_remove(device, template.as_ptr(), pin.as_ptr())
+            })
+        }
+    }
+}
+
+/// Allocates a template with given ID.
+unsafe fn template(id: &[u8]) -> Result<Owned<fido_bio_template>> {
+    let mut template = Owned::new(fido_bio_template_new(), fido_bio_template_free);
+    match fido_bio_template_set_id(template.as_ptr_mut(), id.as_ptr(), id.len()) {
+        FIDO_OK => Ok(template),
+        err => Err(FidoError(err)),
+    }
+}
+
+fn milliseconds(timeout: Duration) -> u32 {
+    u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX)
+}
@@ -0,0 +1,105 @@
+use crate::{Device, FidoError, Result};
+use std::{error, fmt, sync::mpsc, thread};
+
+/// Outcome of a request which was broadcast to multiple devices,
+/// returned by [`Fido::broadcast_credential_creation`] and [`Fido::broadcast_assertion_verification`].
+///
+/// [`Fido::broadcast_credential_creation`]: struct.Fido.html#method.broadcast_credential_creation
+/// [`Fido::broadcast_assertion_verification`]: struct.Fido.html#method.broadcast_assertion_verification
+pub struct Broadcast<T> {
+    /// The device which completed the request first.
+    pub device: Device,
+    /// Index of `device` among the devices the request was broadcast to.
+    pub index: usize,
+    /// The result of the request.
+    pub value: T,
+    /// Errors of devices which failed before the request was completed, with their index.
+    pub errors: Vec<(usize, FidoError)>,
+}
+
+/// Error of a request which was broadcast to multiple devices, if none of them completed it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BroadcastError {
+    /// Errors of every device, with their index among the devices the request was broadcast to.
+    pub errors: Vec<(usize, FidoError)>,
+}
+
+impl error::Error for BroadcastError {}
+
+impl fmt::Display for BroadcastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.errors.is_empty() {
+            return f.write_str("no devices to broadcast to");
+        }
+        f.write_str("all devices failed: ")?;
+        for (i, (index, err)) in self.errors.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}: {}", index, err)?;
+        }
+        Ok(())
+    }
+}
+
+/// Performs a request on every device in parallel, and returns the first successful result.
+/// The requests on the other devices are cancelled.
+/// Each device runs on its own thread, which initializes libfido2 first.
+pub(crate) fn broadcast<R, T, F>(
+    requests: Vec<(Device, Result<R>)>,
+    request: F,
+) -> std::result::Result<Broadcast<T>, BroadcastError>
+where
+    R: Send + 'static,
+    T: Send + 'static,
+    F: Fn(&mut Device, R) -> Result<T> + Clone + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    let mut errors = Vec::new();
+    let mut cancel_handles = Vec::with_capacity(requests.len());
+
+    for (index, (mut device, data)) in requests.into_iter().enumerate() {
+        cancel_handles.push(device.cancel_handle());
+        let data = match data {
+            Ok(data) => data,
+            Err(err) => {
+                errors.push((index, err));
+                continue;
+            }
+        };
+
+        let sender = sender.clone();
+        let request = request.clone();
+        thread::spawn(move || {
+            crate::init_thread();
+            let result = request(&mut device, data);
+            // The receiver is gone if another device completed the request first
+            let _ = sender.send((index, device, result));
+        });
+    }
+    drop(sender);
+
+    for (index, device, result) in receiver {
+        match result {
+            Ok(value) => {
+                for (i, cancel) in cancel_handles.iter().enumerate() {
+                    if i != index {
+                        // Devices which are already done ignore this
+                        let _ = cancel.cancel();
+                    }
+                }
+                errors.sort_by_key(|&(index, _)| index);
+                return Ok(Broadcast {
+                    device,
+                    index,
+                    value,
+                    errors,
+                });
+            }
+            Err(err) => errors.push((index, err)),
+        }
+    }
+
+    errors.sort_by_key(|&(index, _)| index);
+    Err(BroadcastError { errors })
+}
@@ -0,0 +1,393 @@
+use std::convert::TryFrom;
+
+/// A decoded CBOR data item.
+///
+/// # Remarks
+/// - Only definite lengths are supported, as CTAP2 requires canonical CBOR.
+/// - Map entries are kept in encoded order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CborValue {
+    Integer(i128),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<CborValue>),
+    Map(Vec<(CborValue, CborValue)>),
+    Tag(u64, Box<CborValue>),
+    Bool(bool),
+    Null,
+    Undefined,
+    Float(f64),
+}
+
+// Maximum nesting of arrays, maps and tags
+const MAX_DEPTH: usize = 16;
+
+impl CborValue {
+    /// Returns the value stored under given text key, if this is a map.
+    pub fn get(&self, key: &str) -> Option<&CborValue> {
+        match self {
+            CborValue::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| matches!(k, CborValue::Text(text) if text == key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value, if this is an integer.
+    pub fn as_integer(&self) -> Option<i128> {
+        match self {
+            CborValue::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Returns the value stored under given integer key, if this is a map.
+    pub(crate) fn get_label(&self, label: i128) -> Option<&CborValue> {
+        match self {
+            CborValue::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| *k == CborValue::Integer(label))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Encodes this value, keeping map entries in their current order.
+    ///
+    /// # Panics
+    /// - If an integer does not fit in 64 bits, which CBOR can not encode.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        self.encode_into(&mut encoded);
+        encoded
+    }
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            CborValue::Integer(i) if *i >= 0 => head(0, integer_argument(*i), out),
+            CborValue::Integer(i) => head(1, integer_argument(-1 - *i), out),
+            CborValue::Bytes(bytes) => {
+                head(2, bytes.len() as u64, out);
+                out.extend_from_slice(bytes);
+            }
+            CborValue::Text(text) => {
+                head(3, text.len() as u64, out);
+                out.extend_from_slice(text.as_bytes());
+            }
+            CborValue::Array(items) => {
+                head(4, items.len() as u64, out);
+                items.iter().for_each(|item| item.encode_into(out));
+            }
+            CborValue::Map(entries) => {
+                head(5, entries.len() as u64, out);
+                for (key, value) in entries {
+                    key.encode_into(out);
+                    value.encode_into(out);
+                }
+            }
+            CborValue::Tag(tag, value) => {
+                head(6, *tag, out);
+                value.encode_into(out);
+            }
+            CborValue::Bool(false) => out.push(0xf4),
+            CborValue::Bool(true) => out.push(0xf5),
+            CborValue::Null => out.push(0xf6),
+            CborValue::Undefined => out.push(0xf7),
+            CborValue::Float(float) => {
+                out.push(0xfb);
+                out.extend_from_slice(&float.to_be_bytes());
+            }
+        }
+    }
+}
+
+fn integer_argument(argument: i128) -> u64 {
+    u64::try_from(argument).expect("integer does not fit in CBOR")
+}
+
+// Writes the initial byte and argument of a data item in its shortest form
+fn head(major: u8, argument: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+    match argument {
+        0..=23 => out.push(major | argument as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, argument as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(argument as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(argument as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&argument.to_be_bytes());
+        }
+    }
+}
+
+/// Reads consecutive CBOR data items from a byte slice.
+pub(crate) struct Decoder<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Decoder { data, position: 0 }
+    }
+
+    /// Returns the amount of bytes read so far.
+    pub(crate) fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Decodes the next data item, or returns `None` if it is truncated or malformed.
+    pub(crate) fn decode(&mut self) -> Option<CborValue> {
+        self.decode_nested(0)
+    }
+
+    fn decode_nested(&mut self, depth: usize) -> Option<CborValue> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+
+        // Simple values and floats use the additional information directly
+        if major == 7 {
+            return match info {
+                20 => Some(CborValue::Bool(false)),
+                21 => Some(CborValue::Bool(true)),
+                22 => Some(CborValue::Null),
+                23 => Some(CborValue::Undefined),
+                25 => Some(CborValue::Float(half_to_f64(u16::from_be_bytes(
+                    self.array()?,
+                )))),
+                26 => Some(CborValue::Float(f32::from_be_bytes(self.array()?).into())),
+                27 => Some(CborValue::Float(f64::from_be_bytes(self.array()?))),
+                _ => None,
+            };
+        }
+
+        let argument = match info {
+            0..=23 => u64::from(info),
+            24 => u64::from(self.take(1)?[0]),
+            25 => u64::from(u16::from_be_bytes(self.array()?)),
+            26 => u64::from(u32::from_be_bytes(self.array()?)),
+            27 => u64::from_be_bytes(self.array()?),
+            _ => return None,
+        };
+        match major {
+            0 => Some(CborValue::Integer(argument.into())),
+            1 => Some(CborValue::Integer(-1 - i128::from(argument))),
+            2 => Some(CborValue::Bytes(self.take(length(argument)?)?.to_vec())),
+            3 => String::from_utf8(self.take(length(argument)?)?.to_vec())
+                .ok()
+                .map(CborValue::Text),
+            4 => {
+                // Every item takes at least one byte, which bounds the allocation
+                let len = length(argument)?;
+                if len > self.data.len() - self.position {
+                    return None;
+                }
+                (0..len)
+                    .map(|_| self.decode_nested(depth + 1))
+                    .collect::<Option<_>>()
+                    .map(CborValue::Array)
+            }
+            5 => {
+                let len = length(argument)?;
+                if len > (self.data.len() - self.position) / 2 {
+                    return None;
+                }
+                (0..len)
+                    .map(|_| {
+                        Some((
+                            self.decode_nested(depth + 1)?,
+                            self.decode_nested(depth + 1)?,
+                        ))
+                    })
+                    .collect::<Option<_>>()
+                    .map(CborValue::Map)
+            }
+            6 => Some(CborValue::Tag(
+                argument,
+                Box::new(self.decode_nested(depth + 1)?),
+            )),
+            _ => None,
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.position.checked_add(len)?;
+        let taken = self.data.get(self.position..end)?;
+        self.position = end;
+        Some(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Some(array)
+    }
+}
+
+fn length(argument: u64) -> Option<usize> {
+    usize::try_from(argument).ok()
+}
+
+fn half_to_f64(half: u16) -> f64 {
+    let exponent = i32::from((half >> 10) & 0x1f);
+    let mantissa = f64::from(half & 0x3ff);
+    let value = match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (mantissa + 1024.0) * 2f64.powi(exponent - 25),
+    };
+    if half & 0x8000 != 0 {
+        -value
+    } else {
+        value
+    }
+}
+
+/// Returns the contents of given encoded CBOR byte string, which must not be followed by other data.
+pub(crate) fn byte_string(data: &[u8]) -> Option<&[u8]> {
+    let mut decoder = Decoder::new(data);
+    match decoder.decode()? {
+        CborValue::Bytes(bytes) if decoder.position() == data.len() => {
+            Some(&data[data.len() - bytes.len()..])
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(data: &[u8]) -> Option<CborValue> {
+        let mut decoder = Decoder::new(data);
+        let value = decoder.decode()?;
+        assert_eq!(decoder.position(), data.len());
+        Some(value)
+    }
+
+    fn nested_arrays(depth: usize) -> Vec<u8> {
+        let mut data = vec![0x81; depth];
+        data.push(0x00);
+        data
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let value = CborValue::Map(vec![
+            (CborValue::Integer(1), CborValue::Integer(-1)),
+            (CborValue::Integer(-7), CborValue::Integer(u64::MAX.into())),
+            (
+                CborValue::Text("bytes".to_owned()),
+                CborValue::Bytes(vec![0; 300]),
+            ),
+            (
+                CborValue::Text("array".to_owned()),
+                CborValue::Array(vec![
+                    CborValue::Bool(false),
+                    CborValue::Bool(true),
+                    CborValue::Null,
+                    CborValue::Undefined,
+                    CborValue::Float(1.5),
+                ]),
+            ),
+            (
+                CborValue::Text("tag".to_owned()),
+                CborValue::Tag(24, Box::new(CborValue::Integer(100_000))),
+            ),
+        ]);
+        assert_eq!(decode(&value.encode()), Some(value));
+    }
+
+    #[test]
+    fn decodes_short_floats() {
+        assert_eq!(decode(&[0xf9, 0x3e, 0x00]), Some(CborValue::Float(1.5)));
+        assert_eq!(
+            decode(&[0xf9, 0x7c, 0x00]),
+            Some(CborValue::Float(f64::INFINITY))
+        );
+        assert_eq!(
+            decode(&[0xfa, 0xc0, 0x20, 0x00, 0x00]),
+            Some(CborValue::Float(-2.5))
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_items() {
+        let value = CborValue::Map(vec![
+            (CborValue::Integer(1), CborValue::Text("text".to_owned())),
+            (
+                CborValue::Integer(2),
+                CborValue::Array(vec![CborValue::Bytes(vec![1, 2, 3]), CborValue::Float(0.5)]),
+            ),
+            (CborValue::Integer(1000), CborValue::Integer(-1000)),
+        ]);
+        let encoded = value.encode();
+        for len in 0..encoded.len() {
+            assert_eq!(Decoder::new(&encoded[..len]).decode(), None, "{}", len);
+        }
+    }
+
+    #[test]
+    fn reads_consecutive_items() {
+        let mut decoder = Decoder::new(&[0x01, 0x62, b'h', b'i', 0xff]);
+        assert_eq!(decoder.decode(), Some(CborValue::Integer(1)));
+        assert_eq!(decoder.decode(), Some(CborValue::Text("hi".to_owned())));
+        assert_eq!(decoder.position(), 4);
+        // 0xff is a break, which only ends indefinite lengths
+        assert_eq!(decoder.decode(), None);
+    }
+
+    #[test]
+    fn limits_nesting() {
+        assert!(decode(&nested_arrays(MAX_DEPTH)).is_some());
+        assert_eq!(decode(&nested_arrays(MAX_DEPTH + 1)), None);
+        // Tags count towards the depth as well
+        let mut tags = vec![0xc1; MAX_DEPTH + 1];
+        tags.push(0x00);
+        assert_eq!(decode(&tags), None);
+    }
+
+    #[test]
+    fn rejects_oversized_lengths() {
+        // Byte and text strings, arrays and maps with 2^64 - 1 elements
+        for &major in &[0x40, 0x60, 0x80, 0xa0] {
+            let mut data = vec![major | 27];
+            data.extend_from_slice(&[0xff; 8]);
+            assert_eq!(decode(&data), None);
+        }
+        // An array and a map which announce more items than bytes follow
+        assert_eq!(decode(&[0x83, 0x01, 0x02]), None);
+        assert_eq!(decode(&[0xa2, 0x01, 0x02, 0x03]), None);
+        assert_eq!(decode(&[0x44, 0x01, 0x02, 0x03]), None);
+    }
+
+    #[test]
+    fn rejects_unsupported_items() {
+        // Indefinite lengths
+        assert_eq!(decode(&[0x5f, 0x41, 0x00, 0xff]), None);
+        assert_eq!(decode(&[0x9f, 0xff]), None);
+        // Reserved additional information
+        assert_eq!(decode(&[0x1c]), None);
+        // Text which is not UTF-8
+        assert_eq!(decode(&[0x61, 0xff]), None);
+    }
+
+    #[test]
+    fn unwraps_byte_strings() {
+        assert_eq!(byte_string(&[0x42, 0x01, 0x02]), Some(&[0x01, 0x02][..]));
+        assert_eq!(byte_string(&[0x42, 0x01, 0x02, 0x03]), None);
+        assert_eq!(byte_string(&[0x62, b'h', b'i']), None);
+        assert_eq!(byte_string(&[]), None);
+    }
+}
@@ -1,6 +1,12 @@
 use crate::ffi::*;
 use libfido2_sys::*;
-use std::{collections::HashMap, iter::FromIterator, slice, str};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    iter::FromIterator,
+    slice, str,
+};
 
 /// Owns additional data stored as CBOR on a device.
 #[derive(PartialEq, Eq)]
@@ -15,6 +21,27 @@ pub struct CBORDataRef<'a> {
     pub extensions: Box<[&'a str]>,
     pub ctap_versions: Box<[&'a str]>,
     pub options: HashMap<&'a str, bool>,
+    /// The maximum length of blobs stored through the `credBlob` extension, or 0 if it is unsupported.
+    pub max_credential_blob_length: u64,
+}
+
+/// Owned version of [`CBORDataRef`], which does not borrow from [`CBORData`].
+///
+/// # Remarks
+/// - Implements `Serialize` and `Deserialize` if the `serde` feature is enabled.
+///
+/// [`CBORDataRef`]: struct.CBORDataRef.html
+/// [`CBORData`]: struct.CBORData.html
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CBORInfo {
+    pub aag_uid: Option<Vec<u8>>,
+    pub pin_protocols: Vec<u8>,
+    pub extensions: Vec<String>,
+    pub ctap_versions: Vec<String>,
+    pub options: BTreeMap<String, bool>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_credential_blob_length: u64,
 }
 
 impl CBORData {
@@ -61,17 +88,43 @@ impl CBORData {
                 })
                 .unwrap_or(HashMap::with_capacity(0));
 
+            let max_credential_blob_length = fido_cbor_info_maxcredbloblen(cbor_info);
+
             CBORDataRef {
                 aag_uid,
                 pin_protocols,
                 extensions,
                 ctap_versions,
                 options,
+                max_credential_blob_length,
             }
         }
     }
 }
 
+impl From<CBORDataRef<'_>> for CBORInfo {
+    fn from(data: CBORDataRef<'_>) -> Self {
+        CBORInfo {
+            aag_uid: data.aag_uid.map(<[u8]>::to_vec),
+            pin_protocols: data.pin_protocols.to_vec(),
+            extensions: data.extensions.iter().map(|s| s.to_string()).collect(),
+            ctap_versions: data.ctap_versions.iter().map(|s| s.to_string()).collect(),
+            options: data
+                .options
+                .into_iter()
+                .map(|(name, value)| (name.to_owned(), value))
+                .collect(),
+            max_credential_blob_length: data.max_credential_blob_length,
+        }
+    }
+}
+
+impl From<&CBORData> for CBORInfo {
+    fn from(data: &CBORData) -> Self {
+        data.as_ref().into()
+    }
+}
+
 // libfido2_sys guarantees this.
 unsafe impl Send for CBORData {}
 unsafe impl Sync for CBORData {}
@@ -1,7 +1,17 @@
-use crate::{ffi::NonNull, FidoError, PublicKey, Result, FIDO_OK};
+use crate::{
+    auth_data, der, ffi::NonNull, AuthenticatorData, AuthenticatorDataError, CborValue, FidoError,
+    PublicKey, Result, FIDO_OK,
+};
 use bitflags::bitflags;
 use libfido2_sys::*;
-use std::{error, ffi::CStr, fmt, os::raw, ptr, slice, str::FromStr};
+use std::{
+    collections::BTreeMap, convert::TryFrom, error, ffi::CStr, fmt, os::raw, ptr, slice,
+    str::FromStr, time::Duration,
+};
+
+const EXTENSION_CREDENTIAL_BLOB: &str = "credBlob";
+const EXTENSION_HMAC_SECRET: &str = "hmac-secret";
+const EXTENSION_MIN_PIN_LENGTH: &str = "minPinLength";
 
 // Raw Credential is initialized with NULL data
 // Only expose this type when it is properly initialized (returned from device)
@@ -10,11 +20,34 @@ pub struct Credential {
 }
 
 // Wrapper type to safely initialize the Credential with enough information to pass to a device
-pub struct CredentialCreator(Credential);
+pub struct CredentialCreator {
+    credential: Credential,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) enforce_protection: bool,
+}
 
 /// Required information to request a new [`Credential`] from a `Device`.
 ///
+/// # Remarks
+/// - `protection`: requests the `credProtect` extension with given policy, if set.
+/// - `enforce_protection`: fails the request with `FIDO_ERR_UNSUPPORTED_EXTENSION`
+///   if `protection` is set and the device does not support the `credProtect` extension.
+///   Otherwise, CTAP2 devices which ignore the extension create the credential without protection,
+///   and U2F devices fail with `FIDO_ERR_UNSUPPORTED_OPTION` since they support no extensions.
+/// - `credential_blob`: requests the `credBlob` extension, which stores given data with the credential
+///   and returns it in assertions. The maximum length is listed in the [CBOR data] of the device.
+///   Devices may create the credential without storing the blob, as reported by `credential_blob_stored`.
+///   libfido2 expects requested blobs to be stored, so verifying such a credential fails
+///   with `FIDO_ERR_INVALID_PARAM`.
+/// - `enterprise_attestation`: requests enterprise attestation in given mode, if set.
+///   The device must have it [enabled]. Requires building against libfido2 1.14 or later,
+///   otherwise the request fails with `FIDO_ERR_UNSUPPORTED_OPTION`.
+/// - `timeout`: overrides the [timeout] of the device for this request, if set.
+///
 /// [`Credential`]: struct.Credential.html
+/// [enabled]: struct.Device.html#method.enable_enterprise_attestation
+/// [CBOR data]: struct.CBORDataRef.html#structfield.max_credential_blob_length
+/// [timeout]: struct.Device.html#method.set_timeout
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct CredentialCreationData<'a> {
     pub excluded_ids: &'a [u8],
@@ -28,19 +61,60 @@ pub struct CredentialCreationData<'a> {
     pub user_image_uri: Option<&'a CStr>,
     pub options: CredentialOptions,
     pub extensions: CredentialExtensions,
+    pub protection: Option<CredentialProtection>,
+    pub enforce_protection: bool,
+    pub credential_blob: Option<&'a [u8]>,
+    pub enterprise_attestation: Option<EnterpriseAttestation>,
+    pub timeout: Option<Duration>,
+}
+
+/// Attestation of a [`Credential`], as a relying party receives it from a client.
+///
+/// # Remarks
+/// - `auth_data`: the raw authenticator data, as contained in attestation objects.
+/// - `x509_certificate`: the first certificate of the `x5c` chain of the attestation statement,
+///   or `None` for self attestation, where the statement has no `x5c` chain.
+///   Self attested Credentials must be verified with [`Credential::verify_self`].
+/// - `relying_party_id`: the ID which the relying party expects the credential to be scoped to.
+///
+/// [`Credential`]: struct.Credential.html
+/// [`Credential::verify_self`]: struct.Credential.html#method.verify_self
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CredentialAttestationData<'a> {
+    pub format: CredentialFormat,
+    pub auth_data: &'a [u8],
+    pub x509_certificate: Option<&'a [u8]>,
+    pub signature: &'a [u8],
+    pub client_data_hash: &'a [u8],
+    pub relying_party_id: &'a CStr,
+    pub credential_type: CredentialType,
 }
 
 // Possible to retrieve after a Credential was returned from a device
+// `protection` is the policy reported by the authenticator, which may differ from the requested one
+// `credential_blob_stored` is whether the authenticator stored the requested credential blob
+// `min_pin_length` is the minimum PIN length reported through the `minPinLength` extension,
+// which is not verified against an expected length
+// `auth_data` is wrapped in a CBOR byte string, `raw_auth_data` is as contained in attestation objects
+// `x509_certificate` is empty for self attestation
+// `enterprise_attestation` is whether the device returned an enterprise attestation,
+// which is only reported when built against libfido2 1.14 or later
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct CredentialRef<'a> {
     pub format: &'a CStr,
     pub auth_data: &'a [u8],
+    pub raw_auth_data: &'a [u8],
     pub client_data_hash: &'a [u8],
     pub id: &'a [u8],
     pub credential_type: CredentialType,
     pub public_key: &'a [u8],
     pub signature: &'a [u8],
     pub x509_certificate: &'a [u8],
+    pub protection: Option<CredentialProtection>,
+    pub large_blob_key: Option<&'a [u8]>,
+    pub credential_blob_stored: bool,
+    pub min_pin_length: Option<u64>,
+    pub enterprise_attestation: bool,
 }
 
 impl<'a> CredentialCreationData<'a> {
@@ -64,6 +138,11 @@ impl<'a> CredentialCreationData<'a> {
             user_image_uri: None,
             options: CredentialOptions::empty(),
             extensions: CredentialExtensions::empty(),
+            protection: None,
+            enforce_protection: false,
+            credential_blob: None,
+            enterprise_attestation: None,
+            timeout: None,
         }
     }
 }
@@ -81,7 +160,7 @@ impl CredentialCreator {
         }
         credential.set_type(data.credential_type)?;
         credential.set_client_data_hash(data.client_data_hash)?;
-        credential.set_relying_party(data.relying_party_id, data.relying_party_name)?;
+        credential.set_relying_party(data.relying_party_id, Some(data.relying_party_name))?;
         credential.set_user(
             data.user_id,
             data.user_name,
@@ -90,24 +169,37 @@ impl CredentialCreator {
         )?;
         credential.set_options(data.options)?;
         credential.set_extensions(data.extensions)?;
-        Ok(CredentialCreator(credential))
+        if let Some(protection) = data.protection {
+            credential.set_protection(protection)?;
+        }
+        if let Some(blob) = data.credential_blob {
+            credential.set_blob(blob)?;
+        }
+        if let Some(mode) = data.enterprise_attestation {
+            credential.set_enterprise_attestation(mode)?;
+        }
+        Ok(CredentialCreator {
+            credential,
+            timeout: data.timeout,
+            enforce_protection: data.enforce_protection && data.protection.is_some(),
+        })
     }
 
     pub(crate) fn raw(&self) -> &NonNull<fido_cred> {
-        &self.0.raw
+        &self.credential.raw
     }
 
     pub(crate) fn raw_mut(&mut self) -> &mut NonNull<fido_cred> {
-        &mut self.0.raw
+        &mut self.credential.raw
     }
 
     /// NB. Only call this after the Credential was returned from a device, or it will cause panics
     pub(crate) fn into_inner(self) -> Credential {
-        self.0
+        self.credential
     }
 }
 
-impl CredentialRef<'_> {
+impl<'a> CredentialRef<'a> {
     /// Tries to parse the contained public key as a [`PublicKey`].
     ///
     /// [`PublicKey`]: enum.PublicKey.html
@@ -118,9 +210,96 @@ impl CredentialRef<'_> {
             CredentialType::EDDSA => PublicKey::new_eddsa(self.public_key),
         }
     }
+
+    /// Parses the contained authenticator data as [`AuthenticatorData`].
+    ///
+    /// [`AuthenticatorData`]: struct.AuthenticatorData.html
+    pub fn authenticator_data(
+        &self,
+    ) -> std::result::Result<AuthenticatorData<'a>, AuthenticatorDataError> {
+        AuthenticatorData::parse_cbor(self.auth_data)
+    }
+
+    /// Returns the serial number of the attestation certificate, if it can be parsed.
+    ///
+    /// # Remarks
+    /// - Batch attestation certificates are shared by many devices, so this only identifies
+    ///   a single device if the vendor issues a certificate per device, as with enterprise attestation.
+    pub fn certificate_serial_number(&self) -> Option<&'a [u8]> {
+        der::certificate_serial_number(self.x509_certificate)
+    }
+
+    /// Returns the `serialNumber` attribute of the subject of the attestation certificate, if it has one.
+    ///
+    /// # Remarks
+    /// - Vendors which issue a certificate per device commonly list the serial number of the device here.
+    pub fn device_serial_number(&self) -> Option<&'a str> {
+        der::certificate_subject_serial_number(self.x509_certificate)
+    }
 }
 
 impl Credential {
+    /// Reconstructs a Credential from its attestation, so it can be [verified] without a device.
+    ///
+    /// # Remarks
+    /// - Supports the `packed` format, with a certificate or as self attestation,
+    ///   and the `fido-u2f` format. Attestations of the `none` format carry no signature to verify,
+    ///   their authenticator data can be read with [`AuthenticatorData::parse`] instead.
+    /// - Fails if the authenticator data does not contain a public key of given `CredentialType`.
+    /// - Extension outputs are accepted as reported, as the requested extensions are unknown.
+    ///   They can be inspected through [`as_ref`].
+    /// - Without `x509_certificate`, the Credential is self attested and only passes [`verify_self`].
+    ///   Relying parties must decide themselves whether to accept self attestation.
+    ///
+    /// [verified]: #method.verify
+    /// [`verify_self`]: #method.verify_self
+    /// [`as_ref`]: #method.as_ref
+    /// [`AuthenticatorData::parse`]: struct.AuthenticatorData.html#method.parse
+    pub fn from_attestation(data: CredentialAttestationData<'_>) -> Result<Credential> {
+        let mut credential = unsafe {
+            Credential {
+                raw: NonNull::new(fido_cred_new()).unwrap(),
+            }
+        };
+        credential.set_type(data.credential_type)?;
+        credential.set_client_data_hash(data.client_data_hash)?;
+        credential.set_relying_party(data.relying_party_id, None)?;
+        credential.set_auth_data_raw(data.auth_data)?;
+        // libfido2 verifies the reported extension outputs against the requested extensions
+        let extensions = credential.extension_outputs();
+        let reported = |name| extensions.get(name) == Some(&CborValue::Bool(true));
+        let mut mask = 0;
+        if reported(EXTENSION_HMAC_SECRET) {
+            mask |= FIDO_EXT_HMAC_SECRET;
+        }
+        if reported(EXTENSION_CREDENTIAL_BLOB) {
+            // Only requested through `CredentialCreationData::credential_blob`, which has no flag
+            mask |= FIDO_EXT_CRED_BLOB;
+        }
+        unsafe {
+            match fido_cred_set_extensions(credential.raw.as_ptr_mut(), mask as raw::c_int) {
+                FIDO_OK => {}
+                err => return Err(FidoError(err)),
+            }
+        }
+        if let Some(protection) = protection(&extensions) {
+            credential.set_protection(protection)?;
+        }
+        if let Some(min_pin_length) = min_pin_length(&extensions) {
+            let min_pin_length = usize::try_from(min_pin_length)
+                .map_err(|_| FidoError(FIDO_ERR_INVALID_ARGUMENT))?;
+            unsafe {
+                fido_cred_set_pin_minlen(credential.raw.as_ptr_mut(), min_pin_length);
+            }
+        }
+        if let Some(x509_certificate) = data.x509_certificate {
+            credential.set_x509_certificate(x509_certificate)?;
+        }
+        credential.set_signature(data.signature)?;
+        credential.set_format(data.format)?;
+        Ok(credential)
+    }
+
     pub fn as_ref<'a>(&'a self) -> CredentialRef<'a> {
         unsafe {
             let credential = self.raw.as_ptr();
@@ -135,6 +314,11 @@ impl Credential {
                 .map(|ptr| slice::from_raw_parts(ptr, fido_cred_authdata_len(credential)))
                 .unwrap();
 
+            let raw_auth_data = fido_cred_authdata_raw_ptr(credential)
+                .as_ref()
+                .map(|ptr| slice::from_raw_parts(ptr, fido_cred_authdata_raw_len(credential)))
+                .unwrap();
+
             let client_data_hash = fido_cred_clientdata_hash_ptr(credential)
                 .as_ref()
                 .map(|ptr| slice::from_raw_parts(ptr, fido_cred_clientdata_hash_len(credential)))
@@ -145,7 +329,8 @@ impl Credential {
                 .map(|ptr| slice::from_raw_parts(ptr, fido_cred_id_len(credential)))
                 .unwrap();
 
-            let credential_type = CredentialType::from_ffi(fido_cred_type(credential));
+            // The type is only ever set from a `CredentialType`
+            let credential_type = CredentialType::from_ffi(fido_cred_type(credential)).unwrap();
 
             let public_key = fido_cred_pubkey_ptr(credential)
                 .as_ref()
@@ -160,17 +345,39 @@ impl Credential {
             let x509_certificate = fido_cred_x5c_ptr(credential)
                 .as_ref()
                 .map(|ptr| slice::from_raw_parts(ptr, fido_cred_x5c_len(credential)))
-                .unwrap();
+                .unwrap_or_default();
+
+            let large_blob_key = fido_cred_largeblob_key_ptr(credential)
+                .as_ref()
+                .map(|ptr| slice::from_raw_parts(ptr, fido_cred_largeblob_key_len(credential)));
+
+            // libfido2 only exposes the requested values, so read the reported ones from the extensions
+            let extensions = self.extension_outputs();
+            let protection = protection(&extensions);
+            let credential_blob_stored =
+                extensions.get(EXTENSION_CREDENTIAL_BLOB) == Some(&CborValue::Bool(true));
+            let min_pin_length = min_pin_length(&extensions);
+
+            #[cfg(fido2_1_14)]
+            let enterprise_attestation = fido_cred_entattest(credential);
+            #[cfg(not(fido2_1_14))]
+            let enterprise_attestation = false;
 
             CredentialRef {
                 format,
                 auth_data,
+                raw_auth_data,
                 client_data_hash,
                 id,
                 credential_type,
                 public_key,
                 signature,
                 x509_certificate,
+                protection,
+                large_blob_key,
+                credential_blob_stored,
+                min_pin_length,
+                enterprise_attestation,
             }
         }
     }
@@ -179,6 +386,9 @@ impl Credential {
     ///
     /// # Remarks
     /// - The x509 certificate itself is not verified
+    /// - Fails for self attestation, which has no certificate. Use [`verify_self`] instead.
+    ///
+    /// [`verify_self`]: #method.verify_self
     pub fn verify(&self) -> Result<()> {
         unsafe {
             match fido_cred_verify(self.raw.as_ptr()) {
@@ -188,6 +398,32 @@ impl Credential {
         }
     }
 
+    /// Verifies that the Credential was signed with its own key, as in self attestation.
+    ///
+    /// # Remarks
+    /// - Self attestation proves possession of the key, but not which authenticator created it.
+    /// - Fails for attestations which were signed with the key of an x509 certificate.
+    pub fn verify_self(&self) -> Result<()> {
+        unsafe {
+            match fido_cred_verify_self(self.raw.as_ptr()) {
+                FIDO_OK => Ok(()),
+                err => Err(FidoError(err)),
+            }
+        }
+    }
+
+    /// Decodes the extension outputs of the authenticator data, if it has any.
+    fn extension_outputs(&self) -> BTreeMap<String, CborValue> {
+        unsafe {
+            let credential = self.raw.as_ptr();
+            let raw_auth_data = fido_cred_authdata_raw_ptr(credential)
+                .as_ref()
+                .map(|ptr| slice::from_raw_parts(ptr, fido_cred_authdata_raw_len(credential)))
+                .unwrap_or_default();
+            auth_data::extension_outputs(raw_auth_data)
+        }
+    }
+
     /*
         Private FFI setters
     */
@@ -227,16 +463,29 @@ impl Credential {
         }
     }
 
-    fn set_relying_party(&mut self, id: &CStr, name: &CStr) -> Result<()> {
+    fn set_relying_party(&mut self, id: &CStr, name: Option<&CStr>) -> Result<()> {
         unsafe {
-            match fido_cred_set_rp(self.raw.as_ptr_mut(), id.as_ptr(), name.as_ptr()) {
+            match fido_cred_set_rp(
+                self.raw.as_ptr_mut(),
+                id.as_ptr(),
+                name.map(CStr::as_ptr).unwrap_or(ptr::null()),
+            ) {
                 FIDO_OK => Ok(()),
                 err => Err(FidoError(err)),
             }
         }
     }
 
-    fn set_user(
+    pub(crate) fn set_id(&mut self, id: &[u8]) -> Result<()> {
+        unsafe {
+            match fido_cred_set_id(self.raw.as_ptr_mut(), id as *const _ as *const _, id.len()) {
+                FIDO_OK => Ok(()),
+                err => Err(FidoError(err)),
+            }
+        }
+    }
+
+    pub(crate) fn set_user(
         &mut self,
         id: &[u8],
         name: &CStr,
@@ -280,6 +529,19 @@ impl Credential {
         }
     }
 
+    fn set_auth_data_raw(&mut self, auth_data: &[u8]) -> Result<()> {
+        unsafe {
+            match fido_cred_set_authdata_raw(
+                self.raw.as_ptr_mut(),
+                auth_data as *const _ as *const _,
+                auth_data.len(),
+            ) {
+                FIDO_OK => Ok(()),
+                err => Err(FidoError(err)),
+            }
+        }
+    }
+
     fn set_x509_certificate(&mut self, x509_certificate: &[u8]) -> Result<()> {
         unsafe {
             match fido_cred_set_x509(
@@ -327,6 +589,40 @@ impl Credential {
             }
         }
     }
+
+    fn set_blob(&mut self, blob: &[u8]) -> Result<()> {
+        unsafe {
+            match fido_cred_set_blob(self.raw.as_ptr_mut(), blob.as_ptr(), blob.len()) {
+                FIDO_OK => Ok(()),
+                err => Err(FidoError(err)),
+            }
+        }
+    }
+
+    fn set_protection(&mut self, protection: CredentialProtection) -> Result<()> {
+        unsafe {
+            match fido_cred_set_prot(self.raw.as_ptr_mut(), protection as raw::c_int) {
+                FIDO_OK => Ok(()),
+                err => Err(FidoError(err)),
+            }
+        }
+    }
+
+    #[cfg(fido2_1_14)]
+    fn set_enterprise_attestation(&mut self, mode: EnterpriseAttestation) -> Result<()> {
+        unsafe {
+            match fido_cred_set_entattest(self.raw.as_ptr_mut(), mode as raw::c_int) {
+                FIDO_OK => Ok(()),
+                err => Err(FidoError(err)),
+            }
+        }
+    }
+
+    // libfido2 only supports enterprise attestation since 1.14
+    #[cfg(not(fido2_1_14))]
+    fn set_enterprise_attestation(&mut self, _mode: EnterpriseAttestation) -> Result<()> {
+        Err(FidoError(FIDO_ERR_UNSUPPORTED_OPTION as raw::c_int))
+    }
 }
 
 // libfido2_sys guarantees this.
@@ -350,6 +646,16 @@ bitflags! {
     pub struct CredentialExtensions: raw::c_int {
         /// Enables the ability to generate a symmetric secret.
         const HMAC_SECRET = FIDO_EXT_HMAC_SECRET as raw::c_int;
+        /// Requests a key to encrypt the large blob of the credential with.
+        /// Devices only return it for resident credentials.
+        const LARGE_BLOB_KEY = FIDO_EXT_LARGEBLOB_KEY as raw::c_int;
+        /// Requests the minimum PIN length of the device, which it only reports
+        /// to relying parties that were allowed to read it.
+        ///
+        /// libfido2 verifies the reported length against an expected one, which cannot be set
+        /// in advance. Verifying such a Credential therefore fails with `FIDO_ERR_INVALID_PARAM`,
+        /// and the reported `min_pin_length` is not verified.
+        const MIN_PIN_LENGTH = FIDO_EXT_MINPINLEN as raw::c_int;
     }
 }
 
@@ -365,6 +671,65 @@ bitflags! {
     }
 }
 
+/// Reads the output of the `credProtect` extension.
+fn protection(extensions: &BTreeMap<String, CborValue>) -> Option<CredentialProtection> {
+    extensions
+        .get(CredentialProtection::EXTENSION)
+        .and_then(CborValue::as_integer)
+        .and_then(|i| raw::c_int::try_from(i).ok())
+        .and_then(CredentialProtection::from_ffi)
+}
+
+/// Reads the output of the `minPinLength` extension.
+fn min_pin_length(extensions: &BTreeMap<String, CborValue>) -> Option<u64> {
+    extensions
+        .get(EXTENSION_MIN_PIN_LENGTH)
+        .and_then(CborValue::as_integer)
+        .and_then(|i| u64::try_from(i).ok())
+}
+
+/// Policies of the `credProtect` extension, which restrict when a [`Credential`] may be used.
+///
+/// [`Credential`]: struct.Credential.html
+#[repr(i32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CredentialProtection {
+    /// The credential can be used without verifying the user.
+    UserVerificationOptional = FIDO_CRED_PROT_UV_OPTIONAL as i32,
+    /// The credential can be used without verifying the user only if its ID is provided.
+    /// This hides resident credentials from anyone who does not know the PIN.
+    UserVerificationOptionalWithCredentialIdList = FIDO_CRED_PROT_UV_OPTIONAL_WITH_ID as i32,
+    /// The credential can only be used after verifying the user.
+    UserVerificationRequired = FIDO_CRED_PROT_UV_REQUIRED as i32,
+}
+
+impl CredentialProtection {
+    pub(crate) const EXTENSION: &'static str = "credProtect";
+
+    pub(crate) fn from_ffi(i: raw::c_int) -> Option<Self> {
+        match i as u32 {
+            FIDO_CRED_PROT_UV_OPTIONAL => Some(CredentialProtection::UserVerificationOptional),
+            FIDO_CRED_PROT_UV_OPTIONAL_WITH_ID => {
+                Some(CredentialProtection::UserVerificationOptionalWithCredentialIdList)
+            }
+            FIDO_CRED_PROT_UV_REQUIRED => Some(CredentialProtection::UserVerificationRequired),
+            _ => None,
+        }
+    }
+}
+
+/// Modes of enterprise attestation, which identifies the individual device in the attestation of a [`Credential`].
+///
+/// [`Credential`]: struct.Credential.html
+#[repr(i32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EnterpriseAttestation {
+    /// The device only returns an enterprise attestation to relying parties its vendor lists.
+    VendorFacilitated = 1,
+    /// The platform decides which relying parties receive an enterprise attestation.
+    PlatformManaged = 2,
+}
+
 /// Possible data formats for a [`Credential`].
 ///
 /// [`Credential`]: struct.Credential.html
@@ -412,14 +777,12 @@ pub enum CredentialType {
 }
 
 impl CredentialType {
-    /// # Panics
-    /// - When passed an invalid value
-    pub(crate) fn from_ffi(i: raw::c_int) -> Self {
+    pub(crate) fn from_ffi(i: raw::c_int) -> Option<Self> {
         match i {
-            COSE_ES256 => CredentialType::ES256,
-            COSE_RS256 => CredentialType::RS256,
-            COSE_EDDSA => CredentialType::EDDSA,
-            _ => panic!("Invalid credential type"),
+            COSE_ES256 => Some(CredentialType::ES256),
+            COSE_RS256 => Some(CredentialType::RS256),
+            COSE_EDDSA => Some(CredentialType::EDDSA),
+            _ => None,
         }
     }
 }
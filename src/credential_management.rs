@@ -0,0 +1,234 @@
+use crate::{
+    ffi::{bytes, optional_ptr, string, NonNull, Owned},
+    Credential, CredentialType, Device, FidoError, PublicKey, Result,
+};
+use libfido2_sys::*;
+use std::{ffi::CStr, os::raw};
+
+/// Amounts of resident credentials on a device, returned by [`Device::credential_metadata`].
+///
+/// [`Device::credential_metadata`]: struct.Device.html#method.credential_metadata
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CredentialMetadata {
+    /// The amount of resident credentials stored on the device.
+    pub existing: u64,
+    /// The estimated amount of resident credentials which can still be stored.
+    pub remaining: u64,
+}
+
+/// A relying party which has resident credentials on a device.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResidentRelyingParty {
+    pub id: String,
+    pub name: Option<String>,
+    /// SHA-256 hash of `id`, which the device uses to identify the relying party.
+    pub id_hash: Vec<u8>,
+}
+
+/// A resident credential stored on a device.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResidentCredential {
+    pub id: Vec<u8>,
+    pub credential_type: CredentialType,
+    pub public_key: Vec<u8>,
+    pub user_id: Vec<u8>,
+    pub user_name: Option<String>,
+    pub user_display_name: Option<String>,
+}
+
+/// New user information of a resident credential, used by [`Device::update_user_information`].
+///
+/// # Remarks
+/// - `id` must match the user ID stored with the credential.
+/// - The display name is removed from the credential if it is `None`.
+///
+/// [`Device::update_user_information`]: struct.Device.html#method.update_user_information
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UserInformation<'a> {
+    pub id: &'a [u8],
+    pub name: &'a CStr,
+    pub display_name: Option<&'a CStr>,
+}
+
+impl ResidentCredential {
+    /// Tries to parse the contained public key as a [`PublicKey`].
+    ///
+    /// [`PublicKey`]: enum.PublicKey.html
+    pub fn public_key(&self) -> Result<PublicKey> {
+        match self.credential_type {
+            CredentialType::ES256 => PublicKey::new_es256(&self.public_key),
+            CredentialType::RS256 => PublicKey::new_rs256(&self.public_key),
+            CredentialType::EDDSA => PublicKey::new_eddsa(&self.public_key),
+        }
+    }
+
+    /// Returns `None` if the credential uses an algorithm which [`CredentialType`] does not cover.
+    ///
+    /// # Safety
+    /// - `credential` must be a valid credential returned by `fido_credman_rk`.
+    ///
+    /// [`CredentialType`]: enum.CredentialType.html
+    unsafe fn from_ffi(credential: *const fido_cred_t) -> Option<Self> {
+        Some(ResidentCredential {
+            id: bytes(fido_cred_id_ptr(credential), fido_cred_id_len(credential)),
+            credential_type: CredentialType::from_ffi(fido_cred_type(credential))?,
+            public_key: bytes(
+                fido_cred_pubkey_ptr(credential),
+                fido_cred_pubkey_len(credential),
+            ),
+            user_id: bytes(
+                fido_cred_user_id_ptr(credential),
+                fido_cred_user_id_len(credential),
+            ),
+            user_name: string(fido_cred_user_name(credential)),
+            user_display_name: string(fido_cred_display_name(credential)),
+        })
+    }
+}
+
+impl Device {
+    /// Requests the amounts of resident credentials on the device.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Credential management requires the PIN, unless the device verifies the user itself.
+    /// - Devices without credential management fail with `FIDO_ERR_INVALID_COMMAND`.
+    pub fn credential_metadata(&mut self, pin: Option<&CStr>) -> Result<CredentialMetadata> {
+        unsafe {
+            let mut metadata = Owned::new(fido_credman_metadata_new(), fido_credman_metadata_free);
+            self.request(None, |device| {
+                fido_credman_get_dev_metadata(device, metadata.as_ptr_mut(), optional_ptr(pin))
+            })?;
+            Ok(CredentialMetadata {
+                existing: fido_credman_rk_existing(metadata.as_ptr()),
+                remaining: fido_credman_rk_remaining(metadata.as_ptr()),
+            })
+        }
+    }
+
+    /// Requests the relying parties which have resident credentials on the device.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Credential management requires the PIN, unless the device verifies the user itself.
+    /// - Returns an empty list if the device does not store any resident credentials.
+    pub fn resident_relying_parties(
+        &mut self,
+        pin: Option<&CStr>,
+    ) -> Result<Vec<ResidentRelyingParty>> {
+        unsafe {
+            let mut relying_parties = Owned::new(fido_credman_rp_new(), fido_credman_rp_free);
+            match self.request(None, |device| {
+                fido_credman_get_dev_rp(device, relying_parties.as_ptr_mut(), optional_ptr(pin))
+            }) {
+                Ok(()) => {}
+                // Devices report that nothing is stored as an error
+                Err(FidoError(err)) if err == FIDO_ERR_NO_CREDENTIALS as raw::c_int => {
+                    return Ok(Vec::new())
+                }
+                Err(err) => return Err(err),
+            }
+
+            let relying_parties = relying_parties.as_ptr();
+            Ok((0..fido_credman_rp_count(relying_parties))
+                .map(|i| ResidentRelyingParty {
+                    id: string(fido_credman_rp_id(relying_parties, i)).unwrap_or_default(),
+                    name: string(fido_credman_rp_name(relying_parties, i)),
+                    id_hash: bytes(
+                        fido_credman_rp_id_hash_ptr(relying_parties, i),
+                        fido_credman_rp_id_hash_len(relying_parties, i),
+                    ),
+                })
+                .collect())
+        }
+    }
+
+    /// Requests the resident credentials of a relying party on the device.
+    ///
+    /// # Arguments
+    /// - `relying_party_id`: The ID of the relying party, as listed by [`resident_relying_parties`].
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Credential management requires the PIN, unless the device verifies the user itself.
+    /// - Returns an empty list if the relying party does not have any resident credentials.
+    /// - Credentials with an algorithm other than those of [`CredentialType`] are skipped.
+    ///
+    /// [`CredentialType`]: enum.CredentialType.html
+    /// [`resident_relying_parties`]: #method.resident_relying_parties
+    pub fn resident_credentials(
+        &mut self,
+        relying_party_id: &CStr,
+        pin: Option<&CStr>,
+    ) -> Result<Vec<ResidentCredential>> {
+        unsafe {
+            let mut credentials = Owned::new(fido_credman_rk_new(), fido_credman_rk_free);
+            match self.request(None, |device| {
+                fido_credman_get_dev_rk(
+                    device,
+                    relying_party_id.as_ptr(),
+                    credentials.as_ptr_mut(),
+                    optional_ptr(pin),
+                )
+            }) {
+                Ok(()) => {}
+                // Devices report that nothing is stored as an error
+                Err(FidoError(err)) if err == FIDO_ERR_NO_CREDENTIALS as raw::c_int => {
+                    return Ok(Vec::new())
+                }
+                Err(err) => return Err(err),
+            }
+
+            let credentials = credentials.as_ptr();
+            Ok((0..fido_credman_rk_count(credentials))
+                .filter_map(|i| ResidentCredential::from_ffi(fido_credman_rk(credentials, i)))
+                .collect())
+        }
+    }
+
+    /// Deletes a resident credential from the device.
+    ///
+    /// # Arguments
+    /// - `id`: The ID of the credential.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Credential management requires the PIN, unless the device verifies the user itself.
+    /// - Fails with `FIDO_ERR_NO_CREDENTIALS` if the device does not store the credential.
+    pub fn delete_resident_credential(&mut self, id: &[u8], pin: Option<&CStr>) -> Result<()> {
+        unsafe {
+            self.request(None, |device| {
+                fido_credman_del_dev_rk(device, id.as_ptr(), id.len(), optional_ptr(pin))
+            })
+        }
+    }
+
+    /// Replaces the user information of a resident credential on the device.
+    ///
+    /// # Arguments
+    /// - `id`: The ID of the credential.
+    /// - `user`: The new user information.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Credential management requires the PIN, unless the device verifies the user itself.
+    /// - This is part of CTAP 2.1, so devices which only implement CTAP 2.0 reject it.
+    /// - Fails with `FIDO_ERR_NO_CREDENTIALS` if the device does not store the credential.
+    pub fn update_user_information(
+        &mut self,
+        id: &[u8],
+        user: UserInformation<'_>,
+        pin: Option<&CStr>,
+    ) -> Result<()> {
+        unsafe {
+            let mut credential = Credential {
+                raw: NonNull::new(fido_cred_new()).unwrap(),
+            };
+            credential.set_id(id)?;
+            credential.set_user(user.id, user.name, user.display_name, None)?;
+            self.request(None, |device| {
+                fido_credman_set_dev_rk(device, credential.raw.as_ptr_mut(), optional_ptr(pin))
+            })
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{
+    transport::{Transport, FRAME_SIZE},
+    FidoError, Result, FIDO_ERR_RX, FIDO_ERR_TIMEOUT,
+};
+use libfido2_sys::*;
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io,
+    os::raw,
+    time::{Duration, Instant},
+};
+
+// CTAPHID command which is not part of the bindings
+const CTAP_CMD_ERROR: u8 = 0x3f;
+
+const INIT_HEADER: usize = 7;
+const CONT_HEADER: usize = 5;
+const INIT_NONCE_LEN: usize = 8;
+const MAX_PAYLOAD_LEN: usize = FRAME_SIZE - INIT_HEADER + 128 * (FRAME_SIZE - CONT_HEADER);
+
+/// A CTAPHID channel on a device, separate from the one libfido2 allocated for itself.
+/// Used for commands which libfido2 does not expose.
+pub(crate) struct Channel<'a> {
+    transport: &'a dyn Transport,
+    id: u32,
+    timeout: Option<Duration>,
+}
+
+impl<'a> Channel<'a> {
+    /// Allocates a new channel on the device.
+    ///
+    /// # Arguments
+    /// - `timeout`: The maximum duration of each transaction, or `None` to wait indefinitely.
+    pub(crate) fn open(transport: &'a dyn Transport, timeout: Option<Duration>) -> Result<Self> {
+        let mut channel = Channel {
+            transport,
+            id: CTAP_CID_BROADCAST,
+            timeout,
+        };
+
+        let nonce = nonce();
+        let deadline = channel.deadline();
+        channel.send(CTAP_CMD_INIT as u8, &nonce)?;
+        loop {
+            let (command, response) = channel.receive(deadline)?;
+            // Other clients may be allocating channels at the same time
+            if command != CTAP_CMD_INIT as u8 || !response.starts_with(&nonce) {
+                continue;
+            }
+            if response.len() < INIT_NONCE_LEN + 4 {
+                return Err(FidoError(FIDO_ERR_RX));
+            }
+            let id = &response[INIT_NONCE_LEN..INIT_NONCE_LEN + 4];
+            channel.id = u32::from_be_bytes([id[0], id[1], id[2], id[3]]);
+            return Ok(channel);
+        }
+    }
+
+    /// Sends a message with given command, and returns the payload of the response.
+    ///
+    /// # Remarks
+    /// - Error responses are returned as the contained CTAP1 error, which libfido2 reports with the same code.
+    pub(crate) fn transact(&self, command: u8, payload: &[u8]) -> Result<Vec<u8>> {
+        match self.transact_raw(command, payload)? {
+            (CTAP_CMD_ERROR, response) => {
+                let code = response
+                    .first()
+                    .copied()
+                    .unwrap_or(FIDO_ERR_ERR_OTHER as u8);
+                Err(FidoError(raw::c_int::from(code)))
+            }
+            (response_command, response) if response_command == command => Ok(response),
+            _ => Err(FidoError(FIDO_ERR_RX)),
+        }
+    }
+
+    /// Sends a message with given command, and returns the command and payload of the response,
+    /// skipping keepalive messages.
+    pub(crate) fn transact_raw(&self, command: u8, payload: &[u8]) -> Result<(u8, Vec<u8>)> {
+        let deadline = self.deadline();
+        self.send(command, payload)?;
+        loop {
+            match self.receive(deadline)? {
+                // Sent while the device waits for user presence
+                (response_command, _) if u32::from(response_command) == CTAP_KEEPALIVE => {}
+                response => return Ok(response),
+            }
+        }
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.timeout.map(|timeout| Instant::now() + timeout)
+    }
+
+    /// Splits a message into frames, and writes them.
+    fn send(&self, command: u8, payload: &[u8]) -> Result<()> {
+        if payload.len() > MAX_PAYLOAD_LEN {
+            return Err(FidoError(FIDO_ERR_INVALID_ARGUMENT));
+        }
+
+        let mut frame = [0; FRAME_SIZE];
+        frame[..4].copy_from_slice(&self.id.to_be_bytes());
+        frame[4] = command | CTAP_FRAME_INIT as u8;
+        frame[5..INIT_HEADER].copy_from_slice(&(payload.len() as u16).to_be_bytes());
+        let (first, rest) = payload.split_at(payload.len().min(FRAME_SIZE - INIT_HEADER));
+        frame[INIT_HEADER..INIT_HEADER + first.len()].copy_from_slice(first);
+        self.write(&frame)?;
+
+        for (sequence, chunk) in rest.chunks(FRAME_SIZE - CONT_HEADER).enumerate() {
+            let mut frame = [0; FRAME_SIZE];
+            frame[..4].copy_from_slice(&self.id.to_be_bytes());
+            frame[4] = sequence as u8;
+            frame[CONT_HEADER..CONT_HEADER + chunk.len()].copy_from_slice(chunk);
+            self.write(&frame)?;
+        }
+        Ok(())
+    }
+
+    /// Reads frames until a complete message was received on this channel,
+    /// and returns its command and payload.
+    fn receive(&self, deadline: Option<Instant>) -> Result<(u8, Vec<u8>)> {
+        let frame = loop {
+            let frame = self.read(deadline)?;
+            if self.is_own(&frame) && frame[4] & CTAP_FRAME_INIT as u8 != 0 {
+                break frame;
+            }
+        };
+        let command = frame[4] & !(CTAP_FRAME_INIT as u8);
+        let length = usize::from(u16::from_be_bytes([frame[5], frame[6]]));
+        let mut payload =
+            frame[INIT_HEADER..INIT_HEADER + length.min(FRAME_SIZE - INIT_HEADER)].to_vec();
+
+        let mut sequence = 0;
+        while payload.len() < length {
+            let frame = self.read(deadline)?;
+            if !self.is_own(&frame) {
+                continue;
+            }
+            if frame[4] != sequence {
+                return Err(FidoError(FIDO_ERR_RX));
+            }
+            sequence += 1;
+            let received = (length - payload.len()).min(FRAME_SIZE - CONT_HEADER);
+            payload.extend_from_slice(&frame[CONT_HEADER..CONT_HEADER + received]);
+        }
+        Ok((command, payload))
+    }
+
+    fn is_own(&self, frame: &[u8; FRAME_SIZE]) -> bool {
+        frame[..4] == self.id.to_be_bytes()
+    }
+
+    fn read(&self, deadline: Option<Instant>) -> Result<[u8; FRAME_SIZE]> {
+        let timeout = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let mut frame = [0; FRAME_SIZE];
+        match self.transport.read(&mut frame, timeout) {
+            Ok(FRAME_SIZE) => Ok(frame),
+            Err(err) if err.kind() == io::ErrorKind::TimedOut => Err(FidoError(FIDO_ERR_TIMEOUT)),
+            _ => Err(FidoError(FIDO_ERR_RX)),
+        }
+    }
+
+    fn write(&self, frame: &[u8; FRAME_SIZE]) -> Result<()> {
+        match self.transport.write(frame) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(FidoError(FIDO_ERR_TX)),
+        }
+    }
+}
+
+/// Generates the nonce of a channel allocation.
+/// It is only used to recognize the response, so it does not need to be cryptographically secure.
+fn nonce() -> [u8; INIT_NONCE_LEN] {
+    RandomState::new().build_hasher().finish().to_ne_bytes()
+}
+
+/// Direct access to a hidraw device node, next to the connection libfido2 holds.
+#[cfg(target_os = "linux")]
+pub(crate) struct Hidraw(std::fs::File);
+
+#[cfg(target_os = "linux")]
+impl Hidraw {
+    pub(crate) fn open(path: &std::ffi::CStr) -> io::Result<Self> {
+        use std::{ffi::OsStr, fs::OpenOptions, os::unix::ffi::OsStrExt};
+
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(OsStr::from_bytes(path.to_bytes()))
+            .map(Hidraw)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Transport for Hidraw {
+    fn read(&self, frame: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        use std::{convert::TryFrom, io::Read, os::unix::io::AsRawFd};
+
+        let mut fd = libc::pollfd {
+            fd: self.0.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ms = match timeout {
+            Some(timeout) => raw::c_int::try_from(timeout.as_millis()).unwrap_or(raw::c_int::MAX),
+            None => -1,
+        };
+        match unsafe { libc::poll(&mut fd, 1, ms) } {
+            0 => Err(io::ErrorKind::TimedOut.into()),
+            n if n < 0 => Err(io::Error::last_os_error()),
+            _ => (&self.0).read(frame),
+        }
+    }
+
+    fn write(&self, frame: &[u8]) -> io::Result<usize> {
+        use std::io::Write;
+
+        // Reports are prefixed with their ID, which is zero for FIDO devices
+        let mut report = [0; FRAME_SIZE + 1];
+        report[1..].copy_from_slice(&frame[..FRAME_SIZE]);
+        (&self.0).write_all(&report)?;
+        Ok(frame.len())
+    }
+}
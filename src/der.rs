@@ -0,0 +1,154 @@
+use std::convert::TryFrom;
+
+pub(crate) const TAG_INTEGER: u8 = 0x02;
+pub(crate) const TAG_BIT_STRING: u8 = 0x03;
+pub(crate) const TAG_NULL: u8 = 0x05;
+pub(crate) const TAG_OBJECT_IDENTIFIER: u8 = 0x06;
+pub(crate) const TAG_UTF8_STRING: u8 = 0x0c;
+pub(crate) const TAG_PRINTABLE_STRING: u8 = 0x13;
+pub(crate) const TAG_SEQUENCE: u8 = 0x30;
+pub(crate) const TAG_SET: u8 = 0x31;
+// [0] EXPLICIT, used for the version of X.509 certificates
+pub(crate) const TAG_CONTEXT_0: u8 = 0xa0;
+
+/// Reads consecutive DER elements from a byte slice.
+///
+/// # Remarks
+/// - Only single byte tags and definite lengths are supported, as DER requires.
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Reader { data }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the tag of the next element without reading it.
+    pub(crate) fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Reads the next element, and returns its tag and contents.
+    pub(crate) fn read_any(&mut self) -> Option<(u8, &'a [u8])> {
+        let (&tag, rest) = self.data.split_first()?;
+        let (&first, mut rest) = rest.split_first()?;
+        let len = match first {
+            0x00..=0x7f => usize::from(first),
+            0x81..=0x84 => {
+                let count = usize::from(first & 0x7f);
+                if rest.len() < count {
+                    return None;
+                }
+                let (bytes, remainder) = rest.split_at(count);
+                rest = remainder;
+                let len = bytes
+                    .iter()
+                    .fold(0u64, |len, &byte| (len << 8) | u64::from(byte));
+                usize::try_from(len).ok()?
+            }
+            _ => return None,
+        };
+        let contents = rest.get(..len)?;
+        self.data = &rest[len..];
+        Some((tag, contents))
+    }
+
+    /// Reads the next element, which must have given tag, and returns its contents.
+    pub(crate) fn read(&mut self, tag: u8) -> Option<&'a [u8]> {
+        match self.read_any()? {
+            (found, contents) if found == tag => Some(contents),
+            _ => None,
+        }
+    }
+}
+
+/// Encodes an element with given tag and contents.
+pub(crate) fn encode(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![tag];
+    match contents.len() {
+        len @ 0..=0x7f => encoded.push(len as u8),
+        len => {
+            let len = len.to_be_bytes();
+            let start = len.iter().position(|&b| b != 0).unwrap_or(len.len() - 1);
+            encoded.push(0x80 | (len.len() - start) as u8);
+            encoded.extend_from_slice(&len[start..]);
+        }
+    }
+    encoded.extend_from_slice(contents);
+    encoded
+}
+
+/// Encodes an INTEGER with given unsigned big-endian value.
+pub(crate) fn encode_unsigned_integer(value: &[u8]) -> Vec<u8> {
+    let start = value.iter().position(|&b| b != 0).unwrap_or(value.len());
+    let value = &value[start..];
+    match value.first() {
+        Some(&first) if first & 0x80 == 0 => encode(TAG_INTEGER, value),
+        // Positive integers with the high bit set need a padding byte
+        _ => encode(TAG_INTEGER, &[&[0], value].concat()),
+    }
+}
+
+/// Returns the unsigned big-endian value of given INTEGER contents, or `None` if it is negative.
+pub(crate) fn unsigned_integer(contents: &[u8]) -> Option<&[u8]> {
+    match contents {
+        [first, ..] if first & 0x80 != 0 => None,
+        [0x00, rest @ ..] if !rest.is_empty() => Some(rest),
+        [] => None,
+        _ => Some(contents),
+    }
+}
+
+// id-at-serialNumber (2.5.4.5)
+const OID_SERIAL_NUMBER: [u8; 3] = [0x55, 0x04, 0x05];
+
+/// Reads the serial number of an X.509 certificate, without the sign padding of DER integers.
+pub(crate) fn certificate_serial_number(certificate: &[u8]) -> Option<&[u8]> {
+    let mut tbs_certificate = tbs_certificate(certificate)?;
+    let serial_number = tbs_certificate.read(TAG_INTEGER)?;
+    match serial_number {
+        [0x00, rest @ ..] if !rest.is_empty() => Some(rest),
+        _ => Some(serial_number),
+    }
+}
+
+/// Reads the `serialNumber` attribute of the subject of an X.509 certificate, if it has one.
+pub(crate) fn certificate_subject_serial_number(certificate: &[u8]) -> Option<&str> {
+    let mut tbs_certificate = tbs_certificate(certificate)?;
+    // Skip the serial number, signature algorithm, issuer and validity
+    for &tag in &[TAG_INTEGER, TAG_SEQUENCE, TAG_SEQUENCE, TAG_SEQUENCE] {
+        tbs_certificate.read(tag)?;
+    }
+    let mut subject = Reader::new(tbs_certificate.read(TAG_SEQUENCE)?);
+    while !subject.is_empty() {
+        let mut relative_name = Reader::new(subject.read(TAG_SET)?);
+        while !relative_name.is_empty() {
+            let mut attribute = Reader::new(relative_name.read(TAG_SEQUENCE)?);
+            if attribute.read(TAG_OBJECT_IDENTIFIER)? != OID_SERIAL_NUMBER {
+                continue;
+            }
+            return match attribute.read_any()? {
+                (TAG_PRINTABLE_STRING, value) | (TAG_UTF8_STRING, value) => {
+                    std::str::from_utf8(value).ok()
+                }
+                _ => None,
+            };
+        }
+    }
+    None
+}
+
+/// Returns a reader positioned at the serial number of an X.509 certificate.
+fn tbs_certificate(certificate: &[u8]) -> Option<Reader<'_>> {
+    let mut certificate = Reader::new(Reader::new(certificate).read(TAG_SEQUENCE)?);
+    let mut tbs_certificate = Reader::new(certificate.read(TAG_SEQUENCE)?);
+    if tbs_certificate.peek_tag()? == TAG_CONTEXT_0 {
+        tbs_certificate.read(TAG_CONTEXT_0)?;
+    }
+    Some(tbs_certificate)
+}
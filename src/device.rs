@@ -1,18 +1,92 @@
 use crate::{
-    cbor_info::CBORData, ffi::NonNull, Assertion, AssertionCreator, Credential, CredentialCreator,
-    FidoError, Result, FIDO_OK,
+    cbor_info::CBORData, ctaphid, ffi::NonNull, transport, Assertion, AssertionCreator, Credential,
+    CredentialCreator, FidoError, Result, FIDO_ERR_RX, FIDO_ERR_TIMEOUT, FIDO_OK,
 };
 use bitflags::bitflags;
 use libfido2_sys::*;
-use std::{convert::AsRef, ffi::CStr, ptr, str};
+use std::{
+    convert::{AsRef, TryFrom},
+    ffi::{CStr, CString},
+    os::raw,
+    ptr, str,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// Time to wait for a touch on one device, before checking the next one
+const TOUCH_POLL_MS: raw::c_int = 50;
 
 /// Represents a connection to a FIDO2 device.
-#[derive(PartialEq, Eq)]
 pub struct Device {
     pub(crate) raw: NonNull<fido_dev>,
+    cancel: CancelHandle,
+    timeout: Option<Duration>,
+    // Path of a device opened through the built-in HID backend, which is not exposed by libfido2
+    pub(crate) path: Option<CString>,
 }
 
 impl Device {
+    /// Allocates a new, closed device.
+    pub(crate) fn new() -> Self {
+        unsafe {
+            let raw = fido_dev_new();
+            Device {
+                raw: NonNull::new(raw).unwrap(),
+                cancel: CancelHandle(Arc::new(Mutex::new(RawDevice {
+                    device: raw,
+                    concurrent: false,
+                }))),
+                timeout: None,
+                path: None,
+            }
+        }
+    }
+
+    /// Sets the maximum time requests may take, including waiting for user presence.
+    /// Requests which exceed it fail with an error of kind [`FidoErrorKind::Timeout`],
+    /// and are cancelled on the device.
+    ///
+    /// # Arguments
+    /// - `timeout`: The maximum duration of a request, or `None` to wait indefinitely (default).
+    ///
+    /// # Remarks
+    /// - The timeout can be overridden for a single request through `CredentialCreationData::timeout`
+    ///   and `AssertionCreationData::timeout`.
+    /// - The authenticator may give up earlier by itself, which results in an error of the same kind.
+    ///
+    /// [`FidoErrorKind::Timeout`]: enum.FidoErrorKind.html#variant.Timeout
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.apply_timeout(timeout)?;
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns the timeout set by [`set_timeout`].
+    ///
+    /// [`set_timeout`]: #method.set_timeout
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Returns a [`CancelHandle`] which can be used to cancel pending requests from another thread.
+    ///
+    /// # Remarks
+    /// - This is only supported for some backends, see [`CancelHandle::cancel`].
+    ///
+    /// [`CancelHandle`]: struct.CancelHandle.html
+    /// [`CancelHandle::cancel`]: struct.CancelHandle.html#method.cancel
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Allows [`CancelHandle`]s to cancel pending requests of this device from other threads.
+    /// Only call this for backends which support writing while a request is pending.
+    ///
+    /// [`CancelHandle`]: struct.CancelHandle.html
+    pub(crate) fn allow_concurrent_cancel(&mut self) {
+        self.cancel.0.lock().unwrap().concurrent = true;
+    }
+
     /// Returns the latest mode the device supports.
     pub fn mode(&self) -> DeviceMode {
         unsafe {
@@ -58,6 +132,84 @@ impl Device {
         }
     }
 
+    /// Makes the device identify itself to the user, for example by blinking a LED.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Devices which do not have the `WINK` [capability] fail with `FIDO_ERR_INVALID_COMMAND`,
+    ///   which is of kind [`FidoErrorKind::Unsupported`].
+    /// - The command is sent through a second handle and channel, with the same limitations as
+    ///   [`transact_hid`].
+    ///
+    /// [capability]: struct.CTAPHIDCapabilities.html
+    /// [`FidoErrorKind::Unsupported`]: enum.FidoErrorKind.html#variant.Unsupported
+    /// [`transact_hid`]: #method.transact_hid
+    pub fn wink(&mut self) -> Result<()> {
+        if !self
+            .ctap_hid_info()
+            .capabilities
+            .contains(CTAPHIDCapabilities::WINK)
+        {
+            return Err(FidoError(FIDO_ERR_INVALID_COMMAND as raw::c_int));
+        }
+        self.hid_transaction(CTAP_CMD_WINK as u8, &[]).map(drop)
+    }
+
+    /// Sends a raw CTAPHID message, and returns the [response].
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - The message bypasses libfido2. On Linux, each call opens a second hidraw handle to the device
+    ///   and allocates a new CTAPHID channel on it. Devices opened through a [`Transport`] get a new
+    ///   channel on the same transport. Keepalive messages are skipped.
+    /// - On other platforms, devices opened by path return `FIDO_ERR_INVALID_COMMAND`.
+    /// - The results are undefined if a libfido2 request is pending on the same device meanwhile,
+    ///   for example through another `Device` opened on the same path.
+    /// - The status of the response is its CTAPHID command. This is `command` if it succeeded,
+    ///   and `CTAPHID_ERROR` (`0x3f`) with the error code as data otherwise.
+    ///
+    /// # Arguments
+    /// - `command`: The CTAPHID command, without the initialization frame bit.
+    /// - `payload`: The payload of the message.
+    ///
+    /// [response]: struct.RawResponse.html
+    /// [`Transport`]: trait.Transport.html
+    pub fn transact_hid(&mut self, command: u8, payload: &[u8]) -> Result<RawResponse> {
+        let (status, data) = self.with_channel(|channel| channel.transact_raw(command, payload))?;
+        Ok(RawResponse { status, data })
+    }
+
+    /// Sends a raw CTAP2 command, and returns the [response].
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - The status of the response is its CTAP2 status code, which is `0x00` on success.
+    ///   Errors reported by the device are returned as a response rather than as an error.
+    /// - The command is sent as a `CTAPHID_CBOR` message, with the same second handle, channel and
+    ///   limitations as [`transact_hid`].
+    ///
+    /// # Arguments
+    /// - `command`: The CTAP2 command byte, such as `0x04` for `authenticatorGetInfo`.
+    /// - `payload`: The CBOR encoded parameters of the command, which may be empty.
+    ///
+    /// [response]: struct.RawResponse.html
+    /// [`transact_hid`]: #method.transact_hid
+    pub fn transact_cbor(&mut self, command: u8, payload: &[u8]) -> Result<RawResponse> {
+        let mut request = Vec::with_capacity(1 + payload.len());
+        request.push(command);
+        request.extend_from_slice(payload);
+
+        let mut response = self.hid_transaction(CTAP_CMD_CBOR as u8, &request)?;
+        if response.is_empty() {
+            return Err(FidoError(FIDO_ERR_RX));
+        }
+        let status = response.remove(0);
+        Ok(RawResponse {
+            status,
+            data: response,
+        })
+    }
+
     /// Requests additional [data] stored as CBOR from the device.
     ///
     /// # Remarks
@@ -72,10 +224,10 @@ impl Device {
             };
 
             // Request CBOR information
-            match fido_dev_get_cbor_info(self.raw.as_ptr_mut(), cbor_info.raw.as_ptr_mut()) {
-                FIDO_OK => Ok(cbor_info),
-                err => Err(FidoError(err)),
-            }
+            self.request(None, |device| {
+                fido_dev_get_cbor_info(device, cbor_info.raw.as_ptr_mut())
+            })?;
+            Ok(cbor_info)
         }
     }
 
@@ -89,14 +241,17 @@ impl Device {
         pin: Option<&CStr>,
     ) -> Result<Credential> {
         unsafe {
-            match fido_dev_make_cred(
-                self.raw.as_ptr_mut(),
-                credential.raw_mut().as_ptr_mut(),
-                pin.map(CStr::as_ptr).unwrap_or(ptr::null()),
-            ) {
-                FIDO_OK => Ok(credential.into_inner()),
-                err => Err(FidoError(err)),
+            if credential.enforce_protection && !fido_dev_supports_cred_prot(self.raw.as_ptr()) {
+                return Err(FidoError(FIDO_ERR_UNSUPPORTED_EXTENSION as raw::c_int));
             }
+            self.request(credential.timeout, |device| {
+                fido_dev_make_cred(
+                    device,
+                    credential.raw_mut().as_ptr_mut(),
+                    pin.map(CStr::as_ptr).unwrap_or(ptr::null()),
+                )
+            })?;
+            Ok(credential.into_inner())
         }
     }
 
@@ -110,14 +265,14 @@ impl Device {
         pin: Option<&CStr>,
     ) -> Result<Assertion> {
         unsafe {
-            match fido_dev_get_assert(
-                self.raw.as_ptr_mut(),
-                assertion.raw_mut().as_ptr_mut(),
-                pin.map(CStr::as_ptr).unwrap_or(ptr::null()),
-            ) {
-                FIDO_OK => Ok(assertion.into_inner()),
-                err => Err(FidoError(err)),
-            }
+            self.request(assertion.timeout, |device| {
+                fido_dev_get_assert(
+                    device,
+                    assertion.raw_mut().as_ptr_mut(),
+                    pin.map(CStr::as_ptr).unwrap_or(ptr::null()),
+                )
+            })?;
+            Ok(assertion.into_inner())
         }
     }
 
@@ -132,14 +287,13 @@ impl Device {
     /// - Too many invalid PINs will lock the device.
     pub fn set_pin(&mut self, new_pin: &CStr, old_pin: Option<&CStr>) -> Result<()> {
         unsafe {
-            match fido_dev_set_pin(
-                self.raw.as_ptr_mut(),
-                new_pin.as_ptr(),
-                old_pin.map(CStr::as_ptr).unwrap_or(ptr::null()),
-            ) {
-                FIDO_OK => Ok(()),
-                err => Err(FidoError(err)),
-            }
+            self.request(None, |device| {
+                fido_dev_set_pin(
+                    device,
+                    new_pin.as_ptr(),
+                    old_pin.map(CStr::as_ptr).unwrap_or(ptr::null()),
+                )
+            })
         }
     }
 
@@ -148,15 +302,10 @@ impl Device {
     /// # Remarks
     /// - This is synchronous and will block.
     /// - The process to reset a device is outside the FIDO2 specification and is authenticator dependent.
-    /// Yubico authenticators will return `FIDO_ERR_NOT_ALLOWED` if a reset is issued later than 5 seconds after power-up,
-    /// and `FIDO_ERR_ACTION_TIMEOUT` if the user fails to confirm the reset by touching the key within 30 seconds.
+    ///   Yubico authenticators will return `FIDO_ERR_NOT_ALLOWED` if a reset is issued later than 5 seconds after power-up,
+    ///   and `FIDO_ERR_ACTION_TIMEOUT` if the user fails to confirm the reset by touching the key within 30 seconds.
     pub fn reset(&mut self) -> Result<()> {
-        unsafe {
-            match fido_dev_reset(self.raw.as_ptr_mut()) {
-                FIDO_OK => Ok(()),
-                err => Err(FidoError(err)),
-            }
-        }
+        unsafe { self.request(None, |device| fido_dev_reset(device)) }
     }
 
     /// Returns the amount of PIN tries left before the device locks itself.
@@ -166,20 +315,151 @@ impl Device {
     pub fn retry_count(&mut self) -> Result<i32> {
         unsafe {
             let mut amount = 0;
-            match fido_dev_get_retry_count(self.raw.as_ptr_mut(), &mut amount as *mut _) {
-                FIDO_OK => Ok(amount),
+            self.request(None, |device| {
+                fido_dev_get_retry_count(device, &mut amount as *mut _)
+            })?;
+            Ok(amount)
+        }
+    }
+
+    /// Performs a request with given timeout, or the timeout of the device if `None`.
+    pub(crate) fn request<F>(&mut self, timeout: Option<Duration>, request: F) -> Result<()>
+    where
+        F: FnOnce(*mut fido_dev) -> raw::c_int,
+    {
+        let overridden = timeout.is_some() && timeout != self.timeout;
+        let timeout = timeout.or(self.timeout);
+        if overridden {
+            self.apply_timeout(timeout)?;
+        }
+
+        let start = Instant::now();
+        let result = request(self.raw.as_ptr_mut());
+        if overridden {
+            // This only fails for invalid timeouts, and the previous one was valid
+            let _ = self.apply_timeout(self.timeout);
+        }
+
+        match result {
+            FIDO_OK => Ok(()),
+            // libfido2 reports an expired timeout as a failure to receive
+            FIDO_ERR_RX if timeout.is_some_and(|timeout| start.elapsed() >= timeout) => {
+                // Stop the device from waiting for user presence
+                let _ = self.cancel.send();
+                Err(FidoError(FIDO_ERR_TIMEOUT))
+            }
+            err => Err(FidoError(err)),
+        }
+    }
+
+    /// Sends a CTAPHID message on a separate channel, and returns the payload of the response.
+    fn hid_transaction(&mut self, command: u8, payload: &[u8]) -> Result<Vec<u8>> {
+        self.with_channel(|channel| channel.transact(command, payload))
+    }
+
+    /// Allocates a separate CTAPHID channel on the device, and runs `f` on it.
+    fn with_channel<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&ctaphid::Channel<'_>) -> Result<T>,
+    {
+        match &self.path {
+            #[cfg(target_os = "linux")]
+            Some(path) => {
+                let hidraw = ctaphid::Hidraw::open(path).map_err(|_| FidoError(FIDO_ERR_TX))?;
+                f(&ctaphid::Channel::open(&hidraw, self.timeout)?)
+            }
+            // Other platforms do not allow opening the device a second time in a portable way
+            #[cfg(not(target_os = "linux"))]
+            Some(_) => Err(FidoError(FIDO_ERR_INVALID_COMMAND as raw::c_int)),
+            None => unsafe {
+                let transport = transport::transport(fido_dev_io_handle(self.raw.as_ptr()));
+                f(&ctaphid::Channel::open(transport, self.timeout)?)
+            },
+        }
+    }
+
+    fn apply_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        let ms = match timeout {
+            Some(timeout) => raw::c_int::try_from(timeout.as_millis()).unwrap_or(raw::c_int::MAX),
+            None => -1,
+        };
+        // fido_dev_cancel reads the timeout, so it must not change while a handle cancels
+        let _cancel = self.cancel.0.lock().unwrap();
+        unsafe {
+            match fido_dev_set_timeout(self.raw.as_ptr_mut(), ms) {
+                FIDO_OK => Ok(()),
                 err => Err(FidoError(err)),
             }
         }
     }
 }
 
+/// Returns the first of given devices which is touched, and cancels the others.
+pub(crate) fn select(devices: Vec<Device>, timeout: Option<Duration>) -> Result<Device> {
+    let mut error = FidoError(FIDO_ERR_INVALID_ARGUMENT);
+
+    // Let all devices wait for a touch at once
+    let mut waiting = Vec::with_capacity(devices.len());
+    for mut device in devices {
+        match unsafe { fido_dev_get_touch_begin(device.raw.as_ptr_mut()) } {
+            FIDO_OK => waiting.push(device),
+            err => error = FidoError(err),
+        }
+    }
+
+    let start = Instant::now();
+    while !waiting.is_empty() {
+        if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+            error = FidoError(FIDO_ERR_TIMEOUT);
+            break;
+        }
+
+        let mut i = 0;
+        while i < waiting.len() {
+            let mut touched = 0;
+            let device = waiting[i].raw.as_ptr_mut();
+            match unsafe { fido_dev_get_touch_status(device, &mut touched, TOUCH_POLL_MS) } {
+                FIDO_OK if touched != 0 => {
+                    let device = waiting.swap_remove(i);
+                    for other in &waiting {
+                        // This fails for U2F devices, which stop waiting once they are closed
+                        let _ = other.cancel.send();
+                    }
+                    return Ok(device);
+                }
+                FIDO_OK => i += 1,
+                err => {
+                    error = FidoError(err);
+                    waiting.swap_remove(i);
+                }
+            }
+        }
+    }
+
+    for device in &waiting {
+        let _ = device.cancel.send();
+    }
+    Err(error)
+}
+
 // libfido2_sys guarantees this.
 unsafe impl Send for Device {}
 unsafe impl Sync for Device {}
 
+impl PartialEq for Device {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl Eq for Device {}
+
 impl Drop for Device {
     fn drop(&mut self) {
+        // Make sure outstanding cancel handles can no longer reach the device
+        let mut cancel = self.cancel.0.lock().unwrap();
+        cancel.device = ptr::null_mut();
+
         unsafe {
             let mut device = self.raw.as_ptr_mut();
             // This can return an error
@@ -191,6 +471,72 @@ impl Drop for Device {
     }
 }
 
+/// Handle to cancel pending requests of a [`Device`], obtained through [`Device::cancel_handle`].
+///
+/// The handle can be cloned and sent to other threads, while the device itself is blocked on a request.
+///
+/// [`Device`]: struct.Device.html
+/// [`Device::cancel_handle`]: struct.Device.html#method.cancel_handle
+#[derive(Clone)]
+pub struct CancelHandle(Arc<Mutex<RawDevice>>);
+
+struct RawDevice {
+    // Null once the device has been dropped
+    device: *mut fido_dev,
+    // Whether the backend of the device supports cancelling from another thread
+    concurrent: bool,
+}
+
+// The device is only accessed through the mutex, and only while it is known to be safe,
+// see CancelHandle::cancel.
+unsafe impl Send for RawDevice {}
+
+impl CancelHandle {
+    /// Cancels the pending request of the device.
+    /// The request will fail with an error of kind [`FidoErrorKind::Cancelled`].
+    ///
+    /// # Remarks
+    /// - Cancelling is only supported by FIDO2 devices. U2F devices return `FIDO_ERR_INVALID_ARGUMENT`.
+    /// - This is a no-op if the device has been dropped.
+    /// - Authenticators ignore the cancellation if no request is pending.
+    /// - Cancelling from another thread than the one running the request is only supported for
+    ///   devices opened through a [`Transport`], and for HID devices on Linux (`/dev/hidraw*`).
+    ///   Other devices return `FIDO_ERR_UNSUPPORTED_OPTION`, and their requests are not cancelled.
+    ///
+    /// # Thread safety
+    /// libfido2 (up to 1.14) does not lock a device in `fido_dev_cancel`. It only reads the
+    /// channel, I/O handle and flags set when the device was opened, as well as its timeout,
+    /// and writes a single `CTAPHID_CANCEL` frame. This handle keeps the timeout from changing
+    /// meanwhile. Writing the frame while another thread reads a response is safe for Linux
+    /// hidraw devices, which are written and read with separate system calls,
+    /// and for transports, which must support concurrent reads and writes.
+    ///
+    /// [`FidoErrorKind::Cancelled`]: enum.FidoErrorKind.html#variant.Cancelled
+    /// [`Transport`]: trait.Transport.html
+    pub fn cancel(&self) -> Result<()> {
+        if !self.0.lock().unwrap().concurrent {
+            return Err(FidoError(FIDO_ERR_UNSUPPORTED_OPTION as raw::c_int));
+        }
+        self.send()
+    }
+
+    /// Cancels the pending request of the device, regardless of its backend.
+    /// Only call this on the thread which uses the device, while it does not run a request.
+    pub(crate) fn send(&self) -> Result<()> {
+        let device = self.0.lock().unwrap();
+        if device.device.is_null() {
+            return Ok(());
+        }
+
+        unsafe {
+            match fido_dev_cancel(device.device) {
+                FIDO_OK => Ok(()),
+                err => Err(FidoError(err)),
+            }
+        }
+    }
+}
+
 /// Wrapper that represents an OS-specific path to a device.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct DevicePath<'a>(pub(crate) &'a CStr);
@@ -198,7 +544,7 @@ pub struct DevicePath<'a>(pub(crate) &'a CStr);
 impl<'a> DevicePath<'a> {
     /// Creates a new DevicePath from given `CStr`.
     ///
-    /// # Unsafety
+    /// # Safety
     /// The given `CStr` must contain valid UTF-8.
     pub unsafe fn from_cstr(path: &'a CStr) -> Self {
         DevicePath(path)
@@ -222,6 +568,18 @@ pub enum DeviceMode {
     FidoU2F,
 }
 
+/// Raw response to a message sent with [`Device::transact_hid`] or [`Device::transact_cbor`].
+///
+/// [`Device::transact_hid`]: struct.Device.html#method.transact_hid
+/// [`Device::transact_cbor`]: struct.Device.html#method.transact_cbor
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawResponse {
+    /// The status byte of the response.
+    pub status: u8,
+    /// The remaining data of the response.
+    pub data: Vec<u8>,
+}
+
 /// CTAP HID information.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct CTAPHIDInfo {
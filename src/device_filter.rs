@@ -0,0 +1,110 @@
+use crate::{CBORInfo, DeviceInfo};
+
+/// Criteria which devices must meet to be returned by [`Fido::detect_devices_matching`].
+///
+/// Empty criteria do not restrict anything, so the default filter matches every device.
+///
+/// # Remarks
+/// - `products`: Patterns of the product string, in which `*` matches any sequence of characters.
+///   A device matches if any pattern matches.
+/// - `ctap_versions`: Versions which the device must all support, such as `FIDO_2_0`.
+/// - `options`: Options which the device must all have enabled, such as `rk` or `uv`.
+/// - `aag_uids`: AAGUIDs of the allowed models.
+/// - Matching on CTAP versions, options or AAGUIDs requires the [CBOR info] of the device.
+///
+/// [`Fido::detect_devices_matching`]: struct.Fido.html#method.detect_devices_matching
+/// [CBOR info]: struct.CBORInfo.html
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeviceFilter {
+    pub vendor_ids: Vec<i16>,
+    pub product_ids: Vec<i16>,
+    pub products: Vec<String>,
+    pub ctap_versions: Vec<String>,
+    pub options: Vec<String>,
+    pub aag_uids: Vec<[u8; 16]>,
+}
+
+impl DeviceFilter {
+    /// Returns whether given device matches the filter.
+    ///
+    /// # Remarks
+    /// - Devices without CBOR info do not match if the filter requires it.
+    pub fn matches(&self, info: &DeviceInfo) -> bool {
+        let listed = |allowed: &[i16], id| allowed.is_empty() || allowed.contains(&id);
+        if !listed(&self.vendor_ids, info.vendor_id) || !listed(&self.product_ids, info.product_id)
+        {
+            return false;
+        }
+        if !self.products.is_empty()
+            && !self
+                .products
+                .iter()
+                .any(|pattern| matches_pattern(pattern, &info.product))
+        {
+            return false;
+        }
+
+        if !self.requires_cbor_info() {
+            return true;
+        }
+        match &info.cbor_info {
+            Some(cbor_info) => self.matches_cbor_info(cbor_info),
+            None => false,
+        }
+    }
+
+    /// Returns whether matching requires the CBOR info of devices.
+    pub(crate) fn requires_cbor_info(&self) -> bool {
+        !self.ctap_versions.is_empty() || !self.options.is_empty() || !self.aag_uids.is_empty()
+    }
+
+    fn matches_cbor_info(&self, cbor_info: &CBORInfo) -> bool {
+        let versions = self
+            .ctap_versions
+            .iter()
+            .all(|version| cbor_info.ctap_versions.contains(version));
+        let options = self
+            .options
+            .iter()
+            .all(|option| cbor_info.options.get(option) == Some(&true));
+        let aag_uid = self.aag_uids.is_empty()
+            || cbor_info.aag_uid.as_ref().is_some_and(|aag_uid| {
+                self.aag_uids
+                    .iter()
+                    .any(|allowed| allowed[..] == aag_uid[..])
+            });
+        versions && options && aag_uid
+    }
+}
+
+/// Matches `text` against `pattern`, in which `*` matches any sequence of characters.
+fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    // Positions to retry from when a mismatch follows the last wildcard
+    let (mut p, mut t) = (0, 0);
+    let mut retry = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                retry = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match retry {
+                // Let the wildcard consume one more character
+                Some((wildcard, consumed)) => {
+                    p = wildcard + 1;
+                    t = consumed + 1;
+                    retry = Some((wildcard, consumed + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -1,6 +1,15 @@
-use crate::{device::DevicePath, ffi::NonNull};
+use crate::{device::DevicePath, ffi::NonNull, CBORInfo, Fido, FidoError, Result, FIDO_OK};
 use libfido2_sys::*;
-use std::{ffi::CStr, str};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::{CStr, CString},
+    os::raw,
+    str,
+};
+
+// Upper bound on the amount of devices listed when the caller does not choose one
+pub(crate) const MAX_DEVICES: usize = 64;
 
 /// Owns a list of [information] about found devices.
 ///
@@ -25,7 +34,54 @@ pub struct DeviceInformation<'a> {
     pub product: &'a str,
 }
 
+/// Owned version of [`DeviceInformation`], which does not borrow from a [`DeviceList`].
+///
+/// # Remarks
+/// - `cbor_info` is only available after [`request_cbor_info`], since it requires opening the device.
+/// - Implements `Serialize` and `Deserialize` if the `serde` feature is enabled.
+///   The path is stored as a string.
+///
+/// [`DeviceInformation`]: struct.DeviceInformation.html
+/// [`DeviceList`]: struct.DeviceList.html
+/// [`request_cbor_info`]: #method.request_cbor_info
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeviceInfo {
+    #[cfg_attr(feature = "serde", serde(with = "serde_cstring"))]
+    pub path: CString,
+    pub product_id: i16,
+    pub vendor_id: i16,
+    pub manufacturer: String,
+    pub product: String,
+    pub cbor_info: Option<CBORInfo>,
+}
+
 impl DeviceList {
+    /// Lists at most `max_length` connected devices.
+    pub(crate) fn detect(max_length: usize) -> Self {
+        unsafe {
+            // Allocate empty device list
+            let mut device_list = DeviceList {
+                raw: NonNull::new(fido_dev_info_new(max_length)).unwrap(),
+                length: max_length,
+                found: 0,
+            };
+
+            // Fill list with found devices
+            // This should always return FIDO_OK
+            assert_eq!(
+                fido_dev_info_manifest(
+                    device_list.raw.as_ptr_mut(),
+                    max_length,
+                    &mut device_list.found as *mut _
+                ),
+                FIDO_OK
+            );
+
+            device_list
+        }
+    }
+
     /// Creates an iterator over [information] about found devices.
     ///
     /// [information]: struct.DeviceInformation.html
@@ -73,6 +129,47 @@ impl DeviceList {
     }
 }
 
+impl DeviceInfo {
+    /// Returns the path of the device, which can be used to connect to it.
+    ///
+    /// # Remarks
+    /// - Fails with `FIDO_ERR_INVALID_ARGUMENT` if `path` is not valid UTF-8,
+    ///   which a [`DevicePath`] requires.
+    ///
+    /// [`DevicePath`]: struct.DevicePath.html
+    pub fn device_path(&self) -> Result<DevicePath<'_>> {
+        match str::from_utf8(self.path.to_bytes()) {
+            Ok(_) => Ok(DevicePath(&self.path)),
+            Err(_) => Err(FidoError(FIDO_ERR_INVALID_ARGUMENT as raw::c_int)),
+        }
+    }
+
+    /// Opens the device to request its [CBOR info], and stores it in `cbor_info`.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    ///
+    /// [CBOR info]: struct.CBORInfo.html
+    pub fn request_cbor_info(&mut self, fido: &Fido) -> Result<&CBORInfo> {
+        let mut device = fido.new_device(self.device_path()?)?;
+        let cbor_info = CBORInfo::from(&device.request_cbor_data()?);
+        Ok(self.cbor_info.insert(cbor_info))
+    }
+}
+
+impl From<DeviceInformation<'_>> for DeviceInfo {
+    fn from(info: DeviceInformation<'_>) -> Self {
+        DeviceInfo {
+            path: info.path.0.to_owned(),
+            product_id: info.product_id,
+            vendor_id: info.vendor_id,
+            manufacturer: info.manufacturer.to_owned(),
+            product: info.product.to_owned(),
+            cbor_info: None,
+        }
+    }
+}
+
 // libfido2_sys guarantees this.
 unsafe impl Send for DeviceList {}
 unsafe impl Sync for DeviceList {}
@@ -86,3 +183,25 @@ impl Drop for DeviceList {
         }
     }
 }
+
+// Stores paths as strings, which they are on all supported platforms
+#[cfg(feature = "serde")]
+mod serde_cstring {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+    use std::ffi::CString;
+
+    pub(super) fn serialize<S: Serializer>(
+        path: &CString,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        path.to_str()
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<CString, D::Error> {
+        CString::new(String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
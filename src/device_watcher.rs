@@ -0,0 +1,297 @@
+use crate::{device_list::MAX_DEVICES, DeviceInfo, DeviceList};
+use std::{collections::VecDeque, ffi::CString, thread, time::Duration};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A change in the set of connected devices, reported by a [`DeviceWatcher`].
+///
+/// [`DeviceWatcher`]: struct.DeviceWatcher.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceEvent {
+    /// A device was connected.
+    Added(DeviceInfo),
+    /// The device with given path was disconnected.
+    Removed(CString),
+}
+
+/// Source of connected devices, which a [`DeviceWatcher`] observes for changes.
+///
+/// Implement this to drive a watcher from something other than the devices connected to the system,
+/// for example a fake list of devices in tests.
+///
+/// [`DeviceWatcher`]: struct.DeviceWatcher.html
+pub trait DeviceSource: Send {
+    /// Lists the currently connected devices.
+    fn devices(&mut self) -> Vec<DeviceInfo>;
+
+    /// Blocks until the connected devices may have changed.
+    fn wait(&mut self);
+}
+
+/// The FIDO2 HID devices connected to the system, as listed by [`Fido::detect_devices`].
+///
+/// # Remarks
+/// - On Linux, changes are picked up as soon as hidraw device nodes appear or disappear.
+///   Devices are listed again every polling interval as well, in case a notification was missed.
+/// - On other platforms, devices are only listed every polling interval.
+///
+/// [`Fido::detect_devices`]: struct.Fido.html#method.detect_devices
+pub struct HidDeviceSource {
+    interval: Duration,
+    #[cfg(target_os = "linux")]
+    notifier: Option<hidraw::Notifier>,
+}
+
+impl HidDeviceSource {
+    /// Creates a source which uses notifications of the OS where available,
+    /// and polls every second otherwise.
+    pub fn new() -> Self {
+        HidDeviceSource {
+            interval: POLL_INTERVAL,
+            // Polling still works if notifications are unavailable, e.g. in a sandbox without /dev
+            #[cfg(target_os = "linux")]
+            notifier: hidraw::Notifier::new().ok(),
+        }
+    }
+
+    /// Creates a source which only polls, with given interval.
+    pub fn polling(interval: Duration) -> Self {
+        HidDeviceSource {
+            interval,
+            #[cfg(target_os = "linux")]
+            notifier: None,
+        }
+    }
+}
+
+impl Default for HidDeviceSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DeviceSource for HidDeviceSource {
+    fn devices(&mut self) -> Vec<DeviceInfo> {
+        DeviceList::detect(MAX_DEVICES)
+            .iter()
+            .map(DeviceInfo::from)
+            .collect()
+    }
+
+    fn wait(&mut self) {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(notifier) = &mut self.notifier {
+                notifier.wait(self.interval);
+                return;
+            }
+        }
+        thread::sleep(self.interval);
+    }
+}
+
+/// Reports devices being connected and disconnected, by comparing consecutive lists of devices
+/// obtained from a [`DeviceSource`].
+///
+/// The watcher is an endless, blocking iterator of [`DeviceEvent`]s.
+/// The first events report the devices which are already connected.
+///
+/// [`DeviceSource`]: trait.DeviceSource.html
+/// [`DeviceEvent`]: enum.DeviceEvent.html
+pub struct DeviceWatcher {
+    source: Box<dyn DeviceSource>,
+    // Devices as of the last scan
+    connected: Vec<DeviceInfo>,
+    events: VecDeque<DeviceEvent>,
+    scanned: bool,
+}
+
+impl DeviceWatcher {
+    /// Creates a watcher which observes given source.
+    pub fn new<S: DeviceSource + 'static>(source: S) -> Self {
+        DeviceWatcher {
+            source: Box::new(source),
+            connected: Vec::new(),
+            events: VecDeque::new(),
+            scanned: false,
+        }
+    }
+
+    /// Converts the watcher into an asynchronous stream of [`DeviceEvent`]s.
+    ///
+    /// # Remarks
+    /// - The source is observed on a dedicated thread, which initializes libfido2 first.
+    ///   It stops once the stream has been dropped and the source stops waiting.
+    ///
+    /// [`DeviceEvent`]: enum.DeviceEvent.html
+    #[cfg(feature = "tokio")]
+    pub fn into_stream(mut self) -> DeviceEventStream {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        thread::spawn(move || {
+            crate::init_thread();
+            loop {
+                while let Some(event) = self.events.pop_front() {
+                    if sender.send(event).is_err() {
+                        return;
+                    }
+                }
+                if sender.is_closed() {
+                    return;
+                }
+                self.update();
+            }
+        });
+        DeviceEventStream(receiver)
+    }
+
+    /// Waits for the source to change, unless this is the first scan, and queues the differences.
+    fn update(&mut self) {
+        if self.scanned {
+            self.source.wait();
+        }
+        self.scanned = true;
+
+        let devices = self.source.devices();
+        let connected = &self.connected;
+        let removed = connected
+            .iter()
+            .filter(|info| !devices.contains(info))
+            .map(|info| DeviceEvent::Removed(info.path.clone()));
+        let added = devices
+            .iter()
+            .filter(|info| !connected.contains(info))
+            .map(|info| DeviceEvent::Added(info.clone()));
+        self.events.extend(removed.chain(added));
+        self.connected = devices;
+    }
+}
+
+impl Iterator for DeviceWatcher {
+    type Item = DeviceEvent;
+
+    fn next(&mut self) -> Option<DeviceEvent> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Some(event);
+            }
+            self.update();
+        }
+    }
+}
+
+/// Asynchronous stream of [`DeviceEvent`]s, created by [`DeviceWatcher::into_stream`].
+///
+/// [`DeviceEvent`]: enum.DeviceEvent.html
+/// [`DeviceWatcher::into_stream`]: struct.DeviceWatcher.html#method.into_stream
+#[cfg(feature = "tokio")]
+pub struct DeviceEventStream(tokio::sync::mpsc::UnboundedReceiver<DeviceEvent>);
+
+#[cfg(feature = "tokio")]
+impl DeviceEventStream {
+    /// Waits for the next event.
+    pub async fn next(&mut self) -> Option<DeviceEvent> {
+        self.0.recv().await
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl futures_core::Stream for DeviceEventStream {
+    type Item = DeviceEvent;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<DeviceEvent>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod hidraw {
+    use std::{
+        convert::TryFrom,
+        ffi::CStr,
+        io, mem,
+        os::raw::c_int,
+        ptr,
+        time::{Duration, Instant},
+    };
+
+    const DEV: &[u8] = b"/dev\0";
+    const HIDRAW: &[u8] = b"hidraw";
+
+    /// Watches `/dev` for hidraw device nodes being created, removed, or changing permissions.
+    /// The latter happens when udev grants access to a new node.
+    pub(super) struct Notifier {
+        fd: c_int,
+    }
+
+    impl Notifier {
+        pub(super) fn new() -> io::Result<Self> {
+            unsafe {
+                let fd = libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK);
+                if fd < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                let notifier = Notifier { fd };
+
+                let dev = CStr::from_bytes_with_nul_unchecked(DEV);
+                let mask = libc::IN_CREATE | libc::IN_DELETE | libc::IN_ATTRIB;
+                if libc::inotify_add_watch(fd, dev.as_ptr(), mask) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(notifier)
+            }
+        }
+
+        /// Waits until a hidraw device node changed, or the timeout expired.
+        pub(super) fn wait(&mut self, timeout: Duration) {
+            let deadline = Instant::now() + timeout;
+            loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                let mut fd = libc::pollfd {
+                    fd: self.fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                let ms = c_int::try_from(remaining.as_millis()).unwrap_or(c_int::MAX);
+                // Stop on timeout as well as on errors, in which case the caller simply lists devices again
+                if unsafe { libc::poll(&mut fd, 1, ms) } <= 0 || self.read_events() {
+                    return;
+                }
+            }
+        }
+
+        /// Drains pending events, and returns whether any concerned a hidraw device node.
+        fn read_events(&mut self) -> bool {
+            let mut buffer = [0u8; 4096];
+            let mut changed = false;
+            loop {
+                let read =
+                    unsafe { libc::read(self.fd, buffer.as_mut_ptr() as *mut _, buffer.len()) };
+                if read <= 0 {
+                    return changed;
+                }
+
+                let events = &buffer[..read as usize];
+                let mut offset = 0;
+                while offset + mem::size_of::<libc::inotify_event>() <= events.len() {
+                    let event = unsafe {
+                        ptr::read_unaligned(events[offset..].as_ptr() as *const libc::inotify_event)
+                    };
+                    let name = offset + mem::size_of::<libc::inotify_event>();
+                    offset = name + event.len as usize;
+                    changed |= events[name..offset.min(events.len())].starts_with(HIDRAW);
+                }
+            }
+        }
+    }
+
+    impl Drop for Notifier {
+        fn drop(&mut self) {
+            unsafe {
+                libc::close(self.fd);
+            }
+        }
+    }
+}
@@ -1,13 +1,17 @@
 use std::{
     cmp::{Eq, PartialEq},
-    ffi::CStr,
+    ffi::{c_void, CStr},
     os::raw::c_char,
-    slice, str,
+    ptr, slice, str,
 };
 
+extern "C" {
+    fn free(ptr: *mut c_void);
+}
+
 /// Converts a `*const *mut c_char` to a boxed array of `&str`s.
 ///
-/// # Unsafety
+/// # Safety
 /// - Contained strings must be valid UTF-8.
 pub(crate) unsafe fn convert_cstr_array_ptr<'a>(
     array: *const *mut c_char,
@@ -43,8 +47,69 @@ impl<T: ?Sized> NonNull<T> {
 
 impl<T: ?Sized> PartialEq for NonNull<T> {
     fn eq(&self, other: &Self) -> bool {
-        self.0.eq(&other.0)
+        std::ptr::addr_eq(self.0.as_ptr(), other.0.as_ptr())
     }
 }
 
 impl<T: ?Sized> Eq for NonNull<T> {}
+
+/// A libfido2 allocation, which is freed on drop.
+pub(crate) struct Owned<T> {
+    raw: NonNull<T>,
+    free: unsafe extern "C" fn(*mut *mut T),
+}
+
+impl<T> Owned<T> {
+    /// # Panics
+    /// - When the allocation failed.
+    pub(crate) fn new(raw: *mut T, free: unsafe extern "C" fn(*mut *mut T)) -> Self {
+        Owned {
+            raw: NonNull::new(raw).unwrap(),
+            free,
+        }
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const T {
+        self.raw.as_ptr()
+    }
+
+    pub(crate) fn as_ptr_mut(&mut self) -> *mut T {
+        self.raw.as_ptr_mut()
+    }
+}
+
+impl<T> Drop for Owned<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let mut raw = self.raw.as_ptr_mut();
+            (self.free)(&mut raw as *mut _);
+            assert!(raw.is_null());
+        }
+    }
+}
+
+/// Converts an optional string, such as a PIN, to a pointer which is null if it is absent.
+pub(crate) fn optional_ptr(string: Option<&CStr>) -> *const c_char {
+    string.map(CStr::as_ptr).unwrap_or(ptr::null())
+}
+
+/// Copies a buffer returned by libfido2, which is null if it is empty.
+pub(crate) unsafe fn bytes(data: *const u8, len: usize) -> Vec<u8> {
+    data.as_ref()
+        .map(|ptr| slice::from_raw_parts(ptr, len).to_vec())
+        .unwrap_or_default()
+}
+
+/// Copies a buffer which libfido2 allocated for the caller, and frees it.
+pub(crate) unsafe fn take_bytes(data: *mut u8, len: usize) -> Vec<u8> {
+    let copy = bytes(data, len);
+    free(data as *mut c_void);
+    copy
+}
+
+/// Copies a string returned by libfido2, which is null if it is absent.
+pub(crate) unsafe fn string(string: *const c_char) -> Option<String> {
+    string
+        .as_ref()
+        .map(|string| CStr::from_ptr(string).to_string_lossy().into_owned())
+}
@@ -0,0 +1,111 @@
+use crate::{
+    ffi::{optional_ptr, take_bytes},
+    Device, FidoError, Result,
+};
+use libfido2_sys::*;
+use std::{ffi::CStr, os::raw, ptr};
+
+impl Device {
+    /// Reads the large blob associated with a credential.
+    ///
+    /// # Arguments
+    /// - `key`: The `largeBlobKey` of the credential, which is returned on creation and assertion
+    ///   if the `LARGE_BLOB_KEY` extension is requested.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Returns `None` if no blob is associated with the credential.
+    /// - Devices without the `largeBlobs` option fail with `FIDO_ERR_INVALID_COMMAND`.
+    pub fn large_blob(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        unsafe {
+            let mut blob = ptr::null_mut();
+            let mut len = 0;
+            match self.request(None, |device| {
+                fido_dev_largeblob_get(device, key.as_ptr(), key.len(), &mut blob, &mut len)
+            }) {
+                Ok(()) => Ok(Some(take_bytes(blob, len))),
+                Err(FidoError(err)) if err == FIDO_ERR_NOT_FOUND as raw::c_int => Ok(None),
+                Err(err) => Err(err),
+            }
+        }
+    }
+
+    /// Associates a large blob with a credential, replacing any existing one.
+    ///
+    /// # Arguments
+    /// - `key`: The `largeBlobKey` of the credential.
+    /// - `blob`: The data to store. libfido2 compresses and encrypts it with `key`.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Writing requires the PIN, if one is set.
+    /// - Fails with `FIDO_ERR_LARGEBLOB_STORAGE_FULL` if the array of large blobs would grow too large.
+    /// - Fails with `FIDO_ERR_INVALID_ARGUMENT` if `blob` is empty. libfido2 1.12 also fails with
+    ///   `FIDO_ERR_INTERNAL` if `blob` compresses to less than 16 bytes.
+    pub fn set_large_blob(&mut self, key: &[u8], blob: &[u8], pin: Option<&CStr>) -> Result<()> {
+        unsafe {
+            self.request(None, |device| {
+                fido_dev_largeblob_set(
+                    device,
+                    key.as_ptr(),
+                    key.len(),
+                    blob.as_ptr(),
+                    blob.len(),
+                    optional_ptr(pin),
+                )
+            })
+        }
+    }
+
+    /// Removes the large blob associated with a credential.
+    ///
+    /// # Arguments
+    /// - `key`: The `largeBlobKey` of the credential.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Writing requires the PIN, if one is set.
+    /// - Fails with `FIDO_ERR_NOT_FOUND` if no blob is associated with the credential.
+    pub fn remove_large_blob(&mut self, key: &[u8], pin: Option<&CStr>) -> Result<()> {
+        unsafe {
+            self.request(None, |device| {
+                fido_dev_largeblob_remove(device, key.as_ptr(), key.len(), optional_ptr(pin))
+            })
+        }
+    }
+
+    /// Reads the serialized array of large blobs of all credentials.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - The array is CBOR encoded, without the trailing hash which protects it on the device.
+    pub fn large_blob_array(&mut self) -> Result<Vec<u8>> {
+        unsafe {
+            let mut array = ptr::null_mut();
+            let mut len = 0;
+            self.request(None, |device| {
+                fido_dev_largeblob_get_array(device, &mut array, &mut len)
+            })?;
+            Ok(take_bytes(array, len))
+        }
+    }
+
+    /// Replaces the serialized array of large blobs of all credentials.
+    ///
+    /// # Arguments
+    /// - `array`: A CBOR encoded array, as returned by [`large_blob_array`].
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Writing requires the PIN, if one is set.
+    /// - Entries which no credential can decrypt are kept, but are useless.
+    ///
+    /// [`large_blob_array`]: #method.large_blob_array
+    pub fn set_large_blob_array(&mut self, array: &[u8], pin: Option<&CStr>) -> Result<()> {
+        unsafe {
+            self.request(None, |device| {
+                fido_dev_largeblob_set_array(device, array.as_ptr(), array.len(), optional_ptr(pin))
+            })
+        }
+    }
+}
@@ -1,31 +1,66 @@
 #![allow(dead_code)]
 
 mod assertion;
+#[cfg(feature = "tokio")]
+mod async_device;
+mod auth_data;
+mod authenticator_config;
+mod bio_enrollment;
+mod broadcast;
+mod cbor;
 mod cbor_info;
 mod credential;
+mod credential_management;
+mod ctaphid;
+mod der;
 mod device;
+mod device_filter;
 mod device_list;
+mod device_watcher;
 mod ffi;
+mod large_blob;
 mod public_key;
+mod transport;
+#[cfg(feature = "virtual-authenticator")]
+mod virtual_authenticator;
 
 pub use assertion::*;
+#[cfg(feature = "tokio")]
+pub use async_device::*;
+pub use auth_data::*;
+pub use bio_enrollment::*;
+pub use broadcast::*;
+pub use cbor::CborValue;
 pub use cbor_info::*;
 pub use credential::*;
+pub use credential_management::*;
 pub use device::*;
+pub use device_filter::*;
 pub use device_list::*;
+pub use device_watcher::*;
 pub use public_key::*;
+pub use transport::*;
+#[cfg(feature = "virtual-authenticator")]
+pub use virtual_authenticator::*;
 
 use ffi::NonNull;
 use libfido2_sys::*;
-use std::{error, ffi::CStr, fmt, os::raw, str};
+use std::{cell::Cell, error, ffi::CStr, fmt, os::raw, str, time::Duration};
 
 const FIDO_DEBUG: raw::c_int = libfido2_sys::FIDO_DEBUG as raw::c_int;
 const FIDO_OK: raw::c_int = libfido2_sys::FIDO_OK as raw::c_int;
+const FIDO_ERR_RX: raw::c_int = libfido2_sys::FIDO_ERR_RX as raw::c_int;
+const FIDO_ERR_TIMEOUT: raw::c_int = libfido2_sys::FIDO_ERR_TIMEOUT as raw::c_int;
 
 type Result<T> = std::result::Result<T, FidoError>;
 
 /// The entry point of the library.
 /// All access to FIDO2 dongles goes through methods of this struct.
+///
+/// # Remarks
+/// - libfido2 must be initialized on every thread which uses it, so a `Fido` cannot be sent to
+///   other threads. Threads spawned by this library, for example to broadcast requests,
+///   initialize it by themselves.
 pub struct Fido {
     // Each thread must call fido_init, so Fido must be !Send !Sync
     // Can be replaced with negative trait impl when it is stable
@@ -52,18 +87,44 @@ impl Fido {
     pub fn new_device(&self, path: DevicePath<'_>) -> Result<Device> {
         unsafe {
             // Allocate closed device
-            let mut device = Device {
-                raw: NonNull::new(fido_dev_new()).unwrap(),
-            };
+            let mut device = Device::new();
 
             // Try to open the device
             match fido_dev_open(device.raw.as_ptr_mut(), path.0.as_ptr()) {
-                FIDO_OK => Ok(device),
+                FIDO_OK => {
+                    // Only hidraw devices are known to support cancelling from other threads
+                    if cfg!(target_os = "linux") && path.0.to_bytes().starts_with(b"/dev/hidraw") {
+                        device.allow_concurrent_cancel();
+                    }
+                    device.path = Some(path.0.to_owned());
+                    Ok(device)
+                }
                 err => Err(FidoError(err)),
             }
         }
     }
 
+    /// Opens a new [`Device`] connected to given [`VirtualAuthenticator`].
+    ///
+    /// [`Device`]: struct.Device.html
+    /// [`VirtualAuthenticator`]: struct.VirtualAuthenticator.html
+    #[cfg(feature = "virtual-authenticator")]
+    pub fn new_virtual_device(&self, authenticator: &VirtualAuthenticator) -> Result<Device> {
+        self.new_device_with_transport(authenticator.transport())
+    }
+
+    /// Opens a new [`Device`] which communicates through given [`Transport`],
+    /// instead of through the built-in HID backend of libfido2.
+    ///
+    /// [`Device`]: struct.Device.html
+    /// [`Transport`]: trait.Transport.html
+    pub fn new_device_with_transport<T: Transport + 'static>(
+        &self,
+        transport: T,
+    ) -> Result<Device> {
+        transport::open_device(Box::new(transport))
+    }
+
     /// Creates a new [`CredentialCreator`].
     ///
     /// # Remarks
@@ -108,35 +169,177 @@ impl Fido {
     ///
     /// [`DeviceList`]: struct.DeviceList.html
     pub fn detect_devices(&self, max_length: usize) -> DeviceList {
-        unsafe {
-            // Allocate empty device list
-            let mut device_list = DeviceList {
-                raw: NonNull::new(fido_dev_info_new(max_length)).unwrap(),
-                length: max_length,
-                found: 0,
-            };
-
-            // Fill list with found devices
-            // This should always return FIDO_OK
-            assert_eq!(
-                fido_dev_info_manifest(
-                    device_list.raw.as_ptr_mut(),
-                    max_length,
-                    &mut device_list.found as *mut _
-                ),
-                FIDO_OK
-            );
-
-            device_list
-        }
+        DeviceList::detect(max_length)
+    }
+
+    /// Detects connected FIDO2 devices, and returns [information] about the ones which match given [filter].
+    ///
+    /// # Remarks
+    /// - If the filter requires CBOR info, each device is opened to request it.
+    ///   Devices which fail to provide it are skipped.
+    ///
+    /// [information]: struct.DeviceInfo.html
+    /// [filter]: struct.DeviceFilter.html
+    pub fn detect_devices_matching(&self, filter: &DeviceFilter) -> Vec<DeviceInfo> {
+        DeviceList::detect(device_list::MAX_DEVICES)
+            .iter()
+            .map(DeviceInfo::from)
+            .filter_map(|mut info| {
+                if filter.requires_cbor_info() {
+                    info.request_cbor_info(self).ok()?;
+                }
+                Some(info)
+            })
+            .filter(|info| filter.matches(info))
+            .collect()
+    }
+
+    /// Opens all connected FIDO2 devices, lets them wait for a touch,
+    /// and returns the first device which is touched by the user.
+    ///
+    /// See [`select_device_from`].
+    ///
+    /// [`select_device_from`]: #method.select_device_from
+    pub fn select_device(&self, timeout: Option<Duration>) -> Result<Device> {
+        let devices = DeviceList::detect(device_list::MAX_DEVICES)
+            .iter()
+            // Devices which cannot be opened can not be selected either
+            .filter_map(|info| self.new_device(info.path).ok())
+            .collect();
+        self.select_device_from(devices, timeout)
+    }
+
+    /// Lets given devices wait for a touch, and returns the first device which is touched by the user.
+    /// Requests on the other devices are cancelled, after which they are closed.
+    ///
+    /// # Arguments
+    /// - `devices`: The devices to choose from.
+    /// - `timeout`: The maximum time to wait for a touch, or `None` to wait indefinitely.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Devices are expected to blink while waiting.
+    /// - Devices which fail to wait for a touch, for example because they were unplugged, are skipped.
+    ///   If no device remains, the last error is returned. This is `FIDO_ERR_INVALID_ARGUMENT` if no devices were given.
+    /// - Fails with an error of kind [`FidoErrorKind::Timeout`] if no device was touched in time.
+    ///
+    /// [`FidoErrorKind::Timeout`]: enum.FidoErrorKind.html#variant.Timeout
+    pub fn select_device_from(
+        &self,
+        devices: Vec<Device>,
+        timeout: Option<Duration>,
+    ) -> Result<Device> {
+        device::select(devices, timeout)
+    }
+
+    /// Requests every given device to create a new Credential in parallel,
+    /// and returns the first Credential which is created.
+    /// The requests on the other devices are cancelled, after which they are closed.
+    ///
+    /// # Arguments
+    /// - `devices`: The devices to broadcast the request to.
+    /// - `data`: The Credential to request, which is created separately for each device.
+    /// - `pin`: The PIN to use for every device.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Devices which do not support cancelling from another thread (see [`CancelHandle::cancel`])
+    ///   finish or time out their requests before they are closed.
+    /// - Errors of devices which fail are collected, and only returned if no device completes the request.
+    ///
+    /// [`CancelHandle::cancel`]: struct.CancelHandle.html#method.cancel
+    pub fn broadcast_credential_creation(
+        &self,
+        devices: Vec<Device>,
+        data: CredentialCreationData<'_>,
+        pin: Option<&CStr>,
+    ) -> std::result::Result<Broadcast<Credential>, BroadcastError> {
+        let requests = devices
+            .into_iter()
+            .map(|device| (device, self.new_credential_creator(data)))
+            .collect();
+        let pin = pin.map(CStr::to_owned);
+        broadcast::broadcast(requests, move |device, credential| {
+            device.request_credential_creation(credential, pin.as_deref())
+        })
+    }
+
+    /// Requests every given device to verify an Assertion in parallel,
+    /// and returns the first Assertion which is verified.
+    /// The requests on the other devices are cancelled, after which they are closed.
+    ///
+    /// # Arguments
+    /// - `devices`: The devices to broadcast the request to.
+    /// - `data`: The Assertion to request, which is created separately for each device.
+    /// - `pin`: The PIN to use for every device.
+    ///
+    /// # Remarks
+    /// - This is synchronous and will block.
+    /// - Devices which do not support cancelling from another thread (see [`CancelHandle::cancel`])
+    ///   finish or time out their requests before they are closed.
+    /// - Errors of devices which fail are collected, and only returned if no device completes the request.
+    ///   For example, devices which do not hold any of the allowed credentials fail with `FIDO_ERR_NO_CREDENTIALS`.
+    ///
+    /// [`CancelHandle::cancel`]: struct.CancelHandle.html#method.cancel
+    pub fn broadcast_assertion_verification(
+        &self,
+        devices: Vec<Device>,
+        data: AssertionCreationData<'_>,
+        pin: Option<&CStr>,
+    ) -> std::result::Result<Broadcast<Assertion>, BroadcastError> {
+        let requests = devices
+            .into_iter()
+            .map(|device| (device, self.new_assertion_creator(data)))
+            .collect();
+        let pin = pin.map(CStr::to_owned);
+        broadcast::broadcast(requests, move |device, assertion| {
+            device.request_assertion_verification(assertion, pin.as_deref())
+        })
+    }
+
+    /// Creates a [`DeviceWatcher`] which reports connected and disconnected FIDO2 devices.
+    ///
+    /// [`DeviceWatcher`]: struct.DeviceWatcher.html
+    pub fn watch_devices(&self) -> DeviceWatcher {
+        DeviceWatcher::new(HidDeviceSource::new())
     }
 }
 
+/// Initializes libfido2 on a thread spawned by this library, where no [`Fido`] was created.
+/// This does not disable debug output, which a `Fido` enables for the whole process.
+///
+/// [`Fido`]: struct.Fido.html
+pub(crate) fn init_thread() {
+    thread_local!(static INITIALIZED: Cell<bool> = const { Cell::new(false) });
+    INITIALIZED.with(|initialized| {
+        if !initialized.replace(true) {
+            unsafe { fido_init(0) };
+        }
+    });
+}
+
 /// Contains a FIDO2 error.
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct FidoError(raw::c_int);
 
 impl FidoError {
+    /// Returns the [kind] of this error.
+    ///
+    /// [kind]: enum.FidoErrorKind.html
+    pub fn kind(self) -> FidoErrorKind {
+        match self.0 as u32 {
+            FIDO_ERR_KEEPALIVE_CANCEL => FidoErrorKind::Cancelled,
+            libfido2_sys::FIDO_ERR_TIMEOUT
+            | FIDO_ERR_ACTION_TIMEOUT
+            | FIDO_ERR_USER_ACTION_TIMEOUT => FidoErrorKind::Timeout,
+            FIDO_ERR_INVALID_COMMAND
+            | FIDO_ERR_UNSUPPORTED_OPTION
+            | FIDO_ERR_UNSUPPORTED_EXTENSION
+            | FIDO_ERR_UNSUPPORTED_ALGORITHM => FidoErrorKind::Unsupported,
+            _ => FidoErrorKind::Other,
+        }
+    }
+
     pub(crate) fn as_str(self) -> &'static str {
         unsafe {
             let error_str = fido_strerr(self.0);
@@ -146,6 +349,27 @@ impl FidoError {
     }
 }
 
+/// Classification of a [`FidoError`], for errors which callers commonly need to handle.
+///
+/// [`FidoError`]: struct.FidoError.html
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FidoErrorKind {
+    /// The request was cancelled, for example through a [`CancelHandle`].
+    ///
+    /// [`CancelHandle`]: struct.CancelHandle.html
+    Cancelled,
+    /// The request did not complete in time, for example because the user did not confirm it.
+    /// See [`Device::set_timeout`].
+    ///
+    /// [`Device::set_timeout`]: struct.Device.html#method.set_timeout
+    Timeout,
+    /// The device does not support the requested command, option, extension or algorithm.
+    Unsupported,
+    /// Any other error.
+    Other,
+}
+
 impl error::Error for FidoError {}
 
 impl fmt::Debug for FidoError {
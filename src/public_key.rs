@@ -1,18 +1,447 @@
-use crate::{ffi::NonNull, CredentialType, FidoError, Result, FIDO_OK};
+use crate::{
+    cbor::{self, CborValue},
+    der,
+    ffi::NonNull,
+    CredentialType, FidoError, Result, FIDO_OK,
+};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
 use libfido2_sys::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::os::raw;
 
+// Labels of COSE_Key parameters
+const COSE_KEY_KTY: i128 = 1;
+const COSE_KEY_ALG: i128 = 3;
+const COSE_KEY_CRV: i128 = -1;
+const COSE_KEY_X: i128 = -2;
+const COSE_KEY_Y: i128 = -3;
+const COSE_KEY_RSA_N: i128 = -1;
+const COSE_KEY_RSA_E: i128 = -2;
+
+// Sizes of the raw keys which libfido2 accepts
+const ES256_COORDINATE_LEN: usize = 32;
+const RS256_MODULUS_LEN: usize = 256;
+const RS256_EXPONENT_LEN: usize = 3;
+const EDDSA_KEY_LEN: usize = 32;
+
+// Object identifiers of SubjectPublicKeyInfo algorithms and curves
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const OID_P256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const OID_RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const OID_ED25519: &[u8] = &[0x2b, 0x65, 0x70];
+
+const PEM_BEGIN: &str = "-----BEGIN PUBLIC KEY-----";
+const PEM_END: &str = "-----END PUBLIC KEY-----";
+const PEM_LINE_LEN: usize = 64;
+
+// Parameter values of JSON Web Keys
+const JWK_KTY_EC: &str = "EC";
+const JWK_KTY_RSA: &str = "RSA";
+const JWK_KTY_OKP: &str = "OKP";
+const JWK_CRV_P256: &str = "P-256";
+const JWK_CRV_ED25519: &str = "Ed25519";
+const JWK_ALG_ES256: &str = "ES256";
+const JWK_ALG_RS256: &str = "RS256";
+const JWK_ALG_EDDSA: &str = "EdDSA";
+
+const FIDO_ERR_INVALID_ARGUMENT: raw::c_int = libfido2_sys::FIDO_ERR_INVALID_ARGUMENT;
+const FIDO_ERR_UNSUPPORTED_ALGORITHM: raw::c_int =
+    libfido2_sys::FIDO_ERR_UNSUPPORTED_ALGORITHM as raw::c_int;
+
+/// A JSON Web Key, as defined by RFC 7517, with the parameters of public keys.
+///
+/// # Remarks
+/// - Binary parameters are base64url encoded without padding.
+/// - Implements `Serialize` and `Deserialize` if the `serde` feature is enabled,
+///   which omits parameters that are not set.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Jwk {
+    pub kty: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub crv: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub alg: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub x: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub y: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub n: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub e: Option<String>,
+}
+
 pub enum PublicKey {
     ES256(#[doc(hidden)] ES256),
     RS256(#[doc(hidden)] RS256),
     EDDSA(#[doc(hidden)] EDDSA),
 }
 
-// @TODO add way to create this from <something else>. openssl maybe.
 impl PublicKey {
+    /// Parses a public key in COSE_Key format, as contained in attested credential data.
+    ///
+    /// # Remarks
+    /// - Supports EC2 keys on P-256, OKP keys on Ed25519 and 2048 bit RSA keys,
+    ///   with the algorithm of the matching `CredentialType`.
+    /// - Fails with `FIDO_ERR_INVALID_ARGUMENT` if the data is not a well-formed COSE_Key,
+    ///   or with `FIDO_ERR_UNSUPPORTED_ALGORITHM` if the key type or algorithm is not supported.
+    pub fn from_cose(data: &[u8]) -> Result<PublicKey> {
+        let mut decoder = cbor::Decoder::new(data);
+        let key = match decoder.decode() {
+            Some(key @ CborValue::Map(_)) if decoder.position() == data.len() => key,
+            _ => return Err(invalid_argument()),
+        };
+        let integer = |label| {
+            key.get_label(label)
+                .and_then(CborValue::as_integer)
+                .ok_or_else(invalid_argument)
+        };
+        let bytes = |label| match key.get_label(label) {
+            Some(CborValue::Bytes(bytes)) => Ok(bytes.as_slice()),
+            _ => Err(invalid_argument()),
+        };
+
+        match (integer(COSE_KEY_KTY)?, integer(COSE_KEY_ALG)?) {
+            (kty, alg) if kty == COSE_KTY_EC2.into() && alg == COSE_ES256.into() => {
+                if integer(COSE_KEY_CRV)? != COSE_P256.into() {
+                    return Err(unsupported_algorithm());
+                }
+                PublicKey::from_es256_coordinates(bytes(COSE_KEY_X)?, bytes(COSE_KEY_Y)?)
+            }
+            (kty, alg) if kty == COSE_KTY_OKP.into() && alg == COSE_EDDSA.into() => {
+                if integer(COSE_KEY_CRV)? != COSE_ED25519.into() {
+                    return Err(unsupported_algorithm());
+                }
+                PublicKey::from_eddsa_key(bytes(COSE_KEY_X)?)
+            }
+            (kty, alg) if kty == COSE_KTY_RSA.into() && alg == COSE_RS256.into() => {
+                PublicKey::from_rs256_components(bytes(COSE_KEY_RSA_N)?, bytes(COSE_KEY_RSA_E)?)
+            }
+            _ => Err(unsupported_algorithm()),
+        }
+    }
+
+    /// Encodes this public key in COSE_Key format, in the canonical form which authenticators use.
+    pub fn to_cose(&self) -> Vec<u8> {
+        let entry = |label: i128, value: CborValue| (CborValue::Integer(label), value);
+        let integer = |value: i32| CborValue::Integer(value.into());
+        let bytes = |bytes: &[u8]| CborValue::Bytes(bytes.to_vec());
+        let entries = match self.components() {
+            Components::Ec2 { x, y } => vec![
+                entry(COSE_KEY_KTY, integer(COSE_KTY_EC2 as i32)),
+                entry(COSE_KEY_ALG, integer(COSE_ES256)),
+                entry(COSE_KEY_CRV, integer(COSE_P256 as i32)),
+                entry(COSE_KEY_X, bytes(x)),
+                entry(COSE_KEY_Y, bytes(y)),
+            ],
+            Components::Rsa { n, e } => vec![
+                entry(COSE_KEY_KTY, integer(COSE_KTY_RSA as i32)),
+                entry(COSE_KEY_ALG, integer(COSE_RS256)),
+                entry(COSE_KEY_RSA_N, bytes(n)),
+                entry(COSE_KEY_RSA_E, bytes(e)),
+            ],
+            Components::Okp { x } => vec![
+                entry(COSE_KEY_KTY, integer(COSE_KTY_OKP as i32)),
+                entry(COSE_KEY_ALG, integer(COSE_EDDSA)),
+                entry(COSE_KEY_CRV, integer(COSE_ED25519 as i32)),
+                entry(COSE_KEY_X, bytes(x)),
+            ],
+        };
+        CborValue::Map(entries).encode()
+    }
+
+    /// Parses a public key in DER encoded SubjectPublicKeyInfo format.
+    ///
+    /// # Remarks
+    /// - Supports P-256, Ed25519 and 2048 bit RSA keys. P-256 points must be uncompressed.
+    /// - Fails with `FIDO_ERR_INVALID_ARGUMENT` if the data is not well-formed,
+    ///   or with `FIDO_ERR_UNSUPPORTED_ALGORITHM` if the key type is not supported.
+    pub fn from_der(data: &[u8]) -> Result<PublicKey> {
+        let mut outer = der::Reader::new(data);
+        let mut info =
+            der::Reader::new(outer.read(der::TAG_SEQUENCE).ok_or_else(invalid_argument)?);
+        let mut algorithm =
+            der::Reader::new(info.read(der::TAG_SEQUENCE).ok_or_else(invalid_argument)?);
+        let key = match info.read(der::TAG_BIT_STRING) {
+            // Keys are always a whole amount of bytes
+            Some([0, key @ ..]) if outer.is_empty() && info.is_empty() => key,
+            _ => return Err(invalid_argument()),
+        };
+        let oid = algorithm
+            .read(der::TAG_OBJECT_IDENTIFIER)
+            .ok_or_else(invalid_argument)?;
+        let parameters = if algorithm.is_empty() {
+            None
+        } else {
+            let parameters = algorithm.read_any().ok_or_else(invalid_argument)?;
+            if !algorithm.is_empty() {
+                return Err(invalid_argument());
+            }
+            Some(parameters)
+        };
+
+        match (oid, parameters) {
+            (OID_EC_PUBLIC_KEY, Some((der::TAG_OBJECT_IDENTIFIER, OID_P256))) => match key {
+                [0x04, point @ ..] if point.len() == 2 * ES256_COORDINATE_LEN => {
+                    let (x, y) = point.split_at(ES256_COORDINATE_LEN);
+                    PublicKey::from_es256_coordinates(x, y)
+                }
+                _ => Err(unsupported_algorithm()),
+            },
+            (OID_EC_PUBLIC_KEY, _) => Err(unsupported_algorithm()),
+            (OID_RSA_ENCRYPTION, Some((der::TAG_NULL, []))) => {
+                let mut outer = der::Reader::new(key);
+                let mut rsa_key =
+                    der::Reader::new(outer.read(der::TAG_SEQUENCE).ok_or_else(invalid_argument)?);
+                let mut component = || {
+                    rsa_key
+                        .read(der::TAG_INTEGER)
+                        .and_then(der::unsigned_integer)
+                        .ok_or_else(invalid_argument)
+                };
+                let (n, e) = (component()?, component()?);
+                if !outer.is_empty() || !rsa_key.is_empty() {
+                    return Err(invalid_argument());
+                }
+                PublicKey::from_rs256_components(n, e)
+            }
+            (OID_ED25519, None) => PublicKey::from_eddsa_key(key),
+            (OID_RSA_ENCRYPTION, _) | (OID_ED25519, _) => Err(invalid_argument()),
+            _ => Err(unsupported_algorithm()),
+        }
+    }
+
+    /// Encodes this public key in DER encoded SubjectPublicKeyInfo format.
+    pub fn to_der(&self) -> Vec<u8> {
+        let (algorithm, key) = match self.components() {
+            Components::Ec2 { x, y } => (
+                [
+                    der::encode(der::TAG_OBJECT_IDENTIFIER, OID_EC_PUBLIC_KEY),
+                    der::encode(der::TAG_OBJECT_IDENTIFIER, OID_P256),
+                ]
+                .concat(),
+                [&[0x04], x, y].concat(),
+            ),
+            Components::Rsa { n, e } => (
+                [
+                    der::encode(der::TAG_OBJECT_IDENTIFIER, OID_RSA_ENCRYPTION),
+                    der::encode(der::TAG_NULL, &[]),
+                ]
+                .concat(),
+                der::encode(
+                    der::TAG_SEQUENCE,
+                    &[
+                        der::encode_unsigned_integer(n),
+                        der::encode_unsigned_integer(e),
+                    ]
+                    .concat(),
+                ),
+            ),
+            Components::Okp { x } => (
+                der::encode(der::TAG_OBJECT_IDENTIFIER, OID_ED25519),
+                x.to_vec(),
+            ),
+        };
+        der::encode(
+            der::TAG_SEQUENCE,
+            &[
+                der::encode(der::TAG_SEQUENCE, &algorithm),
+                der::encode(der::TAG_BIT_STRING, &[&[0], key.as_slice()].concat()),
+            ]
+            .concat(),
+        )
+    }
+
+    /// Parses a public key in PEM encoded SubjectPublicKeyInfo format,
+    /// which is labeled `PUBLIC KEY`.
+    ///
+    /// # Remarks
+    /// - Text around the PEM block is ignored.
+    /// - See [`from_der`] for the supported keys.
+    ///
+    /// [`from_der`]: #method.from_der
+    pub fn from_pem(pem: &str) -> Result<PublicKey> {
+        let start = pem.find(PEM_BEGIN).ok_or_else(invalid_argument)? + PEM_BEGIN.len();
+        let end = start + pem[start..].find(PEM_END).ok_or_else(invalid_argument)?;
+        let encoded = pem[start..end].split_whitespace().collect::<String>();
+        let der = STANDARD.decode(encoded).map_err(|_| invalid_argument())?;
+        PublicKey::from_der(&der)
+    }
+
+    /// Encodes this public key in PEM encoded SubjectPublicKeyInfo format,
+    /// which is labeled `PUBLIC KEY`.
+    pub fn to_pem(&self) -> String {
+        let encoded = STANDARD.encode(self.to_der());
+        let mut pem = String::from(PEM_BEGIN);
+        pem.push('\n');
+        for line in encoded.as_bytes().chunks(PEM_LINE_LEN) {
+            // Base64 is ASCII
+            pem.push_str(std::str::from_utf8(line).unwrap());
+            pem.push('\n');
+        }
+        pem.push_str(PEM_END);
+        pem.push('\n');
+        pem
+    }
+
+    /// Parses a public key in [JWK] format.
+    ///
+    /// # Remarks
+    /// - Supports `EC` keys on `P-256`, `OKP` keys on `Ed25519` and 2048 bit `RSA` keys.
+    /// - If `alg` is set, it must match the key type.
+    /// - Fails with `FIDO_ERR_INVALID_ARGUMENT` if the key is not well-formed,
+    ///   or with `FIDO_ERR_UNSUPPORTED_ALGORITHM` if the key type is not supported.
+    ///
+    /// [JWK]: struct.Jwk.html
+    pub fn from_jwk(jwk: &Jwk) -> Result<PublicKey> {
+        let parameter = |parameter: &Option<String>| {
+            parameter
+                .as_ref()
+                .and_then(|parameter| URL_SAFE_NO_PAD.decode(parameter).ok())
+                .ok_or_else(invalid_argument)
+        };
+        let (public_key, alg) = match (jwk.kty.as_str(), jwk.crv.as_deref()) {
+            (JWK_KTY_EC, Some(JWK_CRV_P256)) => (
+                PublicKey::from_es256_coordinates(&parameter(&jwk.x)?, &parameter(&jwk.y)?)?,
+                JWK_ALG_ES256,
+            ),
+            (JWK_KTY_OKP, Some(JWK_CRV_ED25519)) => (
+                PublicKey::from_eddsa_key(&parameter(&jwk.x)?)?,
+                JWK_ALG_EDDSA,
+            ),
+            (JWK_KTY_RSA, None) => (
+                PublicKey::from_rs256_components(&parameter(&jwk.n)?, &parameter(&jwk.e)?)?,
+                JWK_ALG_RS256,
+            ),
+            _ => return Err(unsupported_algorithm()),
+        };
+        match jwk.alg.as_deref() {
+            Some(found) if found != alg => Err(unsupported_algorithm()),
+            _ => Ok(public_key),
+        }
+    }
+
+    /// Encodes this public key in [JWK] format.
+    ///
+    /// [JWK]: struct.Jwk.html
+    pub fn to_jwk(&self) -> Jwk {
+        let encode = |parameter: &[u8]| Some(URL_SAFE_NO_PAD.encode(parameter));
+        match self.components() {
+            Components::Ec2 { x, y } => Jwk {
+                kty: JWK_KTY_EC.to_owned(),
+                crv: Some(JWK_CRV_P256.to_owned()),
+                alg: Some(JWK_ALG_ES256.to_owned()),
+                x: encode(x),
+                y: encode(y),
+                n: None,
+                e: None,
+            },
+            Components::Rsa { n, e } => Jwk {
+                kty: JWK_KTY_RSA.to_owned(),
+                crv: None,
+                alg: Some(JWK_ALG_RS256.to_owned()),
+                x: None,
+                y: None,
+                n: encode(n),
+                e: encode(e),
+            },
+            Components::Okp { x } => Jwk {
+                kty: JWK_KTY_OKP.to_owned(),
+                crv: Some(JWK_CRV_ED25519.to_owned()),
+                alg: Some(JWK_ALG_EDDSA.to_owned()),
+                x: encode(x),
+                y: None,
+                n: None,
+                e: None,
+            },
+        }
+    }
+
+    fn from_es256_coordinates(x: &[u8], y: &[u8]) -> Result<PublicKey> {
+        if x.len() != ES256_COORDINATE_LEN || y.len() != ES256_COORDINATE_LEN {
+            return Err(invalid_argument());
+        }
+        PublicKey::new_es256(&[x, y].concat())
+    }
+
+    fn from_rs256_components(n: &[u8], e: &[u8]) -> Result<PublicKey> {
+        // libfido2 expects both components zero padded to a fixed size
+        let padded = |component: &[u8], len| {
+            let start = component
+                .iter()
+                .position(|&b| b != 0)
+                .unwrap_or(component.len());
+            let component = &component[start..];
+            if component.is_empty() || component.len() > len {
+                return Err(invalid_argument());
+            }
+            let mut padded = vec![0; len - component.len()];
+            padded.extend_from_slice(component);
+            Ok(padded)
+        };
+        let n = padded(n, RS256_MODULUS_LEN)?;
+        let e = padded(e, RS256_EXPONENT_LEN)?;
+        if n[0] & 0x80 == 0 {
+            // Only 2048 bit moduli are supported
+            return Err(unsupported_algorithm());
+        }
+        PublicKey::new_rs256(&[n, e].concat())
+    }
+
+    fn from_eddsa_key(key: &[u8]) -> Result<PublicKey> {
+        if key.len() != EDDSA_KEY_LEN {
+            return Err(invalid_argument());
+        }
+        PublicKey::new_eddsa(key)
+    }
+
+    /// Splits the raw key into its components, without padding.
+    fn components(&self) -> Components<'_> {
+        match self {
+            PublicKey::ES256(pk) => {
+                // libfido2 also accepts the uncompressed point format, which is prefixed
+                let point = match &*pk.1 {
+                    [0x04, point @ ..] if point.len() == 2 * ES256_COORDINATE_LEN => point,
+                    point => point,
+                };
+                let (x, y) = point.split_at(ES256_COORDINATE_LEN);
+                Components::Ec2 { x, y }
+            }
+            PublicKey::RS256(pk) => {
+                let (n, e) = pk.1.split_at(RS256_MODULUS_LEN);
+                let e = &e[e.iter().position(|&b| b != 0).unwrap_or(e.len() - 1)..];
+                Components::Rsa { n, e }
+            }
+            PublicKey::EDDSA(pk) => Components::Okp { x: &pk.1 },
+        }
+    }
+
     pub(crate) fn new_es256(data: &[u8]) -> Result<PublicKey> {
         unsafe {
-            let mut pk = ES256(NonNull::new(es256_pk_new()).unwrap());
+            let mut pk = ES256(NonNull::new(es256_pk_new()).unwrap(), data.into());
             match es256_pk_from_ptr(pk.0.as_ptr_mut(), data as *const _ as *const _, data.len()) {
                 FIDO_OK => Ok(PublicKey::ES256(pk)),
                 err => Err(FidoError(err)),
@@ -22,7 +451,7 @@ impl PublicKey {
 
     pub(crate) fn new_rs256(data: &[u8]) -> Result<PublicKey> {
         unsafe {
-            let mut pk = RS256(NonNull::new(rs256_pk_new()).unwrap());
+            let mut pk = RS256(NonNull::new(rs256_pk_new()).unwrap(), data.into());
             match rs256_pk_from_ptr(pk.0.as_ptr_mut(), data as *const _ as *const _, data.len()) {
                 FIDO_OK => Ok(PublicKey::RS256(pk)),
                 err => Err(FidoError(err)),
@@ -32,7 +461,7 @@ impl PublicKey {
 
     pub(crate) fn new_eddsa(data: &[u8]) -> Result<PublicKey> {
         unsafe {
-            let mut pk = EDDSA(NonNull::new(eddsa_pk_new()).unwrap());
+            let mut pk = EDDSA(NonNull::new(eddsa_pk_new()).unwrap(), data.into());
             match eddsa_pk_from_ptr(pk.0.as_ptr_mut(), data as *const _ as *const _, data.len()) {
                 FIDO_OK => Ok(PublicKey::EDDSA(pk)),
                 err => Err(FidoError(err)),
@@ -57,14 +486,31 @@ impl PublicKey {
     }
 }
 
+// Components of a raw key, named after their COSE key type
+enum Components<'a> {
+    Ec2 { x: &'a [u8], y: &'a [u8] },
+    Rsa { n: &'a [u8], e: &'a [u8] },
+    Okp { x: &'a [u8] },
+}
+
+fn invalid_argument() -> FidoError {
+    FidoError(FIDO_ERR_INVALID_ARGUMENT)
+}
+
+fn unsupported_algorithm() -> FidoError {
+    FidoError(FIDO_ERR_UNSUPPORTED_ALGORITHM)
+}
+
+// The raw key is kept, as libfido2 can not export it
+
 #[doc(hidden)]
-pub struct ES256(pub(crate) NonNull<es256_pk>);
+pub struct ES256(pub(crate) NonNull<es256_pk>, Box<[u8]>);
 
 #[doc(hidden)]
-pub struct RS256(pub(crate) NonNull<rs256_pk>);
+pub struct RS256(pub(crate) NonNull<rs256_pk>, Box<[u8]>);
 
 #[doc(hidden)]
-pub struct EDDSA(pub(crate) NonNull<eddsa_pk>);
+pub struct EDDSA(pub(crate) NonNull<eddsa_pk>, Box<[u8]>);
 
 // libfido2_sys guarantees this.
 unsafe impl Send for ES256 {}
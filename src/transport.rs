@@ -0,0 +1,143 @@
+use crate::{Device, FidoError, Result, FIDO_OK};
+use libfido2_sys::*;
+use std::{
+    cell::RefCell,
+    ffi::CStr,
+    io,
+    os::raw,
+    panic::{self, AssertUnwindSafe},
+    slice,
+    time::Duration,
+};
+
+/// Size of a CTAPHID frame, excluding the HID report ID.
+pub(crate) const FRAME_SIZE: usize = 64;
+
+// libfido2 requires a path to open a device, even if the I/O functions ignore it
+const TRANSPORT_PATH: &[u8] = b"transport\0";
+
+/// Frame level I/O with a device, which can replace the built-in HID backend of libfido2.
+///
+/// A transport exchanges CTAPHID frames (packets) of 64 bytes, excluding any HID report ID.
+/// Framing, channel allocation and the CTAP protocol itself are handled by libfido2.
+///
+/// # Remarks
+/// - Methods take `&self` since `read` and `write` may be called concurrently,
+///   for example when a pending request is cancelled from another thread.
+/// - Errors and panics are reported to libfido2 as `FIDO_ERR_TX` or `FIDO_ERR_RX`.
+pub trait Transport: Send + Sync {
+    /// Called once when the device is opened, before any other method.
+    fn open(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called once when the device is closed. The transport is dropped afterwards.
+    fn close(&self) {}
+
+    /// Reads a single frame into `frame`, and returns the amount of bytes read.
+    ///
+    /// # Arguments
+    /// - `timeout`: The maximum time to wait for a frame, or `None` to wait indefinitely.
+    fn read(&self, frame: &mut [u8], timeout: Option<Duration>) -> io::Result<usize>;
+
+    /// Writes a single frame, and returns the amount of bytes written.
+    fn write(&self, frame: &[u8]) -> io::Result<usize>;
+}
+
+thread_local! {
+    // fido_dev_open calls the open hook on the calling thread, which takes the transport from here
+    static PENDING: RefCell<Option<Box<dyn Transport>>> = RefCell::new(None);
+}
+
+/// Opens a new [`Device`] which communicates through the given transport.
+pub(crate) fn open_device(transport: Box<dyn Transport>) -> Result<Device> {
+    unsafe {
+        // Allocate closed device
+        let mut device = Device::new();
+
+        let io = fido_dev_io {
+            open: Some(io_open),
+            close: Some(io_close),
+            read: Some(io_read),
+            write: Some(io_write),
+        };
+        match fido_dev_set_io_functions(device.raw.as_ptr_mut(), &io as *const _) {
+            FIDO_OK => {}
+            err => return Err(FidoError(err)),
+        }
+
+        // Hand the transport to the open hook and make sure it is dropped if it was never taken
+        PENDING.with(|pending| *pending.borrow_mut() = Some(transport));
+        let path = CStr::from_bytes_with_nul_unchecked(TRANSPORT_PATH);
+        let result = fido_dev_open(device.raw.as_ptr_mut(), path.as_ptr());
+        PENDING.with(|pending| pending.borrow_mut().take());
+
+        match result {
+            FIDO_OK => {
+                device.allow_concurrent_cancel();
+                Ok(device)
+            }
+            err => Err(FidoError(err)),
+        }
+    }
+}
+
+/// Runs `f`, mapping panics and I/O errors to -1 so they do not cross the FFI boundary.
+fn guard<F: FnOnce() -> io::Result<usize>>(f: F) -> raw::c_int {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(n)) => n as raw::c_int,
+        _ => -1,
+    }
+}
+
+/// Returns the transport behind the I/O handle of a device opened through [`open_device`].
+pub(crate) unsafe fn transport<'a>(handle: *mut raw::c_void) -> &'a dyn Transport {
+    &**(handle as *const Box<dyn Transport>)
+}
+
+unsafe extern "C" fn io_open(_path: *const raw::c_char) -> *mut raw::c_void {
+    let transport = match PENDING.with(|pending| pending.borrow_mut().take()) {
+        Some(transport) => transport,
+        None => return std::ptr::null_mut(),
+    };
+    match panic::catch_unwind(AssertUnwindSafe(|| transport.open())) {
+        Ok(Ok(())) => Box::into_raw(Box::new(transport)) as *mut _,
+        _ => std::ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn io_close(handle: *mut raw::c_void) {
+    let transport = Box::from_raw(handle as *mut Box<dyn Transport>);
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| transport.close()));
+}
+
+unsafe extern "C" fn io_read(
+    handle: *mut raw::c_void,
+    buf: *mut raw::c_uchar,
+    len: usize,
+    ms: raw::c_int,
+) -> raw::c_int {
+    let transport = transport(handle);
+    let frame = slice::from_raw_parts_mut(buf, len);
+    // A negative timeout means to wait indefinitely
+    let timeout = if ms < 0 {
+        None
+    } else {
+        Some(Duration::from_millis(ms as u64))
+    };
+    guard(|| transport.read(frame, timeout))
+}
+
+unsafe extern "C" fn io_write(
+    handle: *mut raw::c_void,
+    buf: *const raw::c_uchar,
+    len: usize,
+) -> raw::c_int {
+    let transport = transport(handle);
+    if len == 0 {
+        return -1;
+    }
+    // libfido2 prefixes each frame with the HID report ID, which is not part of CTAPHID
+    let frame = slice::from_raw_parts(buf.add(1), len - 1);
+    guard(|| transport.write(frame).map(|n| n + 1))
+}
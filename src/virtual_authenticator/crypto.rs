@@ -0,0 +1,298 @@
+use ciborium::value::Value;
+use hmac::{Hmac, Mac};
+use p256::{
+    ecdsa::{signature::Signer, DerSignature},
+    elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint},
+    EncodedPoint,
+};
+use rand::{rngs::OsRng, RngCore};
+use rsa::{pkcs1v15, traits::PublicKeyParts, RsaPrivateKey};
+use sha2::{Digest, Sha256};
+
+use cbc::cipher::{block_padding::NoPadding, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+
+// COSE identifiers, see RFC 8152
+const COSE_KTY: i64 = 1;
+const COSE_ALG: i64 = 3;
+const COSE_CRV: i64 = -1;
+const COSE_X: i64 = -2;
+const COSE_Y: i64 = -3;
+const COSE_RSA_N: i64 = -1;
+const COSE_RSA_E: i64 = -2;
+const COSE_ECDH_ES_HKDF_256: i64 = -25;
+
+// Self-signed ES256 attestation certificate, shared by all virtual authenticators
+const ATTESTATION_KEY: [u8; 32] = [
+    0xe8, 0xb3, 0x82, 0xdf, 0x1c, 0x1f, 0xfc, 0xce, 0xdd, 0x6a, 0xb4, 0x36, 0x54, 0x87, 0x6e, 0x09,
+    0x65, 0x0b, 0x74, 0x40, 0x54, 0xb9, 0x55, 0x63, 0x03, 0x65, 0x3d, 0x5f, 0xfe, 0x5a, 0xd3, 0xb1,
+];
+
+pub(super) const ATTESTATION_CERTIFICATE: [u8; 578] = [
+    0x30, 0x82, 0x02, 0x3e, 0x30, 0x82, 0x01, 0xe3, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x14, 0x0a,
+    0xc7, 0xbb, 0x0e, 0xb3, 0xc1, 0x00, 0x84, 0x3c, 0xa4, 0xfc, 0x7e, 0xad, 0xd2, 0x1e, 0x37, 0x4b,
+    0x57, 0x45, 0x0b, 0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x30,
+    0x73, 0x31, 0x0b, 0x30, 0x09, 0x06, 0x03, 0x55, 0x04, 0x06, 0x13, 0x02, 0x4e, 0x4c, 0x31, 0x14,
+    0x30, 0x12, 0x06, 0x03, 0x55, 0x04, 0x0a, 0x0c, 0x0b, 0x6c, 0x69, 0x62, 0x66, 0x69, 0x64, 0x6f,
+    0x32, 0x2d, 0x72, 0x73, 0x31, 0x22, 0x30, 0x20, 0x06, 0x03, 0x55, 0x04, 0x0b, 0x0c, 0x19, 0x41,
+    0x75, 0x74, 0x68, 0x65, 0x6e, 0x74, 0x69, 0x63, 0x61, 0x74, 0x6f, 0x72, 0x20, 0x41, 0x74, 0x74,
+    0x65, 0x73, 0x74, 0x61, 0x74, 0x69, 0x6f, 0x6e, 0x31, 0x2a, 0x30, 0x28, 0x06, 0x03, 0x55, 0x04,
+    0x03, 0x0c, 0x21, 0x6c, 0x69, 0x62, 0x66, 0x69, 0x64, 0x6f, 0x32, 0x2d, 0x72, 0x73, 0x20, 0x56,
+    0x69, 0x72, 0x74, 0x75, 0x61, 0x6c, 0x20, 0x41, 0x75, 0x74, 0x68, 0x65, 0x6e, 0x74, 0x69, 0x63,
+    0x61, 0x74, 0x6f, 0x72, 0x30, 0x20, 0x17, 0x0d, 0x32, 0x36, 0x31, 0x30, 0x31, 0x37, 0x32, 0x32,
+    0x35, 0x30, 0x32, 0x34, 0x5a, 0x18, 0x0f, 0x32, 0x31, 0x32, 0x36, 0x30, 0x39, 0x32, 0x33, 0x32,
+    0x32, 0x35, 0x30, 0x32, 0x34, 0x5a, 0x30, 0x73, 0x31, 0x0b, 0x30, 0x09, 0x06, 0x03, 0x55, 0x04,
+    0x06, 0x13, 0x02, 0x4e, 0x4c, 0x31, 0x14, 0x30, 0x12, 0x06, 0x03, 0x55, 0x04, 0x0a, 0x0c, 0x0b,
+    0x6c, 0x69, 0x62, 0x66, 0x69, 0x64, 0x6f, 0x32, 0x2d, 0x72, 0x73, 0x31, 0x22, 0x30, 0x20, 0x06,
+    0x03, 0x55, 0x04, 0x0b, 0x0c, 0x19, 0x41, 0x75, 0x74, 0x68, 0x65, 0x6e, 0x74, 0x69, 0x63, 0x61,
+    0x74, 0x6f, 0x72, 0x20, 0x41, 0x74, 0x74, 0x65, 0x73, 0x74, 0x61, 0x74, 0x69, 0x6f, 0x6e, 0x31,
+    0x2a, 0x30, 0x28, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x21, 0x6c, 0x69, 0x62, 0x66, 0x69, 0x64,
+    0x6f, 0x32, 0x2d, 0x72, 0x73, 0x20, 0x56, 0x69, 0x72, 0x74, 0x75, 0x61, 0x6c, 0x20, 0x41, 0x75,
+    0x74, 0x68, 0x65, 0x6e, 0x74, 0x69, 0x63, 0x61, 0x74, 0x6f, 0x72, 0x30, 0x59, 0x30, 0x13, 0x06,
+    0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03,
+    0x01, 0x07, 0x03, 0x42, 0x00, 0x04, 0xf6, 0xbc, 0x2a, 0xfd, 0xa8, 0xa6, 0x87, 0x66, 0x5a, 0x22,
+    0x79, 0x3f, 0xbe, 0x38, 0x62, 0xcd, 0xdc, 0x3b, 0x33, 0x34, 0xdd, 0x01, 0x81, 0x15, 0xab, 0x37,
+    0x08, 0x95, 0x67, 0xc6, 0x4a, 0xc5, 0xc3, 0x6b, 0x61, 0xd2, 0x4d, 0x8a, 0x5b, 0xb8, 0xb2, 0xec,
+    0x4c, 0x6b, 0x9f, 0x9d, 0xca, 0xb5, 0xf1, 0x97, 0xdd, 0xdb, 0x11, 0x85, 0x92, 0xcc, 0x19, 0x41,
+    0xac, 0xe9, 0x4e, 0xae, 0x38, 0xbd, 0xa3, 0x53, 0x30, 0x51, 0x30, 0x1d, 0x06, 0x03, 0x55, 0x1d,
+    0x0e, 0x04, 0x16, 0x04, 0x14, 0xe9, 0xea, 0x78, 0xaf, 0x16, 0x4c, 0x11, 0xa0, 0x41, 0xa2, 0x29,
+    0x3b, 0x49, 0x19, 0x04, 0xb2, 0x00, 0xee, 0xe2, 0x58, 0x30, 0x1f, 0x06, 0x03, 0x55, 0x1d, 0x23,
+    0x04, 0x18, 0x30, 0x16, 0x80, 0x14, 0xe9, 0xea, 0x78, 0xaf, 0x16, 0x4c, 0x11, 0xa0, 0x41, 0xa2,
+    0x29, 0x3b, 0x49, 0x19, 0x04, 0xb2, 0x00, 0xee, 0xe2, 0x58, 0x30, 0x0f, 0x06, 0x03, 0x55, 0x1d,
+    0x13, 0x01, 0x01, 0xff, 0x04, 0x05, 0x30, 0x03, 0x01, 0x01, 0xff, 0x30, 0x0a, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x03, 0x49, 0x00, 0x30, 0x46, 0x02, 0x21, 0x00, 0xda,
+    0x7c, 0x46, 0x25, 0xd8, 0x37, 0x24, 0xba, 0xd7, 0xed, 0x96, 0xc8, 0x5c, 0x77, 0xf4, 0x9d, 0xea,
+    0xe0, 0xd7, 0x57, 0x2c, 0x3b, 0xdc, 0xa5, 0x7a, 0x88, 0xa9, 0x23, 0xdc, 0xf2, 0x47, 0x18, 0x02,
+    0x21, 0x00, 0x9b, 0xe9, 0x67, 0x2d, 0xbc, 0x10, 0x97, 0x56, 0x24, 0x0f, 0x48, 0xd4, 0xcb, 0x3e,
+    0xeb, 0x3e, 0x3b, 0x7a, 0x94, 0x96, 0x50, 0x6b, 0x94, 0xc6, 0x3d, 0x53, 0xe3, 0x37, 0x93, 0x83,
+    0xa1, 0xee,
+];
+
+// Enterprise attestation certificate with the same key, whose subject identifies the device
+pub(super) const ENTERPRISE_ATTESTATION_CERTIFICATE: [u8; 616] = [
+    0x30, 0x82, 0x02, 0x64, 0x30, 0x82, 0x02, 0x09, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x14, 0x62,
+    0x1d, 0xcc, 0x51, 0xf0, 0x87, 0x6f, 0xa9, 0xa0, 0xa2, 0xfd, 0x17, 0x6a, 0xd1, 0x48, 0x48, 0x96,
+    0x76, 0xe0, 0x45, 0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x30,
+    0x81, 0x85, 0x31, 0x0b, 0x30, 0x09, 0x06, 0x03, 0x55, 0x04, 0x06, 0x13, 0x02, 0x4e, 0x4c, 0x31,
+    0x14, 0x30, 0x12, 0x06, 0x03, 0x55, 0x04, 0x0a, 0x0c, 0x0b, 0x6c, 0x69, 0x62, 0x66, 0x69, 0x64,
+    0x6f, 0x32, 0x2d, 0x72, 0x73, 0x31, 0x22, 0x30, 0x20, 0x06, 0x03, 0x55, 0x04, 0x0b, 0x0c, 0x19,
+    0x41, 0x75, 0x74, 0x68, 0x65, 0x6e, 0x74, 0x69, 0x63, 0x61, 0x74, 0x6f, 0x72, 0x20, 0x41, 0x74,
+    0x74, 0x65, 0x73, 0x74, 0x61, 0x74, 0x69, 0x6f, 0x6e, 0x31, 0x2a, 0x30, 0x28, 0x06, 0x03, 0x55,
+    0x04, 0x03, 0x0c, 0x21, 0x6c, 0x69, 0x62, 0x66, 0x69, 0x64, 0x6f, 0x32, 0x2d, 0x72, 0x73, 0x20,
+    0x56, 0x69, 0x72, 0x74, 0x75, 0x61, 0x6c, 0x20, 0x41, 0x75, 0x74, 0x68, 0x65, 0x6e, 0x74, 0x69,
+    0x63, 0x61, 0x74, 0x6f, 0x72, 0x31, 0x10, 0x30, 0x0e, 0x06, 0x03, 0x55, 0x04, 0x05, 0x13, 0x07,
+    0x56, 0x41, 0x2d, 0x30, 0x30, 0x30, 0x31, 0x30, 0x20, 0x17, 0x0d, 0x32, 0x36, 0x31, 0x30, 0x31,
+    0x38, 0x30, 0x30, 0x34, 0x38, 0x33, 0x36, 0x5a, 0x18, 0x0f, 0x32, 0x31, 0x32, 0x36, 0x30, 0x39,
+    0x32, 0x34, 0x30, 0x30, 0x34, 0x38, 0x33, 0x36, 0x5a, 0x30, 0x81, 0x85, 0x31, 0x0b, 0x30, 0x09,
+    0x06, 0x03, 0x55, 0x04, 0x06, 0x13, 0x02, 0x4e, 0x4c, 0x31, 0x14, 0x30, 0x12, 0x06, 0x03, 0x55,
+    0x04, 0x0a, 0x0c, 0x0b, 0x6c, 0x69, 0x62, 0x66, 0x69, 0x64, 0x6f, 0x32, 0x2d, 0x72, 0x73, 0x31,
+    0x22, 0x30, 0x20, 0x06, 0x03, 0x55, 0x04, 0x0b, 0x0c, 0x19, 0x41, 0x75, 0x74, 0x68, 0x65, 0x6e,
+    0x74, 0x69, 0x63, 0x61, 0x74, 0x6f, 0x72, 0x20, 0x41, 0x74, 0x74, 0x65, 0x73, 0x74, 0x61, 0x74,
+    0x69, 0x6f, 0x6e, 0x31, 0x2a, 0x30, 0x28, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x21, 0x6c, 0x69,
+    0x62, 0x66, 0x69, 0x64, 0x6f, 0x32, 0x2d, 0x72, 0x73, 0x20, 0x56, 0x69, 0x72, 0x74, 0x75, 0x61,
+    0x6c, 0x20, 0x41, 0x75, 0x74, 0x68, 0x65, 0x6e, 0x74, 0x69, 0x63, 0x61, 0x74, 0x6f, 0x72, 0x31,
+    0x10, 0x30, 0x0e, 0x06, 0x03, 0x55, 0x04, 0x05, 0x13, 0x07, 0x56, 0x41, 0x2d, 0x30, 0x30, 0x30,
+    0x31, 0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08,
+    0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00, 0x04, 0xf6, 0xbc, 0x2a, 0xfd,
+    0xa8, 0xa6, 0x87, 0x66, 0x5a, 0x22, 0x79, 0x3f, 0xbe, 0x38, 0x62, 0xcd, 0xdc, 0x3b, 0x33, 0x34,
+    0xdd, 0x01, 0x81, 0x15, 0xab, 0x37, 0x08, 0x95, 0x67, 0xc6, 0x4a, 0xc5, 0xc3, 0x6b, 0x61, 0xd2,
+    0x4d, 0x8a, 0x5b, 0xb8, 0xb2, 0xec, 0x4c, 0x6b, 0x9f, 0x9d, 0xca, 0xb5, 0xf1, 0x97, 0xdd, 0xdb,
+    0x11, 0x85, 0x92, 0xcc, 0x19, 0x41, 0xac, 0xe9, 0x4e, 0xae, 0x38, 0xbd, 0xa3, 0x53, 0x30, 0x51,
+    0x30, 0x1d, 0x06, 0x03, 0x55, 0x1d, 0x0e, 0x04, 0x16, 0x04, 0x14, 0xe9, 0xea, 0x78, 0xaf, 0x16,
+    0x4c, 0x11, 0xa0, 0x41, 0xa2, 0x29, 0x3b, 0x49, 0x19, 0x04, 0xb2, 0x00, 0xee, 0xe2, 0x58, 0x30,
+    0x1f, 0x06, 0x03, 0x55, 0x1d, 0x23, 0x04, 0x18, 0x30, 0x16, 0x80, 0x14, 0xe9, 0xea, 0x78, 0xaf,
+    0x16, 0x4c, 0x11, 0xa0, 0x41, 0xa2, 0x29, 0x3b, 0x49, 0x19, 0x04, 0xb2, 0x00, 0xee, 0xe2, 0x58,
+    0x30, 0x0f, 0x06, 0x03, 0x55, 0x1d, 0x13, 0x01, 0x01, 0xff, 0x04, 0x05, 0x30, 0x03, 0x01, 0x01,
+    0xff, 0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x03, 0x49, 0x00,
+    0x30, 0x46, 0x02, 0x21, 0x00, 0xa5, 0xca, 0x0a, 0xdc, 0x87, 0x0e, 0x32, 0xf9, 0xa6, 0x50, 0xe7,
+    0xac, 0x3a, 0xac, 0xd1, 0xe6, 0x21, 0x61, 0xd8, 0x82, 0x9f, 0x26, 0x69, 0xc0, 0x64, 0xaa, 0x05,
+    0x52, 0x7c, 0xd2, 0x5b, 0xc5, 0x02, 0x21, 0x00, 0xd5, 0x27, 0x96, 0x00, 0xdf, 0xa7, 0xb0, 0x19,
+    0x9e, 0xd9, 0xeb, 0x05, 0x1e, 0xa1, 0x8a, 0x5e, 0xb9, 0x22, 0x64, 0x7d, 0x44, 0x5d, 0xe7, 0xa4,
+    0x39, 0x37, 0xc2, 0x99, 0xc3, 0x37, 0xc1, 0x26,
+];
+
+/// Private part of a credential key pair.
+#[allow(clippy::upper_case_acronyms)]
+pub(super) enum PrivateKey {
+    ES256(p256::ecdsa::SigningKey),
+    RS256(Box<RsaPrivateKey>),
+    EDDSA(ed25519_dalek::SigningKey),
+}
+
+impl PrivateKey {
+    /// Generates a new key pair for the given COSE algorithm, if it is supported.
+    ///
+    /// # Remarks
+    /// - Generating RS256 keys is slow, especially in unoptimized builds.
+    pub(super) fn generate(algorithm: i64) -> Option<Self> {
+        match algorithm {
+            alg if alg == i64::from(libfido2_sys::COSE_ES256) => Some(PrivateKey::ES256(
+                p256::ecdsa::SigningKey::random(&mut OsRng),
+            )),
+            alg if alg == i64::from(libfido2_sys::COSE_RS256) => {
+                RsaPrivateKey::new(&mut OsRng, 2048)
+                    .ok()
+                    .map(|key| PrivateKey::RS256(Box::new(key)))
+            }
+            alg if alg == i64::from(libfido2_sys::COSE_EDDSA) => Some(PrivateKey::EDDSA(
+                ed25519_dalek::SigningKey::generate(&mut OsRng),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Returns the COSE algorithm of the key pair.
+    pub(super) fn algorithm(&self) -> i32 {
+        match self {
+            PrivateKey::ES256(_) => libfido2_sys::COSE_ES256,
+            PrivateKey::RS256(_) => libfido2_sys::COSE_RS256,
+            PrivateKey::EDDSA(_) => libfido2_sys::COSE_EDDSA,
+        }
+    }
+
+    /// Encodes the public part of the key pair as a COSE_Key.
+    pub(super) fn cose_public_key(&self) -> Value {
+        match self {
+            PrivateKey::ES256(key) => {
+                let point = key.verifying_key().to_encoded_point(false);
+                cose_map(vec![
+                    (COSE_KTY, libfido2_sys::COSE_KTY_EC2.into()),
+                    (COSE_ALG, libfido2_sys::COSE_ES256.into()),
+                    (COSE_CRV, libfido2_sys::COSE_P256.into()),
+                    (COSE_X, point.x().unwrap().as_slice().into()),
+                    (COSE_Y, point.y().unwrap().as_slice().into()),
+                ])
+            }
+            PrivateKey::RS256(key) => cose_map(vec![
+                (COSE_KTY, libfido2_sys::COSE_KTY_RSA.into()),
+                (COSE_ALG, libfido2_sys::COSE_RS256.into()),
+                (COSE_RSA_N, key.n().to_bytes_be().into()),
+                (COSE_RSA_E, key.e().to_bytes_be().into()),
+            ]),
+            PrivateKey::EDDSA(key) => cose_map(vec![
+                (COSE_KTY, libfido2_sys::COSE_KTY_OKP.into()),
+                (COSE_ALG, libfido2_sys::COSE_EDDSA.into()),
+                (COSE_CRV, libfido2_sys::COSE_ED25519.into()),
+                (COSE_X, key.verifying_key().as_bytes().as_ref().into()),
+            ]),
+        }
+    }
+
+    /// Signs `message` in the format expected by WebAuthn for this algorithm.
+    pub(super) fn sign(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            PrivateKey::ES256(key) => {
+                let signature: DerSignature = key.sign(message);
+                signature.as_bytes().to_vec()
+            }
+            PrivateKey::RS256(key) => {
+                let key = pkcs1v15::SigningKey::<Sha256>::new((**key).clone());
+                let signature: pkcs1v15::Signature = key.sign(message);
+                Box::<[u8]>::from(signature).into_vec()
+            }
+            PrivateKey::EDDSA(key) => key.sign(message).to_bytes().to_vec(),
+        }
+    }
+}
+
+/// Signs `message` with the attestation key.
+pub(super) fn sign_attestation(message: &[u8]) -> Vec<u8> {
+    let key = p256::ecdsa::SigningKey::from_slice(&ATTESTATION_KEY).unwrap();
+    let signature: DerSignature = key.sign(message);
+    signature.as_bytes().to_vec()
+}
+
+/// Ephemeral ECDH key pair of PIN protocol one.
+pub(super) struct KeyAgreement(p256::SecretKey);
+
+impl KeyAgreement {
+    pub(super) fn generate() -> Self {
+        KeyAgreement(p256::SecretKey::random(&mut OsRng))
+    }
+
+    /// Encodes the public part of the key pair as a COSE_Key.
+    pub(super) fn cose_public_key(&self) -> Value {
+        let point = self.0.public_key().to_encoded_point(false);
+        cose_map(vec![
+            (COSE_KTY, libfido2_sys::COSE_KTY_EC2.into()),
+            (COSE_ALG, COSE_ECDH_ES_HKDF_256.into()),
+            (COSE_CRV, libfido2_sys::COSE_P256.into()),
+            (COSE_X, point.x().unwrap().as_slice().into()),
+            (COSE_Y, point.y().unwrap().as_slice().into()),
+        ])
+    }
+
+    /// Computes the shared secret with the platform key, which is `SHA-256((baG).x)`.
+    pub(super) fn shared_secret(&self, platform_key: &Value) -> Option<[u8; 32]> {
+        let map = platform_key.as_map()?;
+        let coordinate = |label| {
+            map.iter()
+                .find(|(key, _)| key.as_integer().map(i128::from) == Some(i128::from(label)))
+                .and_then(|(_, value)| value.as_bytes())
+                .filter(|bytes| bytes.len() == 32)
+        };
+        let point = EncodedPoint::from_affine_coordinates(
+            coordinate(COSE_X)?.as_slice().into(),
+            coordinate(COSE_Y)?.as_slice().into(),
+            false,
+        );
+        let public_key =
+            Option::<p256::PublicKey>::from(p256::PublicKey::from_encoded_point(&point))?;
+        let shared = p256::ecdh::diffie_hellman(self.0.to_nonzero_scalar(), public_key.as_affine());
+        Some(sha256(shared.raw_secret_bytes()))
+    }
+}
+
+fn cose_map(entries: Vec<(i64, Value)>) -> Value {
+    Value::Map(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.into(), value))
+            .collect(),
+    )
+}
+
+pub(super) fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+pub(super) fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+/// Computes `LEFT(HMAC-SHA-256(key, message), 16)`, as used by PIN protocol one.
+pub(super) fn authenticate(key: &[u8], message: &[u8]) -> [u8; 16] {
+    let mut pin_auth = [0; 16];
+    pin_auth.copy_from_slice(&hmac_sha256(key, message)[..16]);
+    pin_auth
+}
+
+/// Encrypts with AES-256-CBC, using an all-zero IV and no padding.
+pub(super) fn encrypt(key: &[u8; 32], data: &[u8]) -> Option<Vec<u8>> {
+    if !data.len().is_multiple_of(16) {
+        return None;
+    }
+    Some(
+        cbc::Encryptor::<aes::Aes256>::new(key.into(), &[0; 16].into())
+            .encrypt_padded_vec_mut::<NoPadding>(data),
+    )
+}
+
+/// Decrypts with AES-256-CBC, using an all-zero IV and no padding.
+pub(super) fn decrypt(key: &[u8; 32], data: &[u8]) -> Option<Vec<u8>> {
+    cbc::Decryptor::<aes::Aes256>::new(key.into(), &[0; 16].into())
+        .decrypt_padded_vec_mut::<NoPadding>(data)
+        .ok()
+}
+
+pub(super) fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Compares two byte slices in constant time.
+pub(super) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
@@ -0,0 +1,1506 @@
+use super::{
+    crypto::{self, KeyAgreement, PrivateKey},
+    ctaphid::Cancel,
+};
+use ciborium::value::Value;
+use libfido2_sys::*;
+use std::{convert::TryFrom, sync::Mutex, time::Duration};
+
+// CTAP2 status codes
+pub(super) const CTAP2_OK: u8 = 0x00;
+const CTAP1_ERR_INVALID_COMMAND: u8 = 0x01;
+const CTAP1_ERR_INVALID_PARAMETER: u8 = 0x02;
+const CTAP1_ERR_INVALID_LENGTH: u8 = 0x03;
+const CTAP1_ERR_INVALID_SEQ: u8 = 0x04;
+const CTAP2_ERR_CBOR_UNEXPECTED_TYPE: u8 = 0x11;
+const CTAP2_ERR_INVALID_CBOR: u8 = 0x12;
+const CTAP2_ERR_MISSING_PARAMETER: u8 = 0x14;
+const CTAP2_ERR_LARGE_BLOB_STORAGE_FULL: u8 = 0x18;
+const CTAP2_ERR_CREDENTIAL_EXCLUDED: u8 = 0x19;
+const CTAP2_ERR_UNSUPPORTED_ALGORITHM: u8 = 0x26;
+const CTAP2_ERR_OPERATION_DENIED: u8 = 0x27;
+const CTAP2_ERR_KEY_STORE_FULL: u8 = 0x28;
+const CTAP2_ERR_UNSUPPORTED_OPTION: u8 = 0x2b;
+const CTAP2_ERR_INVALID_OPTION: u8 = 0x2c;
+const CTAP2_ERR_KEEPALIVE_CANCEL: u8 = 0x2d;
+const CTAP2_ERR_NO_CREDENTIALS: u8 = 0x2e;
+const CTAP2_ERR_NOT_ALLOWED: u8 = 0x30;
+const CTAP2_ERR_PIN_INVALID: u8 = 0x31;
+const CTAP2_ERR_PIN_BLOCKED: u8 = 0x32;
+const CTAP2_ERR_PIN_AUTH_INVALID: u8 = 0x33;
+const CTAP2_ERR_PIN_NOT_SET: u8 = 0x35;
+const CTAP2_ERR_PIN_REQUIRED: u8 = 0x36;
+const CTAP2_ERR_PIN_POLICY_VIOLATION: u8 = 0x37;
+const CTAP2_ERR_INTEGRITY_FAILURE: u8 = 0x3c;
+// Vendor status which is never sent, used to process a request again after user presence
+const AWAITING_USER_PRESENCE: u8 = 0xff;
+
+// clientPIN subcommands
+const PIN_GET_RETRIES: u64 = 0x01;
+const PIN_GET_KEY_AGREEMENT: u64 = 0x02;
+const PIN_SET: u64 = 0x03;
+const PIN_CHANGE: u64 = 0x04;
+const PIN_GET_TOKEN: u64 = 0x05;
+
+// credentialManagement subcommands
+const CREDMAN_GET_METADATA: u64 = 0x01;
+const CREDMAN_ENUMERATE_RPS_BEGIN: u64 = 0x02;
+const CREDMAN_ENUMERATE_RPS_NEXT: u64 = 0x03;
+const CREDMAN_ENUMERATE_CREDENTIALS_BEGIN: u64 = 0x04;
+const CREDMAN_ENUMERATE_CREDENTIALS_NEXT: u64 = 0x05;
+const CREDMAN_DELETE_CREDENTIAL: u64 = 0x06;
+const CREDMAN_UPDATE_USER_INFORMATION: u64 = 0x07;
+
+// bioEnrollment subcommands
+const BIO_ENROLL_BEGIN: u64 = 0x01;
+const BIO_ENROLL_CAPTURE_NEXT_SAMPLE: u64 = 0x02;
+const BIO_CANCEL_ENROLLMENT: u64 = 0x03;
+const BIO_ENUMERATE_ENROLLMENTS: u64 = 0x04;
+const BIO_SET_FRIENDLY_NAME: u64 = 0x05;
+const BIO_REMOVE_ENROLLMENT: u64 = 0x06;
+const BIO_GET_SENSOR_INFO: u64 = 0x07;
+
+// authenticatorConfig subcommands
+const CONFIG_ENABLE_ENTERPRISE_ATTESTATION: u64 = 0x01;
+const CONFIG_TOGGLE_ALWAYS_UV: u64 = 0x02;
+const CONFIG_SET_MIN_PIN_LENGTH: u64 = 0x03;
+
+const BIO_MODALITY_FINGERPRINT: u64 = 0x01;
+const BIO_SENSOR_TOUCH: u64 = 0x01;
+const BIO_SAMPLE_GOOD: u64 = 0x00;
+const BIO_MAX_SAMPLES: u8 = 4;
+const BIO_TEMPLATE_ID_LEN: usize = 4;
+
+const PIN_PROTOCOL: u64 = 1;
+const PIN_MAX_RETRIES: u8 = 8;
+const PIN_MIN_LEN: usize = 4;
+const PIN_PADDED_LEN: usize = 64;
+
+const MAX_MSG_SIZE: u64 = 1200;
+const CREDENTIAL_ID_LEN: usize = 32;
+const MAX_RESIDENT_CREDENTIALS: usize = 25;
+const MAX_LARGE_BLOB_ARRAY: usize = 2048;
+const MAX_CRED_BLOB_LEN: usize = 32;
+// Large blobs are transferred in fragments which fit in a message with some overhead
+const MAX_LARGE_BLOB_FRAGMENT: usize = MAX_MSG_SIZE as usize - 64;
+const LARGE_BLOB_HASH_LEN: usize = 16;
+const EXTENSION_HMAC_SECRET: &str = "hmac-secret";
+const EXTENSION_CRED_PROTECT: &str = "credProtect";
+const EXTENSION_CRED_BLOB: &str = "credBlob";
+const EXTENSION_LARGE_BLOB_KEY: &str = "largeBlobKey";
+const EXTENSION_MIN_PIN_LENGTH: &str = "minPinLength";
+const PUBLIC_KEY_TYPE: &str = "public-key";
+
+pub(super) const AAGUID: [u8; 16] = [
+    0x6c, 0x69, 0x62, 0x66, 0x69, 0x64, 0x6f, 0x32, 0x2d, 0x76, 0x69, 0x72, 0x74, 0x75, 0x61, 0x6c,
+];
+
+type CtapResult<T> = std::result::Result<T, u8>;
+
+/// State of a virtual authenticator, shared by all connections to it.
+pub(super) struct Authenticator {
+    pub(super) credentials: Vec<StoredCredential>,
+    pub(super) sign_count: u32,
+    pub(super) user_presence: bool,
+    pub(super) user_presence_delay: Duration,
+    pub(super) winks: usize,
+    pub(super) fingerprints: Vec<Fingerprint>,
+    // Template ID and remaining samples of the fingerprint being enrolled
+    enrollment: Option<(Vec<u8>, u8)>,
+    pin_hash: Option<[u8; 16]>,
+    pin_length: usize,
+    pub(super) pin_retries: u8,
+    pub(super) min_pin_length: usize,
+    // Relying parties which may read the minimum PIN length
+    pub(super) min_pin_length_rp_ids: Vec<String>,
+    pub(super) force_pin_change: bool,
+    pub(super) always_uv: bool,
+    pub(super) enterprise_attestation: bool,
+    // Whether attestation statements are signed with the credential key, without certificate
+    pub(super) self_attestation: bool,
+    pin_token: [u8; 32],
+    key_agreement: KeyAgreement,
+    next_assertions: Vec<Value>,
+    // Remaining responses of a credential enumeration, with the subcommand which fetches them
+    next_enumerated: Option<(u64, Vec<Value>)>,
+    // Serialized large blob array, followed by its truncated hash
+    pub(super) large_blob_array: Vec<u8>,
+    // Fragments of a large blob array being written, with its expected length
+    large_blob_write: Option<(Vec<u8>, usize)>,
+    // Whether the request being processed was cancelled while waiting for user presence,
+    // once that was waited for
+    cancelled: Option<bool>,
+}
+
+/// A fingerprint enrolled on the virtual authenticator.
+pub(super) struct Fingerprint {
+    pub(super) id: Vec<u8>,
+    pub(super) name: Option<String>,
+}
+
+/// A credential created by the virtual authenticator.
+pub(super) struct StoredCredential {
+    pub(super) id: Vec<u8>,
+    pub(super) relying_party_id: String,
+    pub(super) relying_party_name: Option<String>,
+    pub(super) user_id: Vec<u8>,
+    pub(super) user_name: Option<String>,
+    pub(super) user_display_name: Option<String>,
+    pub(super) user_icon: Option<String>,
+    pub(super) resident: bool,
+    // Policy of the credProtect extension, if requested
+    pub(super) protection: Option<u8>,
+    large_blob_key: Option<[u8; 32]>,
+    cred_blob: Option<Vec<u8>>,
+    key: PrivateKey,
+    // Indexed by whether the user was verified
+    cred_random: Option<[[u8; 32]; 2]>,
+}
+
+// Parameters of an authenticatorGetAssertion request
+struct AssertionRequest<'a> {
+    client_data_hash: &'a [u8],
+    user_present: bool,
+    user_verified: bool,
+    hmac_secret: Option<&'a Value>,
+    large_blob_key: bool,
+    cred_blob: bool,
+    // Whether the user info may be returned
+    discoverable: bool,
+}
+
+impl Authenticator {
+    pub(super) fn new() -> Self {
+        Authenticator {
+            credentials: Vec::new(),
+            sign_count: 0,
+            user_presence: true,
+            user_presence_delay: Duration::from_secs(0),
+            winks: 0,
+            fingerprints: Vec::new(),
+            enrollment: None,
+            pin_hash: None,
+            pin_length: 0,
+            pin_retries: PIN_MAX_RETRIES,
+            min_pin_length: PIN_MIN_LEN,
+            min_pin_length_rp_ids: Vec::new(),
+            force_pin_change: false,
+            always_uv: false,
+            enterprise_attestation: false,
+            self_attestation: false,
+            pin_token: crypto::random_bytes(),
+            key_agreement: KeyAgreement::generate(),
+            next_assertions: Vec::new(),
+            next_enumerated: None,
+            large_blob_array: empty_large_blob_array(),
+            large_blob_write: None,
+            cancelled: None,
+        }
+    }
+
+    pub(super) fn capabilities(&mut self) -> u8 {
+        (FIDO_CAP_WINK | FIDO_CAP_CBOR | FIDO_CAP_NMSG) as u8
+    }
+
+    pub(super) fn wink(&mut self) {
+        self.winks += 1;
+    }
+
+    pub(super) fn set_pin(&mut self, pin: &[u8]) {
+        self.pin_hash = Some(left_16(&crypto::sha256(pin)));
+        self.pin_length = pin.len();
+        self.pin_retries = PIN_MAX_RETRIES;
+        self.force_pin_change = false;
+    }
+
+    pub(super) fn has_pin(&self) -> bool {
+        self.pin_hash.is_some()
+    }
+
+    /// Processes a CTAP2 request and returns the response, prefixed with its status code.
+    ///
+    /// The authenticator is unlocked while waiting for user presence, so other requests and
+    /// settings are not blocked meanwhile. The request is then processed again from the start.
+    pub(super) fn process(
+        state: &Mutex<Authenticator>,
+        request: &[u8],
+        cancel: &Cancel,
+    ) -> Vec<u8> {
+        let mut cancelled = None;
+        loop {
+            let mut authenticator = state.lock().unwrap();
+            authenticator.cancelled = cancelled;
+            let response = authenticator.process_command(request);
+            authenticator.cancelled = None;
+            if response != [AWAITING_USER_PRESENCE] {
+                return response;
+            }
+
+            let delay = authenticator.user_presence_delay;
+            drop(authenticator);
+            cancelled = Some(cancel.wait(delay));
+        }
+    }
+
+    fn process_command(&mut self, request: &[u8]) -> Vec<u8> {
+        let (&command, parameters) = match request.split_first() {
+            Some(split) => split,
+            None => return vec![CTAP1_ERR_INVALID_PARAMETER],
+        };
+
+        // Any other command invalidates pending assertions
+        if u32::from(command) != CTAP_CBOR_NEXT_ASSERT {
+            self.next_assertions.clear();
+        }
+        if u32::from(command) != CTAP_CBOR_CRED_MGMT
+            && u32::from(command) != CTAP_CBOR_CRED_MGMT_PRE
+        {
+            self.next_enumerated = None;
+        }
+
+        let response = match u32::from(command) {
+            CTAP_CBOR_GETINFO => Ok(self.get_info()),
+            CTAP_CBOR_RESET => self.reset(),
+            CTAP_CBOR_NEXT_ASSERT => self.get_next_assertion(),
+            CTAP_CBOR_MAKECRED
+            | CTAP_CBOR_ASSERT
+            | CTAP_CBOR_CLIENT_PIN
+            | CTAP_CBOR_CRED_MGMT
+            | CTAP_CBOR_CRED_MGMT_PRE
+            | CTAP_CBOR_BIO_ENROLL
+            | CTAP_CBOR_BIO_ENROLL_PRE
+            | CTAP_CBOR_CONFIG
+            | CTAP_CBOR_LARGEBLOB => {
+                decode_map(parameters).and_then(|parameters| match u32::from(command) {
+                    CTAP_CBOR_MAKECRED => self.make_credential(&parameters),
+                    CTAP_CBOR_ASSERT => self.get_assertion(&parameters),
+                    CTAP_CBOR_CLIENT_PIN => self.client_pin(&parameters),
+                    CTAP_CBOR_BIO_ENROLL | CTAP_CBOR_BIO_ENROLL_PRE => {
+                        self.bio_enrollment(&parameters)
+                    }
+                    CTAP_CBOR_CONFIG => self.config(&parameters),
+                    CTAP_CBOR_LARGEBLOB => self.large_blobs(&parameters),
+                    _ => self.credential_management(&parameters),
+                })
+            }
+            _ => Err(CTAP1_ERR_INVALID_COMMAND),
+        };
+
+        match response {
+            Ok(Some(response)) => {
+                let mut encoded = vec![CTAP2_OK];
+                ciborium::ser::into_writer(&response, &mut encoded).unwrap();
+                encoded
+            }
+            Ok(None) => vec![CTAP2_OK],
+            Err(status) => vec![status],
+        }
+    }
+
+    fn get_info(&self) -> Option<Value> {
+        Some(int_map(vec![
+            (
+                0x01,
+                Value::Array(vec!["FIDO_2_0".into(), "FIDO_2_1".into()]),
+            ),
+            (
+                0x02,
+                Value::Array(vec![
+                    EXTENSION_CRED_BLOB.into(),
+                    EXTENSION_CRED_PROTECT.into(),
+                    EXTENSION_HMAC_SECRET.into(),
+                    EXTENSION_LARGE_BLOB_KEY.into(),
+                    EXTENSION_MIN_PIN_LENGTH.into(),
+                ]),
+            ),
+            (0x03, AAGUID.as_ref().into()),
+            (
+                0x04,
+                Value::Map(vec![
+                    ("ep".into(), self.enterprise_attestation.into()),
+                    ("rk".into(), true.into()),
+                    ("up".into(), true.into()),
+                    ("plat".into(), false.into()),
+                    ("alwaysUv".into(), self.always_uv.into()),
+                    ("credMgmt".into(), true.into()),
+                    ("authnrCfg".into(), true.into()),
+                    ("bioEnroll".into(), (!self.fingerprints.is_empty()).into()),
+                    ("clientPin".into(), self.has_pin().into()),
+                    ("largeBlobs".into(), true.into()),
+                    ("setMinPINLength".into(), true.into()),
+                ]),
+            ),
+            (0x05, MAX_MSG_SIZE.into()),
+            (0x06, Value::Array(vec![PIN_PROTOCOL.into()])),
+            (0x0b, (MAX_LARGE_BLOB_ARRAY as u64).into()),
+            (0x0c, self.force_pin_change.into()),
+            (0x0d, (self.min_pin_length as u64).into()),
+            (0x0f, (MAX_CRED_BLOB_LEN as u64).into()),
+        ]))
+    }
+
+    fn reset(&mut self) -> CtapResult<Option<Value>> {
+        self.check_user_presence()?;
+        *self = Authenticator {
+            user_presence: self.user_presence,
+            user_presence_delay: self.user_presence_delay,
+            winks: self.winks,
+            ..Authenticator::new()
+        };
+        Ok(None)
+    }
+
+    fn make_credential(&mut self, parameters: &[(Value, Value)]) -> CtapResult<Option<Value>> {
+        let client_data_hash = bytes(required(parameters, 0x01)?)?;
+        let relying_party = map(required(parameters, 0x02)?)?;
+        let relying_party_id = text(text_required(relying_party, "id")?)?;
+        let relying_party_name = text_key(relying_party, "name")
+            .map(|value| text(value).map(str::to_owned))
+            .transpose()?;
+        let user = map(required(parameters, 0x03)?)?;
+        let user_id = bytes(text_required(user, "id")?)?;
+        let credential_parameters = array(required(parameters, 0x04)?)?;
+        let exclude_list = optional(parameters, 0x05).map(array).transpose()?;
+        let extensions = optional(parameters, 0x06).map(map).transpose()?;
+        let options = optional(parameters, 0x07).map(map).transpose()?;
+        // Both modes attest every relying party, as if all were listed by the vendor
+        let enterprise_attestation = match optional(parameters, 0x0a).map(unsigned).transpose()? {
+            Some(_) if !self.enterprise_attestation => return Err(CTAP1_ERR_INVALID_PARAMETER),
+            Some(1..=2) => true,
+            Some(_) => return Err(CTAP2_ERR_INVALID_OPTION),
+            None => false,
+        };
+
+        let user_verified = self.verify_pin_auth(parameters, 0x08, 0x09, client_data_hash, true)?;
+        self.check_always_uv(user_verified)?;
+        if option(options, "uv")? == Some(true) {
+            return Err(CTAP2_ERR_UNSUPPORTED_OPTION);
+        }
+        let resident = option(options, "rk")? == Some(true);
+
+        // Pick the first supported algorithm
+        let mut algorithm = None;
+        for credential_parameter in credential_parameters {
+            let credential_parameter = map(credential_parameter)?;
+            if text(text_required(credential_parameter, "type")?)? != PUBLIC_KEY_TYPE {
+                continue;
+            }
+            let alg = integer(text_required(credential_parameter, "alg")?)?;
+            if [COSE_ES256, COSE_RS256, COSE_EDDSA]
+                .iter()
+                .any(|supported| i64::from(*supported) == alg)
+            {
+                algorithm = Some(alg);
+                break;
+            }
+        }
+        let algorithm = algorithm.ok_or(CTAP2_ERR_UNSUPPORTED_ALGORITHM)?;
+
+        if let Some(exclude_list) = exclude_list {
+            for excluded in exclude_list {
+                let excluded = bytes(text_required(map(excluded)?, "id")?)?;
+                if self.credentials.iter().any(|credential| {
+                    credential.relying_party_id == relying_party_id && credential.id == excluded
+                }) {
+                    self.check_user_presence()?;
+                    return Err(CTAP2_ERR_CREDENTIAL_EXCLUDED);
+                }
+            }
+        }
+
+        if resident
+            && self.resident_credentials().count() >= MAX_RESIDENT_CREDENTIALS
+            && !self.resident_credentials().any(|stored| {
+                stored.relying_party_id == relying_party_id && stored.user_id == user_id
+            })
+        {
+            return Err(CTAP2_ERR_KEY_STORE_FULL);
+        }
+
+        let hmac_secret =
+            match extensions.and_then(|extensions| text_key(extensions, EXTENSION_HMAC_SECRET)) {
+                Some(value) => value.as_bool().ok_or(CTAP2_ERR_CBOR_UNEXPECTED_TYPE)?,
+                None => false,
+            };
+        let protection = extensions
+            .and_then(|extensions| text_key(extensions, EXTENSION_CRED_PROTECT))
+            .map(|value| match unsigned(value)? {
+                level @ 1..=3 => Ok(level as u8),
+                _ => Err(CTAP2_ERR_INVALID_OPTION),
+            })
+            .transpose()?;
+        let large_blob_key = match extensions
+            .and_then(|extensions| text_key(extensions, EXTENSION_LARGE_BLOB_KEY))
+        {
+            Some(value) => value.as_bool().ok_or(CTAP2_ERR_CBOR_UNEXPECTED_TYPE)?,
+            None => false,
+        };
+        // Large blobs are only supported for resident credentials
+        if large_blob_key && !resident {
+            return Err(CTAP2_ERR_INVALID_OPTION);
+        }
+        // Blobs which are too large are not stored, which the extension output reports
+        let cred_blob = extensions
+            .and_then(|extensions| text_key(extensions, EXTENSION_CRED_BLOB))
+            .map(bytes)
+            .transpose()?;
+        let cred_blob_stored = cred_blob.map(|blob| blob.len() <= MAX_CRED_BLOB_LEN);
+        // The minimum PIN length is only reported to relying parties which may read it
+        let min_pin_length = match extensions
+            .and_then(|extensions| text_key(extensions, EXTENSION_MIN_PIN_LENGTH))
+        {
+            Some(value) => value.as_bool().ok_or(CTAP2_ERR_CBOR_UNEXPECTED_TYPE)?,
+            None => false,
+        } && self
+            .min_pin_length_rp_ids
+            .iter()
+            .any(|id| id == relying_party_id);
+
+        self.check_user_presence()?;
+
+        let key = PrivateKey::generate(algorithm).ok_or(CTAP2_ERR_UNSUPPORTED_ALGORITHM)?;
+        let optional_text = |key| -> CtapResult<Option<String>> {
+            text_key(user, key)
+                .map(|value| text(value).map(str::to_owned))
+                .transpose()
+        };
+        let credential = StoredCredential {
+            id: crypto::random_bytes::<CREDENTIAL_ID_LEN>().to_vec(),
+            relying_party_id: relying_party_id.to_owned(),
+            relying_party_name,
+            user_id: user_id.to_vec(),
+            user_name: optional_text("name")?,
+            user_display_name: optional_text("displayName")?,
+            user_icon: optional_text("icon")?,
+            resident,
+            protection,
+            large_blob_key: if large_blob_key {
+                Some(crypto::random_bytes())
+            } else {
+                None
+            },
+            cred_blob: cred_blob
+                .filter(|_| cred_blob_stored == Some(true))
+                .map(<[u8]>::to_vec),
+            key,
+            cred_random: if hmac_secret {
+                Some([crypto::random_bytes(), crypto::random_bytes()])
+            } else {
+                None
+            },
+        };
+
+        // Build authenticator data with attested credential data
+        let mut flags = CTAP_AUTHDATA_USER_PRESENT | CTAP_AUTHDATA_ATT_CRED;
+        if user_verified {
+            flags |= CTAP_AUTHDATA_USER_VERIFIED;
+        }
+        let mut extension_outputs = Vec::new();
+        if let Some(stored) = cred_blob_stored {
+            extension_outputs.push((EXTENSION_CRED_BLOB.into(), stored.into()));
+        }
+        if let Some(protection) = protection {
+            extension_outputs.push((EXTENSION_CRED_PROTECT.into(), protection.into()));
+        }
+        if hmac_secret {
+            extension_outputs.push((EXTENSION_HMAC_SECRET.into(), true.into()));
+        }
+        if min_pin_length {
+            extension_outputs.push((
+                EXTENSION_MIN_PIN_LENGTH.into(),
+                (self.min_pin_length as u64).into(),
+            ));
+        }
+        if !extension_outputs.is_empty() {
+            flags |= CTAP_AUTHDATA_EXT_DATA;
+        }
+        self.sign_count += 1;
+
+        let mut auth_data = self.auth_data_header(relying_party_id, flags as u8);
+        auth_data.extend_from_slice(&AAGUID);
+        auth_data.extend_from_slice(&(credential.id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&credential.id);
+        ciborium::ser::into_writer(&credential.key.cose_public_key(), &mut auth_data).unwrap();
+        if !extension_outputs.is_empty() {
+            ciborium::ser::into_writer(&Value::Map(extension_outputs), &mut auth_data).unwrap();
+        }
+
+        let mut signed = auth_data.clone();
+        signed.extend_from_slice(client_data_hash);
+        let attestation_statement = if enterprise_attestation {
+            Value::Map(vec![
+                ("alg".into(), COSE_ES256.into()),
+                ("sig".into(), crypto::sign_attestation(&signed).into()),
+                (
+                    "x5c".into(),
+                    Value::Array(vec![crypto::ENTERPRISE_ATTESTATION_CERTIFICATE
+                        .as_ref()
+                        .into()]),
+                ),
+            ])
+        } else if self.self_attestation {
+            Value::Map(vec![
+                ("alg".into(), credential.key.algorithm().into()),
+                ("sig".into(), credential.key.sign(&signed).into()),
+            ])
+        } else {
+            Value::Map(vec![
+                ("alg".into(), COSE_ES256.into()),
+                ("sig".into(), crypto::sign_attestation(&signed).into()),
+                (
+                    "x5c".into(),
+                    Value::Array(vec![crypto::ATTESTATION_CERTIFICATE.as_ref().into()]),
+                ),
+            ])
+        };
+
+        // A resident credential replaces any other resident credential of the same user
+        if resident {
+            self.credentials.retain(|stored| {
+                !(stored.resident
+                    && stored.relying_party_id == credential.relying_party_id
+                    && stored.user_id == credential.user_id)
+            });
+        }
+        let mut response = vec![
+            (0x01, "packed".into()),
+            (0x02, auth_data.into()),
+            (0x03, attestation_statement),
+        ];
+        if enterprise_attestation {
+            response.push((0x04, true.into()));
+        }
+        if let Some(large_blob_key) = credential.large_blob_key {
+            response.push((0x05, large_blob_key.as_ref().into()));
+        }
+        self.credentials.push(credential);
+
+        Ok(Some(int_map(response)))
+    }
+
+    fn get_assertion(&mut self, parameters: &[(Value, Value)]) -> CtapResult<Option<Value>> {
+        let relying_party_id = text(required(parameters, 0x01)?)?;
+        let client_data_hash = bytes(required(parameters, 0x02)?)?;
+        let allow_list = optional(parameters, 0x03).map(array).transpose()?;
+        let extensions = optional(parameters, 0x04).map(map).transpose()?;
+        let options = optional(parameters, 0x05).map(map).transpose()?;
+
+        let user_verified =
+            self.verify_pin_auth(parameters, 0x06, 0x07, client_data_hash, self.always_uv)?;
+        self.check_always_uv(user_verified)?;
+        if option(options, "uv")? == Some(true) {
+            return Err(CTAP2_ERR_UNSUPPORTED_OPTION);
+        }
+        let user_present = option(options, "up")?.unwrap_or(true);
+
+        // Collect applicable credentials, most recent first
+        let mut applicable = Vec::new();
+        match allow_list {
+            Some(allow_list) if !allow_list.is_empty() => {
+                for allowed in allow_list {
+                    let allowed = bytes(text_required(map(allowed)?, "id")?)?;
+                    if let Some(index) = self.credentials.iter().position(|credential| {
+                        credential.relying_party_id == relying_party_id && credential.id == allowed
+                    }) {
+                        applicable.push(index);
+                        break;
+                    }
+                }
+            }
+            _ => applicable.extend(
+                self.credentials
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .filter(|(_, credential)| {
+                        credential.resident && credential.relying_party_id == relying_party_id
+                    })
+                    .map(|(index, _)| index),
+            ),
+        }
+        let discoverable = allow_list.map(|list| list.is_empty()).unwrap_or(true);
+
+        // Protected credentials are hidden unless the user is verified
+        applicable.retain(|&index| match self.credentials[index].protection {
+            Some(3) => user_verified,
+            Some(2) => user_verified || !discoverable,
+            _ => true,
+        });
+
+        if user_present {
+            self.check_user_presence()?;
+        }
+        if applicable.is_empty() {
+            return Err(CTAP2_ERR_NO_CREDENTIALS);
+        }
+
+        let hmac_secret =
+            extensions.and_then(|extensions| text_key(extensions, EXTENSION_HMAC_SECRET));
+        let large_blob_key = extensions
+            .and_then(|extensions| text_key(extensions, EXTENSION_LARGE_BLOB_KEY))
+            .and_then(Value::as_bool)
+            == Some(true);
+        let cred_blob = extensions
+            .and_then(|extensions| text_key(extensions, EXTENSION_CRED_BLOB))
+            .and_then(Value::as_bool)
+            == Some(true);
+        let request = AssertionRequest {
+            client_data_hash,
+            user_present,
+            user_verified,
+            hmac_secret,
+            large_blob_key,
+            cred_blob,
+            discoverable,
+        };
+
+        let mut assertions = Vec::with_capacity(applicable.len());
+        for index in applicable.iter() {
+            assertions.push(self.assertion(*index, &request)?);
+        }
+
+        // The first response carries the amount of credentials, the rest is fetched with authenticatorGetNextAssertion
+        let mut first = assertions.remove(0);
+        if discoverable && applicable.len() > 1 {
+            if let Value::Map(entries) = &mut first {
+                entries.push((0x05.into(), (applicable.len() as u64).into()));
+            }
+        }
+        assertions.reverse();
+        self.next_assertions = assertions;
+
+        Ok(Some(first))
+    }
+
+    fn get_next_assertion(&mut self) -> CtapResult<Option<Value>> {
+        self.next_assertions
+            .pop()
+            .map(Some)
+            .ok_or(CTAP2_ERR_NOT_ALLOWED)
+    }
+
+    fn assertion(&mut self, index: usize, request: &AssertionRequest<'_>) -> CtapResult<Value> {
+        let mut flags = 0;
+        if request.user_present {
+            flags |= CTAP_AUTHDATA_USER_PRESENT;
+        }
+        if request.user_verified {
+            flags |= CTAP_AUTHDATA_USER_VERIFIED;
+        }
+
+        let credential = &self.credentials[index];
+        let mut extension_outputs = Vec::new();
+        if request.cred_blob {
+            // Credentials without a blob return an empty one
+            let blob = credential.cred_blob.clone().unwrap_or_default();
+            extension_outputs.push((EXTENSION_CRED_BLOB.into(), blob.into()));
+        }
+        if let (Some(hmac_secret), Some(cred_random)) =
+            (request.hmac_secret, credential.cred_random)
+        {
+            let output =
+                self.hmac_secret(hmac_secret, &cred_random[request.user_verified as usize])?;
+            extension_outputs.push((EXTENSION_HMAC_SECRET.into(), output.into()));
+        }
+        if !extension_outputs.is_empty() {
+            flags |= CTAP_AUTHDATA_EXT_DATA;
+        }
+
+        self.sign_count += 1;
+        let credential = &self.credentials[index];
+        let mut auth_data = self.auth_data_header(&credential.relying_party_id, flags as u8);
+        if !extension_outputs.is_empty() {
+            ciborium::ser::into_writer(&Value::Map(extension_outputs), &mut auth_data).unwrap();
+        }
+
+        let mut signed = auth_data.clone();
+        signed.extend_from_slice(request.client_data_hash);
+        let signature = credential.key.sign(&signed);
+
+        let mut response = vec![
+            (
+                0x01,
+                Value::Map(vec![
+                    ("id".into(), credential.id.as_slice().into()),
+                    ("type".into(), PUBLIC_KEY_TYPE.into()),
+                ]),
+            ),
+            (0x02, auth_data.into()),
+            (0x03, signature.into()),
+        ];
+        if credential.resident {
+            let mut user = vec![("id".into(), credential.user_id.as_slice().into())];
+            // User identifiable information is only returned after verification
+            if request.discoverable && request.user_verified {
+                let fields = [
+                    ("name", &credential.user_name),
+                    ("displayName", &credential.user_display_name),
+                    ("icon", &credential.user_icon),
+                ];
+                for (key, value) in fields.iter() {
+                    if let Some(value) = value {
+                        user.push(((*key).into(), value.as_str().into()));
+                    }
+                }
+            }
+            response.push((0x04, Value::Map(user)));
+        }
+        if let (true, Some(large_blob_key)) = (request.large_blob_key, credential.large_blob_key) {
+            response.push((0x07, large_blob_key.as_ref().into()));
+        }
+
+        Ok(int_map(response))
+    }
+
+    /// Computes the encrypted output of the hmac-secret extension.
+    fn hmac_secret(&self, input: &Value, cred_random: &[u8; 32]) -> CtapResult<Vec<u8>> {
+        let input = map(input)?;
+        let shared_secret = self
+            .key_agreement
+            .shared_secret(required(input, 0x01)?)
+            .ok_or(CTAP1_ERR_INVALID_PARAMETER)?;
+        let salt_enc = bytes(required(input, 0x02)?)?;
+        let salt_auth = bytes(required(input, 0x03)?)?;
+
+        if !crypto::constant_time_eq(&crypto::authenticate(&shared_secret, salt_enc), salt_auth) {
+            return Err(CTAP2_ERR_PIN_AUTH_INVALID);
+        }
+        let salts = crypto::decrypt(&shared_secret, salt_enc).ok_or(CTAP1_ERR_INVALID_PARAMETER)?;
+        if salts.len() != 32 && salts.len() != 64 {
+            return Err(CTAP1_ERR_INVALID_PARAMETER);
+        }
+
+        let output = salts
+            .chunks(32)
+            .flat_map(|salt| crypto::hmac_sha256(cred_random, salt).to_vec())
+            .collect::<Vec<u8>>();
+        crypto::encrypt(&shared_secret, &output).ok_or(CTAP1_ERR_INVALID_PARAMETER)
+    }
+
+    fn client_pin(&mut self, parameters: &[(Value, Value)]) -> CtapResult<Option<Value>> {
+        if unsigned(required(parameters, 0x01)?)? != PIN_PROTOCOL {
+            return Err(CTAP1_ERR_INVALID_PARAMETER);
+        }
+
+        match unsigned(required(parameters, 0x02)?)? {
+            PIN_GET_RETRIES => Ok(Some(int_map(vec![(0x03, self.pin_retries.into())]))),
+            PIN_GET_KEY_AGREEMENT => Ok(Some(int_map(vec![(
+                0x01,
+                self.key_agreement.cose_public_key(),
+            )]))),
+            PIN_SET => {
+                let shared_secret = self.shared_secret(parameters)?;
+                let pin_auth = bytes(required(parameters, 0x04)?)?;
+                let new_pin_enc = bytes(required(parameters, 0x05)?)?;
+
+                if self.has_pin() {
+                    return Err(CTAP2_ERR_PIN_AUTH_INVALID);
+                }
+                if !crypto::constant_time_eq(
+                    &crypto::authenticate(&shared_secret, new_pin_enc),
+                    pin_auth,
+                ) {
+                    return Err(CTAP2_ERR_PIN_AUTH_INVALID);
+                }
+                let new_pin = decrypt_pin(&shared_secret, new_pin_enc, self.min_pin_length)?;
+                self.set_pin(&new_pin);
+                Ok(None)
+            }
+            PIN_CHANGE => {
+                let shared_secret = self.shared_secret(parameters)?;
+                let pin_auth = bytes(required(parameters, 0x04)?)?;
+                let new_pin_enc = bytes(required(parameters, 0x05)?)?;
+                let pin_hash_enc = bytes(required(parameters, 0x06)?)?;
+
+                let mut message = new_pin_enc.to_vec();
+                message.extend_from_slice(pin_hash_enc);
+                if !crypto::constant_time_eq(
+                    &crypto::authenticate(&shared_secret, &message),
+                    pin_auth,
+                ) {
+                    return Err(CTAP2_ERR_PIN_AUTH_INVALID);
+                }
+                self.check_pin_hash(&shared_secret, pin_hash_enc)?;
+                let new_pin = decrypt_pin(&shared_secret, new_pin_enc, self.min_pin_length)?;
+                // A forced change has to replace the PIN with a different one
+                if self.force_pin_change
+                    && self.pin_hash == Some(left_16(&crypto::sha256(&new_pin)))
+                {
+                    return Err(CTAP2_ERR_PIN_POLICY_VIOLATION);
+                }
+                self.set_pin(&new_pin);
+                Ok(None)
+            }
+            PIN_GET_TOKEN => {
+                let shared_secret = self.shared_secret(parameters)?;
+                let pin_hash_enc = bytes(required(parameters, 0x06)?)?;
+
+                self.check_pin_hash(&shared_secret, pin_hash_enc)?;
+                if self.force_pin_change {
+                    return Err(CTAP2_ERR_PIN_POLICY_VIOLATION);
+                }
+                let pin_token_enc = crypto::encrypt(&shared_secret, &self.pin_token)
+                    .ok_or(CTAP1_ERR_INVALID_PARAMETER)?;
+                Ok(Some(int_map(vec![(0x02, pin_token_enc.into())])))
+            }
+            _ => Err(CTAP1_ERR_INVALID_PARAMETER),
+        }
+    }
+
+    fn credential_management(
+        &mut self,
+        parameters: &[(Value, Value)],
+    ) -> CtapResult<Option<Value>> {
+        let sub_command = unsigned(required(parameters, 0x01)?)?;
+        let sub_command_parameters = optional(parameters, 0x02).map(map).transpose()?;
+
+        // Continuing an enumeration does not require authentication again
+        if sub_command == CREDMAN_ENUMERATE_RPS_NEXT
+            || sub_command == CREDMAN_ENUMERATE_CREDENTIALS_NEXT
+        {
+            return match &mut self.next_enumerated {
+                Some((next, responses)) if *next == sub_command => {
+                    responses.pop().map(Some).ok_or(CTAP2_ERR_NOT_ALLOWED)
+                }
+                _ => Err(CTAP2_ERR_NOT_ALLOWED),
+            };
+        }
+        self.next_enumerated = None;
+
+        // The PIN token authenticates the subcommand and its parameters
+        let mut message = vec![sub_command as u8];
+        if let Some(value) = optional(parameters, 0x02) {
+            ciborium::ser::into_writer(value, &mut message).unwrap();
+        }
+        if !self.verify_pin_auth(parameters, 0x04, 0x03, &message, true)? {
+            return Err(CTAP2_ERR_PIN_REQUIRED);
+        }
+
+        match sub_command {
+            CREDMAN_GET_METADATA => {
+                let existing = self.resident_credentials().count();
+                Ok(Some(int_map(vec![
+                    (0x01, (existing as u64).into()),
+                    (
+                        0x02,
+                        (MAX_RESIDENT_CREDENTIALS.saturating_sub(existing) as u64).into(),
+                    ),
+                ])))
+            }
+            CREDMAN_ENUMERATE_RPS_BEGIN => {
+                let mut relying_parties: Vec<&StoredCredential> = Vec::new();
+                for credential in self.resident_credentials() {
+                    if !relying_parties
+                        .iter()
+                        .any(|listed| listed.relying_party_id == credential.relying_party_id)
+                    {
+                        relying_parties.push(credential);
+                    }
+                }
+
+                let total = relying_parties.len() as u64;
+                let responses = relying_parties
+                    .iter()
+                    .map(|credential| {
+                        let mut relying_party =
+                            vec![("id".into(), credential.relying_party_id.as_str().into())];
+                        if let Some(name) = &credential.relying_party_name {
+                            relying_party.push(("name".into(), name.as_str().into()));
+                        }
+                        vec![
+                            (0x03, Value::Map(relying_party)),
+                            (
+                                0x04,
+                                crypto::sha256(credential.relying_party_id.as_bytes())
+                                    .as_ref()
+                                    .into(),
+                            ),
+                        ]
+                    })
+                    .collect();
+                self.begin_enumeration(CREDMAN_ENUMERATE_RPS_NEXT, responses, 0x05, total)
+            }
+            CREDMAN_ENUMERATE_CREDENTIALS_BEGIN => {
+                let sub_command_parameters =
+                    sub_command_parameters.ok_or(CTAP2_ERR_MISSING_PARAMETER)?;
+                let relying_party_id_hash = bytes(required(sub_command_parameters, 0x01)?)?;
+
+                let credentials = self
+                    .resident_credentials()
+                    .filter(|credential| {
+                        crypto::sha256(credential.relying_party_id.as_bytes())[..]
+                            == *relying_party_id_hash
+                    })
+                    .collect::<Vec<_>>();
+                let total = credentials.len() as u64;
+                let responses = credentials
+                    .iter()
+                    .map(|credential| {
+                        let mut user = vec![("id".into(), credential.user_id.as_slice().into())];
+                        if let Some(name) = &credential.user_name {
+                            user.push(("name".into(), name.as_str().into()));
+                        }
+                        if let Some(display_name) = &credential.user_display_name {
+                            user.push(("displayName".into(), display_name.as_str().into()));
+                        }
+                        vec![
+                            (0x06, Value::Map(user)),
+                            (
+                                0x07,
+                                Value::Map(vec![
+                                    ("id".into(), credential.id.as_slice().into()),
+                                    ("type".into(), PUBLIC_KEY_TYPE.into()),
+                                ]),
+                            ),
+                            (0x08, credential.key.cose_public_key()),
+                        ]
+                        .into_iter()
+                        .chain(
+                            credential
+                                .protection
+                                .map(|protection| (0x0a, protection.into())),
+                        )
+                        .collect()
+                    })
+                    .collect();
+                self.begin_enumeration(CREDMAN_ENUMERATE_CREDENTIALS_NEXT, responses, 0x09, total)
+            }
+            CREDMAN_DELETE_CREDENTIAL => {
+                let sub_command_parameters =
+                    sub_command_parameters.ok_or(CTAP2_ERR_MISSING_PARAMETER)?;
+                let id = bytes(text_required(
+                    map(required(sub_command_parameters, 0x02)?)?,
+                    "id",
+                )?)?;
+
+                let index = self
+                    .credentials
+                    .iter()
+                    .position(|credential| credential.resident && credential.id == id)
+                    .ok_or(CTAP2_ERR_NO_CREDENTIALS)?;
+                self.credentials.remove(index);
+                Ok(None)
+            }
+            CREDMAN_UPDATE_USER_INFORMATION => {
+                let sub_command_parameters =
+                    sub_command_parameters.ok_or(CTAP2_ERR_MISSING_PARAMETER)?;
+                let id = bytes(text_required(
+                    map(required(sub_command_parameters, 0x02)?)?,
+                    "id",
+                )?)?;
+                let user = map(required(sub_command_parameters, 0x03)?)?;
+                let optional_text = |key| -> CtapResult<Option<String>> {
+                    text_key(user, key)
+                        .map(|value| text(value).map(str::to_owned))
+                        .transpose()
+                };
+
+                let credential = self
+                    .credentials
+                    .iter_mut()
+                    .find(|credential| credential.resident && credential.id == id)
+                    .ok_or(CTAP2_ERR_NO_CREDENTIALS)?;
+                if credential.user_id != bytes(text_required(user, "id")?)? {
+                    return Err(CTAP1_ERR_INVALID_PARAMETER);
+                }
+                // Fields which are absent are removed
+                credential.user_name = optional_text("name")?;
+                credential.user_display_name = optional_text("displayName")?;
+                Ok(None)
+            }
+            _ => Err(CTAP1_ERR_INVALID_PARAMETER),
+        }
+    }
+
+    fn bio_enrollment(&mut self, parameters: &[(Value, Value)]) -> CtapResult<Option<Value>> {
+        let sensor_info = || {
+            Ok(Some(int_map(vec![
+                (0x01, BIO_MODALITY_FINGERPRINT.into()),
+                (0x02, BIO_SENSOR_TOUCH.into()),
+                (0x03, BIO_MAX_SAMPLES.into()),
+            ])))
+        };
+        if optional(parameters, 0x06).and_then(Value::as_bool) == Some(true) {
+            return sensor_info();
+        }
+
+        let modality = unsigned(required(parameters, 0x01)?)?;
+        if modality != BIO_MODALITY_FINGERPRINT {
+            return Err(CTAP2_ERR_UNSUPPORTED_OPTION);
+        }
+        let sub_command = unsigned(required(parameters, 0x02)?)?;
+        let sub_command_parameters = optional(parameters, 0x03).map(map).transpose()?;
+        match sub_command {
+            BIO_GET_SENSOR_INFO => return sensor_info(),
+            BIO_CANCEL_ENROLLMENT => {
+                self.enrollment = None;
+                return Ok(None);
+            }
+            _ => {}
+        }
+
+        // The PIN token authenticates the modality, subcommand and its parameters
+        let mut message = vec![modality as u8, sub_command as u8];
+        if let Some(value) = optional(parameters, 0x03) {
+            ciborium::ser::into_writer(value, &mut message).unwrap();
+        }
+        if !self.verify_pin_auth(parameters, 0x05, 0x04, &message, true)? {
+            return Err(CTAP2_ERR_PIN_REQUIRED);
+        }
+
+        let template_id = || -> CtapResult<&[u8]> {
+            bytes(required(
+                sub_command_parameters.ok_or(CTAP2_ERR_MISSING_PARAMETER)?,
+                0x01,
+            )?)
+        };
+        match sub_command {
+            BIO_ENROLL_BEGIN => {
+                self.enrollment = None;
+                self.check_user_presence()?;
+                let id = crypto::random_bytes::<BIO_TEMPLATE_ID_LEN>().to_vec();
+                let remaining = self.take_sample(id.clone(), BIO_MAX_SAMPLES);
+                Ok(Some(int_map(vec![
+                    (0x04, id.into()),
+                    (0x05, BIO_SAMPLE_GOOD.into()),
+                    (0x06, remaining.into()),
+                ])))
+            }
+            BIO_ENROLL_CAPTURE_NEXT_SAMPLE => {
+                let (id, remaining) = self.enrollment.clone().ok_or(CTAP2_ERR_NOT_ALLOWED)?;
+                if id != template_id()? {
+                    return Err(CTAP1_ERR_INVALID_PARAMETER);
+                }
+                self.check_user_presence()?;
+                let remaining = self.take_sample(id, remaining);
+                Ok(Some(int_map(vec![
+                    (0x05, BIO_SAMPLE_GOOD.into()),
+                    (0x06, remaining.into()),
+                ])))
+            }
+            BIO_ENUMERATE_ENROLLMENTS => {
+                if self.fingerprints.is_empty() {
+                    return Err(CTAP2_ERR_INVALID_OPTION);
+                }
+                let templates = self
+                    .fingerprints
+                    .iter()
+                    .map(|fingerprint| {
+                        let mut template = vec![(0x01, fingerprint.id.as_slice().into())];
+                        if let Some(name) = &fingerprint.name {
+                            template.push((0x02, name.as_str().into()));
+                        }
+                        int_map(template)
+                    })
+                    .collect();
+                Ok(Some(int_map(vec![(0x07, Value::Array(templates))])))
+            }
+            BIO_SET_FRIENDLY_NAME => {
+                let id = template_id()?;
+                let name = text(required(
+                    sub_command_parameters.ok_or(CTAP2_ERR_MISSING_PARAMETER)?,
+                    0x02,
+                )?)?;
+                let fingerprint = self
+                    .fingerprints
+                    .iter_mut()
+                    .find(|fingerprint| fingerprint.id == id)
+                    .ok_or(CTAP2_ERR_INVALID_OPTION)?;
+                fingerprint.name = Some(name.to_owned());
+                Ok(None)
+            }
+            BIO_REMOVE_ENROLLMENT => {
+                let id = template_id()?;
+                let index = self
+                    .fingerprints
+                    .iter()
+                    .position(|fingerprint| fingerprint.id == id)
+                    .ok_or(CTAP2_ERR_INVALID_OPTION)?;
+                self.fingerprints.remove(index);
+                Ok(None)
+            }
+            _ => Err(CTAP1_ERR_INVALID_PARAMETER),
+        }
+    }
+
+    /// Records a good sample of the fingerprint being enrolled, and returns the remaining amount.
+    /// The fingerprint is stored once no more samples are required.
+    fn config(&mut self, parameters: &[(Value, Value)]) -> CtapResult<Option<Value>> {
+        let sub_command = unsigned(required(parameters, 0x01)?)?;
+        let sub_command_parameters = optional(parameters, 0x02).map(map).transpose()?;
+
+        // The PIN token authenticates the command, subcommand and its parameters
+        let mut message = vec![0xff; 32];
+        message.extend_from_slice(&[CTAP_CBOR_CONFIG as u8, sub_command as u8]);
+        if let Some(value) = optional(parameters, 0x02) {
+            ciborium::ser::into_writer(value, &mut message).unwrap();
+        }
+        self.verify_pin_auth(parameters, 0x04, 0x03, &message, true)?;
+
+        match sub_command {
+            CONFIG_ENABLE_ENTERPRISE_ATTESTATION => {
+                self.enterprise_attestation = true;
+                Ok(None)
+            }
+            CONFIG_TOGGLE_ALWAYS_UV => {
+                self.always_uv = !self.always_uv;
+                Ok(None)
+            }
+            CONFIG_SET_MIN_PIN_LENGTH => {
+                let sub_command_parameters = sub_command_parameters.map(Vec::as_slice);
+                let min_pin_length = sub_command_parameters
+                    .and_then(|parameters| optional(parameters, 0x01))
+                    .map(unsigned)
+                    .transpose()?
+                    .map_or(self.min_pin_length, |length| length as usize);
+                let rp_ids = sub_command_parameters
+                    .and_then(|parameters| optional(parameters, 0x02))
+                    .map(|rp_ids| {
+                        array(rp_ids)?
+                            .iter()
+                            .map(|rp_id| text(rp_id).map(str::to_owned))
+                            .collect::<CtapResult<Vec<String>>>()
+                    })
+                    .transpose()?;
+                let force_pin_change = sub_command_parameters
+                    .and_then(|parameters| optional(parameters, 0x03))
+                    .map(|value| value.as_bool().ok_or(CTAP2_ERR_CBOR_UNEXPECTED_TYPE))
+                    .transpose()?
+                    .unwrap_or(false);
+
+                // The minimum length can only be increased
+                if min_pin_length < self.min_pin_length {
+                    return Err(CTAP2_ERR_PIN_POLICY_VIOLATION);
+                }
+                if force_pin_change && !self.has_pin() {
+                    return Err(CTAP2_ERR_PIN_NOT_SET);
+                }
+                if let Some(rp_ids) = rp_ids {
+                    self.min_pin_length_rp_ids = rp_ids;
+                }
+                self.min_pin_length = min_pin_length;
+                self.force_pin_change |=
+                    force_pin_change || (self.has_pin() && self.pin_length < min_pin_length);
+                Ok(None)
+            }
+            _ => Err(CTAP1_ERR_INVALID_PARAMETER),
+        }
+    }
+
+    fn large_blobs(&mut self, parameters: &[(Value, Value)]) -> CtapResult<Option<Value>> {
+        let get = optional(parameters, 0x01).map(unsigned).transpose()?;
+        let set = optional(parameters, 0x02).map(bytes).transpose()?;
+        let offset = unsigned(required(parameters, 0x03)?)? as usize;
+
+        match (get, set) {
+            (Some(get), None) => {
+                if get as usize > MAX_LARGE_BLOB_FRAGMENT {
+                    return Err(CTAP1_ERR_INVALID_LENGTH);
+                }
+                if offset > self.large_blob_array.len() {
+                    return Err(CTAP1_ERR_INVALID_PARAMETER);
+                }
+                let end = self.large_blob_array.len().min(offset + get as usize);
+                Ok(Some(int_map(vec![(
+                    0x01,
+                    self.large_blob_array[offset..end].into(),
+                )])))
+            }
+            (None, Some(set)) => {
+                if set.len() > MAX_LARGE_BLOB_FRAGMENT {
+                    return Err(CTAP1_ERR_INVALID_LENGTH);
+                }
+                if offset == 0 {
+                    let length = unsigned(required(parameters, 0x04)?)? as usize;
+                    if length > MAX_LARGE_BLOB_ARRAY {
+                        return Err(CTAP2_ERR_LARGE_BLOB_STORAGE_FULL);
+                    }
+                    if length < LARGE_BLOB_HASH_LEN + 1 {
+                        return Err(CTAP1_ERR_INVALID_PARAMETER);
+                    }
+                    self.large_blob_write = Some((Vec::with_capacity(length), length));
+                }
+                let written = match &self.large_blob_write {
+                    Some((written, _)) => written.len(),
+                    None => return Err(CTAP1_ERR_INVALID_SEQ),
+                };
+                if offset != written {
+                    return Err(CTAP1_ERR_INVALID_SEQ);
+                }
+
+                // The PIN token authenticates the offset and hash of the fragment
+                let mut message = vec![0xff; 32];
+                message.extend_from_slice(&[CTAP_CBOR_LARGEBLOB as u8, 0x00]);
+                message.extend_from_slice(&(offset as u32).to_le_bytes());
+                message.extend_from_slice(&crypto::sha256(set));
+                self.verify_pin_auth(parameters, 0x05, 0x06, &message, true)?;
+
+                let (written, length) = self.large_blob_write.as_mut().unwrap();
+                if written.len() + set.len() > *length {
+                    return Err(CTAP1_ERR_INVALID_PARAMETER);
+                }
+                written.extend_from_slice(set);
+                if written.len() < *length {
+                    return Ok(None);
+                }
+
+                // The complete array has to end with the truncated hash of its contents
+                let (written, _) = self.large_blob_write.take().unwrap();
+                let (array, hash) = written.split_at(written.len() - LARGE_BLOB_HASH_LEN);
+                if !crypto::constant_time_eq(&crypto::sha256(array)[..LARGE_BLOB_HASH_LEN], hash) {
+                    return Err(CTAP2_ERR_INTEGRITY_FAILURE);
+                }
+                self.large_blob_array = written;
+                Ok(None)
+            }
+            _ => Err(CTAP1_ERR_INVALID_PARAMETER),
+        }
+    }
+
+    fn take_sample(&mut self, id: Vec<u8>, remaining: u8) -> u8 {
+        let remaining = remaining.saturating_sub(1);
+        if remaining == 0 {
+            self.enrollment = None;
+            self.fingerprints.push(Fingerprint { id, name: None });
+        } else {
+            self.enrollment = Some((id, remaining));
+        }
+        remaining
+    }
+
+    /// Returns the first response of an enumeration, which carries the total amount of entries,
+    /// and keeps the others for the subcommand which fetches the next entry.
+    fn begin_enumeration(
+        &mut self,
+        next: u64,
+        mut responses: Vec<Vec<(i64, Value)>>,
+        total_key: i64,
+        total: u64,
+    ) -> CtapResult<Option<Value>> {
+        if responses.is_empty() {
+            return Err(CTAP2_ERR_NO_CREDENTIALS);
+        }
+        let mut first = responses.remove(0);
+        first.push((total_key, total.into()));
+
+        responses.reverse();
+        self.next_enumerated = Some((next, responses.into_iter().map(int_map).collect()));
+        Ok(Some(int_map(first)))
+    }
+
+    fn resident_credentials(&self) -> impl Iterator<Item = &StoredCredential> {
+        self.credentials
+            .iter()
+            .filter(|credential| credential.resident)
+    }
+
+    fn shared_secret(&self, parameters: &[(Value, Value)]) -> CtapResult<[u8; 32]> {
+        self.key_agreement
+            .shared_secret(required(parameters, 0x03)?)
+            .ok_or(CTAP1_ERR_INVALID_PARAMETER)
+    }
+
+    /// Verifies the encrypted PIN hash sent by the platform, updating the retry counter.
+    fn check_pin_hash(&mut self, shared_secret: &[u8; 32], pin_hash_enc: &[u8]) -> CtapResult<()> {
+        let pin_hash = self.pin_hash.ok_or(CTAP2_ERR_PIN_NOT_SET)?;
+        if self.pin_retries == 0 {
+            return Err(CTAP2_ERR_PIN_BLOCKED);
+        }
+        self.pin_retries -= 1;
+
+        let received =
+            crypto::decrypt(shared_secret, pin_hash_enc).ok_or(CTAP1_ERR_INVALID_PARAMETER)?;
+        if !crypto::constant_time_eq(&received, &pin_hash) {
+            // The platform has to obtain a new key agreement after a mismatch
+            self.key_agreement = KeyAgreement::generate();
+            return Err(if self.pin_retries == 0 {
+                CTAP2_ERR_PIN_BLOCKED
+            } else {
+                CTAP2_ERR_PIN_INVALID
+            });
+        }
+
+        self.pin_retries = PIN_MAX_RETRIES;
+        Ok(())
+    }
+
+    /// Verifies the pinAuth parameter of a request, returning whether the user was verified.
+    fn verify_pin_auth(
+        &mut self,
+        parameters: &[(Value, Value)],
+        pin_auth_key: i64,
+        pin_protocol_key: i64,
+        client_data_hash: &[u8],
+        pin_required: bool,
+    ) -> CtapResult<bool> {
+        let pin_auth = match optional(parameters, pin_auth_key) {
+            Some(pin_auth) => bytes(pin_auth)?,
+            None if pin_required && self.has_pin() => return Err(CTAP2_ERR_PIN_REQUIRED),
+            None => return Ok(false),
+        };
+
+        // An empty pinAuth is used by platforms to wait for a touch
+        if pin_auth.is_empty() {
+            self.check_user_presence()?;
+            return Err(if self.has_pin() {
+                CTAP2_ERR_PIN_INVALID
+            } else {
+                CTAP2_ERR_PIN_NOT_SET
+            });
+        }
+
+        if unsigned(required(parameters, pin_protocol_key)?)? != PIN_PROTOCOL {
+            return Err(CTAP2_ERR_PIN_AUTH_INVALID);
+        }
+        if !self.has_pin() {
+            return Err(CTAP2_ERR_PIN_NOT_SET);
+        }
+        if !crypto::constant_time_eq(
+            &crypto::authenticate(&self.pin_token, client_data_hash),
+            pin_auth,
+        ) {
+            return Err(CTAP2_ERR_PIN_AUTH_INVALID);
+        }
+        Ok(true)
+    }
+
+    /// Checks that the user was verified, if the authenticator always requires it.
+    fn check_always_uv(&self, user_verified: bool) -> CtapResult<()> {
+        match (self.always_uv && !user_verified, self.has_pin()) {
+            (false, _) => Ok(()),
+            (true, true) => Err(CTAP2_ERR_PIN_REQUIRED),
+            (true, false) => Err(CTAP2_ERR_PIN_NOT_SET),
+        }
+    }
+
+    /// Simulates waiting for the user to touch the authenticator.
+    /// The first time, this aborts the request so it can be processed again after the wait.
+    fn check_user_presence(&self) -> CtapResult<()> {
+        match self.cancelled {
+            None => Err(AWAITING_USER_PRESENCE),
+            Some(true) => Err(CTAP2_ERR_KEEPALIVE_CANCEL),
+            Some(false) if self.user_presence => Ok(()),
+            Some(false) => Err(CTAP2_ERR_OPERATION_DENIED),
+        }
+    }
+
+    /// Builds the part of the authenticator data which is common to all requests.
+    fn auth_data_header(&self, relying_party_id: &str, flags: u8) -> Vec<u8> {
+        let mut auth_data = Vec::with_capacity(37);
+        auth_data.extend_from_slice(&crypto::sha256(relying_party_id.as_bytes()));
+        auth_data.push(flags);
+        auth_data.extend_from_slice(&self.sign_count.to_be_bytes());
+        auth_data
+    }
+}
+
+fn decrypt_pin(
+    shared_secret: &[u8; 32],
+    new_pin_enc: &[u8],
+    min_pin_length: usize,
+) -> CtapResult<Vec<u8>> {
+    if new_pin_enc.len() != PIN_PADDED_LEN {
+        return Err(CTAP1_ERR_INVALID_PARAMETER);
+    }
+    let mut pin = crypto::decrypt(shared_secret, new_pin_enc).ok_or(CTAP1_ERR_INVALID_PARAMETER)?;
+    let len = pin.iter().position(|&b| b == 0).unwrap_or(pin.len());
+    pin.truncate(len);
+    if pin.len() < min_pin_length {
+        return Err(CTAP2_ERR_PIN_POLICY_VIOLATION);
+    }
+    Ok(pin)
+}
+
+/// Serializes an empty large blob array, followed by its truncated hash.
+fn empty_large_blob_array() -> Vec<u8> {
+    const EMPTY_ARRAY: u8 = 0x80;
+    let mut array = vec![EMPTY_ARRAY];
+    array.extend_from_slice(&crypto::sha256(&array)[..LARGE_BLOB_HASH_LEN]);
+    array
+}
+
+fn left_16(hash: &[u8; 32]) -> [u8; 16] {
+    let mut left = [0; 16];
+    left.copy_from_slice(&hash[..16]);
+    left
+}
+
+/*
+    CBOR helpers
+*/
+
+fn decode_map(data: &[u8]) -> CtapResult<Vec<(Value, Value)>> {
+    ciborium::de::from_reader::<Value, _>(data)
+        .map_err(|_| CTAP2_ERR_INVALID_CBOR)?
+        .into_map()
+        .map_err(|_| CTAP2_ERR_CBOR_UNEXPECTED_TYPE)
+}
+
+fn int_map(entries: Vec<(i64, Value)>) -> Value {
+    Value::Map(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.into(), value))
+            .collect(),
+    )
+}
+
+fn optional(map: &[(Value, Value)], key: i64) -> Option<&Value> {
+    map.iter()
+        .find(|(k, _)| k.as_integer().map(i128::from) == Some(i128::from(key)))
+        .map(|(_, value)| value)
+}
+
+fn required(map: &[(Value, Value)], key: i64) -> CtapResult<&Value> {
+    optional(map, key).ok_or(CTAP2_ERR_MISSING_PARAMETER)
+}
+
+fn text_key<'a>(map: &'a [(Value, Value)], key: &str) -> Option<&'a Value> {
+    map.iter()
+        .find(|(k, _)| k.as_text() == Some(key))
+        .map(|(_, value)| value)
+}
+
+fn text_required<'a>(map: &'a [(Value, Value)], key: &str) -> CtapResult<&'a Value> {
+    text_key(map, key).ok_or(CTAP2_ERR_MISSING_PARAMETER)
+}
+
+fn option(options: Option<&Vec<(Value, Value)>>, key: &str) -> CtapResult<Option<bool>> {
+    options
+        .and_then(|options| text_key(options, key))
+        .map(|value| value.as_bool().ok_or(CTAP2_ERR_CBOR_UNEXPECTED_TYPE))
+        .transpose()
+}
+
+fn map(value: &Value) -> CtapResult<&Vec<(Value, Value)>> {
+    value.as_map().ok_or(CTAP2_ERR_CBOR_UNEXPECTED_TYPE)
+}
+
+fn array(value: &Value) -> CtapResult<&Vec<Value>> {
+    value.as_array().ok_or(CTAP2_ERR_CBOR_UNEXPECTED_TYPE)
+}
+
+fn bytes(value: &Value) -> CtapResult<&[u8]> {
+    value
+        .as_bytes()
+        .map(Vec::as_slice)
+        .ok_or(CTAP2_ERR_CBOR_UNEXPECTED_TYPE)
+}
+
+fn text(value: &Value) -> CtapResult<&str> {
+    value.as_text().ok_or(CTAP2_ERR_CBOR_UNEXPECTED_TYPE)
+}
+
+fn integer(value: &Value) -> CtapResult<i64> {
+    value
+        .as_integer()
+        .and_then(|integer| i64::try_from(integer).ok())
+        .ok_or(CTAP2_ERR_CBOR_UNEXPECTED_TYPE)
+}
+
+fn unsigned(value: &Value) -> CtapResult<u64> {
+    value
+        .as_integer()
+        .and_then(|integer| u64::try_from(integer).ok())
+        .ok_or(CTAP2_ERR_CBOR_UNEXPECTED_TYPE)
+}
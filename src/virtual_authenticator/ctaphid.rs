@@ -0,0 +1,298 @@
+use super::{ctap2::Authenticator, VirtualAuthenticator};
+use crate::transport::{Transport, FRAME_SIZE};
+use libfido2_sys::*;
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+// CTAPHID commands and errors which are not part of the bindings
+const CTAP_CMD_CANCEL: u8 = 0x11;
+const CTAP_CMD_ERROR: u8 = 0x3f;
+const CTAP1_ERR_INVALID_CMD: u8 = 0x01;
+const CTAP1_ERR_INVALID_LEN: u8 = 0x03;
+const CTAP1_ERR_INVALID_SEQ: u8 = 0x04;
+const CTAP1_ERR_CHANNEL_BUSY: u8 = 0x06;
+const CTAP1_ERR_INVALID_CHANNEL: u8 = 0x0b;
+
+const INIT_HEADER: usize = 7;
+const CONT_HEADER: usize = 5;
+const INIT_NONCE_LEN: usize = 8;
+const MAX_MESSAGE_LEN: usize = FRAME_SIZE - INIT_HEADER + 128 * (FRAME_SIZE - CONT_HEADER);
+
+// Reported in the CTAPHID_INIT response
+const PROTOCOL_VERSION: u8 = 2;
+const VERSION_MAJOR: u8 = 0;
+const VERSION_MINOR: u8 = 1;
+const VERSION_BUILD: u8 = 0;
+
+/// A single CTAPHID connection to a virtual authenticator.
+pub(super) struct Connection(Arc<Shared>);
+
+struct Shared {
+    authenticator: VirtualAuthenticator,
+    state: Mutex<State>,
+    responses_available: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    next_channel: u32,
+    request: Option<Request>,
+    // CBOR request which is being processed
+    pending: Option<Pending>,
+    responses: VecDeque<[u8; FRAME_SIZE]>,
+}
+
+struct Pending {
+    channel: u32,
+    cancel: Arc<Cancel>,
+}
+
+// Partially received message
+struct Request {
+    channel: u32,
+    command: u8,
+    length: usize,
+    sequence: u8,
+    data: Vec<u8>,
+}
+
+/// Signals cancellation of the request being processed, as requested by CTAPHID_CANCEL.
+#[derive(Default)]
+pub(super) struct Cancel {
+    cancelled: Mutex<bool>,
+    signal: Condvar,
+}
+
+impl Cancel {
+    fn cancel(&self) {
+        *self.cancelled.lock().unwrap() = true;
+        self.signal.notify_all();
+    }
+
+    /// Waits for given duration, and returns whether the request was cancelled in the meantime.
+    pub(super) fn wait(&self, duration: Duration) -> bool {
+        let cancelled = self.cancelled.lock().unwrap();
+        *self
+            .signal
+            .wait_timeout_while(cancelled, duration, |cancelled| !*cancelled)
+            .unwrap()
+            .0
+    }
+}
+
+impl Connection {
+    pub(super) fn new(authenticator: VirtualAuthenticator) -> Self {
+        Connection(Arc::new(Shared {
+            authenticator,
+            state: Mutex::new(State {
+                next_channel: 1,
+                ..State::default()
+            }),
+            responses_available: Condvar::new(),
+        }))
+    }
+}
+
+fn receive(shared: &Arc<Shared>, state: &mut State, frame: &[u8]) {
+    let channel = u32::from_be_bytes([frame[0], frame[1], frame[2], frame[3]]);
+
+    if frame[4] & CTAP_FRAME_INIT as u8 != 0 {
+        let command = frame[4] & !(CTAP_FRAME_INIT as u8);
+        let length = usize::from(u16::from_be_bytes([frame[5], frame[6]]));
+
+        // Cancelling has no response of its own, the pending request fails instead
+        if command == CTAP_CMD_CANCEL {
+            if let Some(pending) = state.pending.as_ref().filter(|p| p.channel == channel) {
+                pending.cancel.cancel();
+            }
+            return;
+        }
+
+        // A new request aborts the pending one on the same channel, e.g. after the host timed out
+        if let Some(pending) = state.pending.take() {
+            if pending.channel == channel {
+                pending.cancel.cancel();
+            } else {
+                state.pending = Some(pending);
+            }
+        }
+        state
+            .responses
+            .retain(|response| response[..4] != channel.to_be_bytes());
+        if length > MAX_MESSAGE_LEN {
+            state.error(channel, CTAP1_ERR_INVALID_LEN);
+            return;
+        }
+
+        let received = length.min(FRAME_SIZE - INIT_HEADER);
+        state.request = Some(Request {
+            channel,
+            command,
+            length,
+            sequence: 0,
+            data: frame[INIT_HEADER..INIT_HEADER + received].to_vec(),
+        });
+    } else {
+        let request = match state.request.as_mut() {
+            Some(request) if request.channel == channel => request,
+            // Continuation frames without a matching initialization frame are ignored
+            _ => return,
+        };
+        if frame[4] != request.sequence {
+            state.request = None;
+            state.error(channel, CTAP1_ERR_INVALID_SEQ);
+            return;
+        }
+        request.sequence += 1;
+
+        let received = (request.length - request.data.len()).min(FRAME_SIZE - CONT_HEADER);
+        request
+            .data
+            .extend_from_slice(&frame[CONT_HEADER..CONT_HEADER + received]);
+    }
+
+    if let Some(request) = state.request.take() {
+        if request.data.len() < request.length {
+            state.request = Some(request);
+        } else {
+            dispatch(shared, state, request);
+        }
+    }
+}
+
+fn dispatch(shared: &Arc<Shared>, state: &mut State, request: Request) {
+    let channel = request.channel;
+    let broadcast = channel == CTAP_CID_BROADCAST;
+
+    match u32::from(request.command) {
+        CTAP_CMD_INIT => {
+            if request.data.len() != INIT_NONCE_LEN {
+                state.error(channel, CTAP1_ERR_INVALID_LEN);
+                return;
+            }
+            let allocated = if broadcast {
+                let allocated = state.next_channel;
+                state.next_channel += 1;
+                allocated
+            } else {
+                channel
+            };
+
+            let capabilities = shared.authenticator.with_state(Authenticator::capabilities);
+            let mut response = request.data;
+            response.extend_from_slice(&allocated.to_be_bytes());
+            response.extend_from_slice(&[
+                PROTOCOL_VERSION,
+                VERSION_MAJOR,
+                VERSION_MINOR,
+                VERSION_BUILD,
+                capabilities,
+            ]);
+            state.send(channel, request.command, &response);
+        }
+        _ if broadcast => state.error(channel, CTAP1_ERR_INVALID_CHANNEL),
+        _ if state.pending.is_some() => state.error(channel, CTAP1_ERR_CHANNEL_BUSY),
+        CTAP_CMD_PING => state.send(channel, request.command, &request.data),
+        CTAP_CMD_WINK => {
+            shared.authenticator.with_state(Authenticator::wink);
+            state.send(channel, request.command, &[]);
+        }
+        CTAP_CMD_CBOR => {
+            // Process in the background, since waiting for user presence may be simulated
+            let cancel = Arc::new(Cancel::default());
+            state.pending = Some(Pending {
+                channel,
+                cancel: cancel.clone(),
+            });
+            let shared = shared.clone();
+            thread::spawn(move || {
+                let response = shared.authenticator.process(&request.data, &cancel);
+
+                // Drop the response if the request was aborted in the meantime
+                let mut state = shared.state.lock().unwrap();
+                match &state.pending {
+                    Some(pending) if Arc::ptr_eq(&pending.cancel, &cancel) => {
+                        state.pending = None;
+                        state.send(channel, request.command, &response);
+                        shared.responses_available.notify_all();
+                    }
+                    _ => {}
+                }
+            });
+        }
+        _ => state.error(channel, CTAP1_ERR_INVALID_CMD),
+    }
+}
+
+impl State {
+    /// Splits a response into frames.
+    fn send(&mut self, channel: u32, command: u8, data: &[u8]) {
+        let mut frame = [0; FRAME_SIZE];
+        frame[..4].copy_from_slice(&channel.to_be_bytes());
+        frame[4] = command | CTAP_FRAME_INIT as u8;
+        frame[5..INIT_HEADER].copy_from_slice(&(data.len() as u16).to_be_bytes());
+
+        let (first, rest) = data.split_at(data.len().min(FRAME_SIZE - INIT_HEADER));
+        frame[INIT_HEADER..INIT_HEADER + first.len()].copy_from_slice(first);
+        self.responses.push_back(frame);
+
+        for (sequence, chunk) in rest.chunks(FRAME_SIZE - CONT_HEADER).enumerate() {
+            let mut frame = [0; FRAME_SIZE];
+            frame[..4].copy_from_slice(&channel.to_be_bytes());
+            frame[4] = sequence as u8;
+            frame[CONT_HEADER..CONT_HEADER + chunk.len()].copy_from_slice(chunk);
+            self.responses.push_back(frame);
+        }
+    }
+
+    fn error(&mut self, channel: u32, error: u8) {
+        self.send(channel, CTAP_CMD_ERROR, &[error]);
+    }
+}
+
+impl Transport for Connection {
+    fn read(&self, frame: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        let state = self.0.state.lock().unwrap();
+        let mut state = match timeout {
+            Some(timeout) => {
+                self.0
+                    .responses_available
+                    .wait_timeout_while(state, timeout, |state| state.responses.is_empty())
+                    .unwrap()
+                    .0
+            }
+            None => self
+                .0
+                .responses_available
+                .wait_while(state, |state| state.responses.is_empty())
+                .unwrap(),
+        };
+
+        match state.responses.pop_front() {
+            Some(response) => {
+                let len = frame.len().min(FRAME_SIZE);
+                frame[..len].copy_from_slice(&response[..len]);
+                Ok(len)
+            }
+            None => Err(io::ErrorKind::TimedOut.into()),
+        }
+    }
+
+    fn write(&self, frame: &[u8]) -> io::Result<usize> {
+        if frame.len() < FRAME_SIZE {
+            return Err(io::ErrorKind::InvalidInput.into());
+        }
+
+        let mut state = self.0.state.lock().unwrap();
+        receive(&self.0, &mut state, &frame[..FRAME_SIZE]);
+        if !state.responses.is_empty() {
+            self.0.responses_available.notify_all();
+        }
+        Ok(frame.len())
+    }
+}
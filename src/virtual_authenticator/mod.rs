@@ -0,0 +1,167 @@
+mod crypto;
+mod ctap2;
+mod ctaphid;
+
+use crate::Transport;
+use ctap2::Authenticator;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// An in-process software authenticator.
+///
+/// The authenticator speaks CTAP2 over CTAPHID, and can be opened as a regular `Device` through
+/// [`Fido::new_virtual_device`]. Cloning it yields a handle to the same authenticator,
+/// so its state (credentials, PIN, sign counter) persists across devices opened from it.
+///
+/// # Remarks
+/// - Supports ES256, RS256 and EdDSA credentials, resident credentials,
+///   PIN protocol one, credential management, authenticator configuration, large blobs
+///   and the `credBlob`, `credProtect`, `hmac-secret`, `largeBlobKey` and `minPinLength` extensions.
+/// - Simulates a fingerprint sensor for biometric enrollment, which accepts every sample.
+///   Enrolled fingerprints are not used to verify the user.
+/// - Attestation statements use the `packed` format with a fixed, self-signed certificate,
+///   or self attestation if enabled with [`set_self_attestation`]. Credentials which request
+///   enterprise attestation, once enabled, get a certificate whose subject lists a serial number.
+/// - User presence is granted immediately, unless delayed with [`set_user_presence_delay`]
+///   or disabled with [`set_user_presence`].
+/// - Pending requests can be cancelled while the authenticator waits for user presence.
+/// - This is meant for testing only: key material is kept in memory and never protected.
+///
+/// [`Fido::new_virtual_device`]: struct.Fido.html#method.new_virtual_device
+/// [`set_self_attestation`]: #method.set_self_attestation
+/// [`set_user_presence`]: #method.set_user_presence
+/// [`set_user_presence_delay`]: #method.set_user_presence_delay
+#[derive(Clone)]
+pub struct VirtualAuthenticator {
+    state: Arc<Mutex<Authenticator>>,
+}
+
+impl VirtualAuthenticator {
+    /// Creates a new authenticator without credentials or PIN.
+    pub fn new() -> Self {
+        VirtualAuthenticator {
+            state: Arc::new(Mutex::new(Authenticator::new())),
+        }
+    }
+
+    /// Creates a new authenticator with given PIN already set.
+    pub fn with_pin(pin: &str) -> Self {
+        let authenticator = Self::new();
+        authenticator.with_state(|state| state.set_pin(pin.as_bytes()));
+        authenticator
+    }
+
+    /// Sets whether the simulated user confirms requests which require user presence.
+    /// If disabled, these requests fail with `FIDO_ERR_OPERATION_DENIED`.
+    pub fn set_user_presence(&self, present: bool) {
+        self.with_state(|state| state.user_presence = present);
+    }
+
+    /// Sets how long the simulated user takes to confirm requests which require user presence.
+    /// Requests cancelled in the meantime fail with `FIDO_ERR_KEEPALIVE_CANCEL`.
+    pub fn set_user_presence_delay(&self, delay: Duration) {
+        self.with_state(|state| state.user_presence_delay = delay);
+    }
+
+    /// Sets whether new credentials are attested with their own key, without a certificate,
+    /// instead of the attestation certificate. Such credentials pass `Credential::verify_self`.
+    pub fn set_self_attestation(&self, enabled: bool) {
+        self.with_state(|state| state.self_attestation = enabled);
+    }
+
+    /// Sets whether enterprise attestation is enabled, as [`Device::enable_enterprise_attestation`]
+    /// does without requiring a PIN. New credentials which request it are attested with
+    /// a certificate whose subject lists a device serial number.
+    ///
+    /// [`Device::enable_enterprise_attestation`]: struct.Device.html#method.enable_enterprise_attestation
+    pub fn set_enterprise_attestation(&self, enabled: bool) {
+        self.with_state(|state| state.enterprise_attestation = enabled);
+    }
+
+    /// Returns the amount of credentials stored on the authenticator, including non-resident ones.
+    pub fn credential_count(&self) -> usize {
+        self.with_state(|state| state.credentials.len())
+    }
+
+    /// Returns the amount of resident credentials stored for the relying party with given ID.
+    pub fn resident_credential_count(&self, relying_party_id: &str) -> usize {
+        self.with_state(|state| {
+            state
+                .credentials
+                .iter()
+                .filter(|credential| {
+                    credential.resident && credential.relying_party_id == relying_party_id
+                })
+                .count()
+        })
+    }
+
+    /// Returns the amount of enrolled fingerprints.
+    pub fn fingerprint_count(&self) -> usize {
+        self.with_state(|state| state.fingerprints.len())
+    }
+
+    /// Returns the current value of the global signature counter.
+    pub fn sign_count(&self) -> u32 {
+        self.with_state(|state| state.sign_count)
+    }
+
+    /// Returns whether a PIN is set.
+    pub fn has_pin(&self) -> bool {
+        self.with_state(|state| state.has_pin())
+    }
+
+    /// Returns the amount of PIN tries left before the authenticator locks itself.
+    pub fn pin_retries(&self) -> u8 {
+        self.with_state(|state| state.pin_retries)
+    }
+
+    /// Returns the minimum length of the PIN.
+    pub fn min_pin_length(&self) -> usize {
+        self.with_state(|state| state.min_pin_length)
+    }
+
+    /// Returns the relying parties which may read the minimum PIN length.
+    pub fn min_pin_length_relying_parties(&self) -> Vec<String> {
+        self.with_state(|state| state.min_pin_length_rp_ids.clone())
+    }
+
+    /// Returns whether the PIN has to be changed before it can be used again.
+    pub fn pin_change_required(&self) -> bool {
+        self.with_state(|state| state.force_pin_change)
+    }
+
+    /// Returns how often the authenticator was asked to wink.
+    pub fn wink_count(&self) -> usize {
+        self.with_state(|state| state.winks)
+    }
+
+    /// Returns the AAGUID reported by the authenticator.
+    pub fn aag_uid(&self) -> [u8; 16] {
+        ctap2::AAGUID
+    }
+
+    /// Creates a new [`Transport`] connected to this authenticator.
+    /// Each transport has its own CTAPHID channel state.
+    ///
+    /// [`Transport`]: trait.Transport.html
+    pub fn transport(&self) -> impl Transport {
+        ctaphid::Connection::new(self.clone())
+    }
+
+    fn process(&self, request: &[u8], cancel: &ctaphid::Cancel) -> Vec<u8> {
+        Authenticator::process(&self.state, request, cancel)
+    }
+
+    fn with_state<T, F: FnOnce(&mut Authenticator) -> T>(&self, f: F) -> T {
+        f(&mut self.state.lock().unwrap())
+    }
+}
+
+impl Default for VirtualAuthenticator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
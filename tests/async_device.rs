@@ -0,0 +1,125 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+// Gives spawned requests time to reach the device
+async fn pause() {
+    tokio::task::spawn_blocking(|| thread::sleep(Duration::from_millis(200)))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn registers_and_authenticates() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let device = AsyncDevice::new(open(&fido, &authenticator));
+
+    let credential = device
+        .make_credential(
+            fido.new_credential_creator(credential_data()).unwrap(),
+            None,
+        )
+        .await
+        .unwrap();
+    credential.verify().unwrap();
+    let credential = credential.as_ref();
+
+    let assertion = device
+        .get_assertion(
+            fido.new_assertion_creator(assertion_data(Some(&[credential.id])))
+                .unwrap(),
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(assertion.verify_one(credential.public_key().unwrap()));
+    assert!(!device
+        .cbor_data()
+        .await
+        .unwrap()
+        .as_ref()
+        .ctap_versions
+        .is_empty());
+}
+
+#[tokio::test]
+async fn cancels_dropped_requests() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    authenticator.set_user_presence_delay(Duration::from_secs(10));
+    let device = Arc::new(AsyncDevice::new(open(&fido, &authenticator)));
+
+    let creator = fido.new_credential_creator(credential_data()).unwrap();
+    let pending = tokio::spawn({
+        let device = device.clone();
+        async move { device.make_credential(creator, None).await }
+    });
+    pause().await;
+    let start = Instant::now();
+    pending.abort();
+
+    // The device is released right away, instead of after the user presence delay
+    device.cbor_data().await.unwrap();
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(authenticator.credential_count(), 0);
+}
+
+#[tokio::test]
+async fn dropping_queued_requests_leaves_others_running() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    authenticator.set_user_presence_delay(Duration::from_millis(500));
+    let device = Arc::new(AsyncDevice::new(open(&fido, &authenticator)));
+
+    let creator = fido.new_credential_creator(credential_data()).unwrap();
+    let running = tokio::spawn({
+        let device = device.clone();
+        async move { device.make_credential(creator, None).await }
+    });
+    pause().await;
+
+    // This request waits for the running one, and is dropped before it gets the device
+    let creator = fido.new_credential_creator(credential_data()).unwrap();
+    let queued = tokio::spawn({
+        let device = device.clone();
+        async move { device.make_credential(creator, None).await }
+    });
+    pause().await;
+    queued.abort();
+
+    running.await.unwrap().unwrap();
+    // Requests run in order, so the dropped one has been skipped once this one completes
+    device.cbor_data().await.unwrap();
+    assert_eq!(authenticator.credential_count(), 1);
+}
+
+#[tokio::test]
+async fn cancels_through_handle() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    authenticator.set_user_presence_delay(Duration::from_secs(10));
+    let device = AsyncDevice::new(open(&fido, &authenticator));
+
+    let cancel = device.cancel_handle();
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        cancel.cancel().unwrap();
+    });
+    let err = device
+        .make_credential(
+            fido.new_credential_creator(credential_data()).unwrap(),
+            None,
+        )
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), FidoErrorKind::Cancelled);
+    canceller.join().unwrap();
+}
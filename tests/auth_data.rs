@@ -0,0 +1,132 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+use sha2::{Digest, Sha256};
+
+#[test]
+fn parses_credential_auth_data() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    let mut data = credential_data();
+    data.protection = Some(CredentialProtection::UserVerificationOptionalWithCredentialIdList);
+    let credential = make_credential(&fido, &mut device, data, None).unwrap();
+    let credential = credential.as_ref();
+
+    let auth_data = credential.authenticator_data().unwrap();
+    assert_eq!(
+        auth_data.relying_party_id_hash,
+        &Sha256::digest(RELYING_PARTY_ID)[..]
+    );
+    assert_eq!(
+        auth_data.flags,
+        AuthenticatorDataFlags::USER_PRESENT
+            | AuthenticatorDataFlags::ATTESTED_CREDENTIAL_DATA
+            | AuthenticatorDataFlags::EXTENSION_DATA
+    );
+    assert_eq!(auth_data.sign_count, authenticator.sign_count());
+    assert_eq!(
+        auth_data.extensions.get("credProtect"),
+        Some(&CborValue::Integer(2))
+    );
+
+    let attested = auth_data.attested_credential.unwrap();
+    assert_eq!(attested.aag_uid, authenticator.aag_uid());
+    assert_eq!(attested.id, credential.id);
+    assert_eq!(
+        PublicKey::from_cose(attested.public_key).unwrap().to_cose(),
+        credential.public_key().unwrap().to_cose()
+    );
+
+    // The CBOR encoded and raw authenticator data are the same
+    assert_eq!(
+        AuthenticatorData::parse_cbor(credential.auth_data).unwrap(),
+        auth_data
+    );
+    assert_eq!(
+        AuthenticatorData::parse(credential.raw_auth_data).unwrap(),
+        auth_data
+    );
+}
+
+#[test]
+fn parses_credentials_without_extensions() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    let credential = make_credential(&fido, &mut device, credential_data(), None).unwrap();
+    let auth_data = credential.as_ref().authenticator_data().unwrap();
+    assert!(!auth_data
+        .flags
+        .contains(AuthenticatorDataFlags::EXTENSION_DATA));
+    assert!(auth_data.extensions.is_empty());
+    assert!(auth_data.attested_credential.is_some());
+}
+
+#[test]
+fn parses_assertion_auth_data() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    let credential = make_credential(&fido, &mut device, credential_data(), Some(pin())).unwrap();
+    let credential = credential.as_ref();
+    let credential_auth_data = credential.authenticator_data().unwrap();
+
+    let ids = [credential.id];
+    let mut data = assertion_data(Some(&ids));
+    data.options = AssertionOptions::USER_PRESENCE;
+    let assertion = get_assertion(&fido, &mut device, data, Some(pin())).unwrap();
+    let statement = assertion.iter().next().unwrap();
+    let auth_data = statement.authenticator_data().unwrap();
+    assert_eq!(
+        auth_data.relying_party_id_hash,
+        credential_auth_data.relying_party_id_hash
+    );
+    assert_eq!(
+        auth_data.flags,
+        AuthenticatorDataFlags::USER_PRESENT | AuthenticatorDataFlags::USER_VERIFIED
+    );
+    assert!(auth_data.sign_count > credential_auth_data.sign_count);
+    assert_eq!(auth_data.attested_credential, None);
+    assert!(auth_data.extensions.is_empty());
+
+    // Without user presence or verification, no flags are set
+    let assertion = get_assertion(
+        &fido,
+        &mut device,
+        assertion_data(Some(&[credential.id])),
+        None,
+    )
+    .unwrap();
+    let auth_data = assertion
+        .iter()
+        .next()
+        .unwrap()
+        .authenticator_data()
+        .unwrap();
+    assert_eq!(auth_data.flags, AuthenticatorDataFlags::empty());
+}
+
+#[test]
+fn rejects_malformed_auth_data() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    let credential = make_credential(&fido, &mut device, credential_data(), None).unwrap();
+    let raw = credential.as_ref().raw_auth_data;
+    assert_eq!(
+        AuthenticatorData::parse(&raw[..36]),
+        Err(AuthenticatorDataError::Truncated("sign count"))
+    );
+    assert!(AuthenticatorData::parse(&raw[..raw.len() - 1]).is_err());
+    assert!(AuthenticatorData::parse(&[raw, &[0]].concat()).is_err());
+    assert_eq!(
+        AuthenticatorData::parse_cbor(raw),
+        Err(AuthenticatorDataError::InvalidEncoding)
+    );
+}
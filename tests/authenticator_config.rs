@@ -0,0 +1,130 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+
+fn option(device: &mut Device, option: &str) -> Option<bool> {
+    device
+        .request_cbor_data()
+        .unwrap()
+        .as_ref()
+        .options
+        .get(option)
+        .copied()
+}
+
+#[test]
+fn toggles_always_uv() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    assert_eq!(option(&mut device, "alwaysUv"), Some(false));
+
+    device.toggle_always_uv(Some(pin())).unwrap();
+    assert_eq!(option(&mut device, "alwaysUv"), Some(true));
+    make_credential(&fido, &mut device, credential_data(), Some(pin())).unwrap();
+
+    device.toggle_always_uv(Some(pin())).unwrap();
+    assert_eq!(option(&mut device, "alwaysUv"), Some(false));
+}
+
+#[test]
+fn enables_enterprise_attestation() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    assert_eq!(option(&mut device, "ep"), Some(false));
+    device.enable_enterprise_attestation(Some(pin())).unwrap();
+    assert_eq!(option(&mut device, "ep"), Some(true));
+}
+
+#[test]
+fn sets_min_pin_length_relying_parties() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    device
+        .set_min_pin_length_relying_parties(
+            &[cstr(b"example.com\0"), cstr(b"example.org\0")],
+            Some(pin()),
+        )
+        .unwrap();
+    assert_eq!(
+        authenticator.min_pin_length_relying_parties(),
+        ["example.com", "example.org"]
+    );
+}
+
+#[test]
+fn raises_min_pin_length() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    let new_pin = cstr(b"123456\0");
+
+    // Raising the minimum PIN length above the length of the current PIN forces a change
+    device.set_min_pin_length(6, Some(pin())).unwrap();
+    assert_eq!(authenticator.min_pin_length(), 6);
+    assert!(authenticator.pin_change_required());
+    assert!(device.toggle_always_uv(Some(pin())).is_err());
+    assert!(device.set_pin(cstr(b"12345\0"), Some(pin())).is_err());
+    device.set_pin(new_pin, Some(pin())).unwrap();
+    assert!(!authenticator.pin_change_required());
+
+    // The minimum PIN length cannot be decreased
+    assert!(device.set_min_pin_length(4, Some(new_pin)).is_err());
+    assert_eq!(authenticator.min_pin_length(), 6);
+}
+
+#[test]
+fn forces_pin_change() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    device.force_pin_change(Some(pin())).unwrap();
+    assert!(authenticator.pin_change_required());
+
+    // The new PIN must differ from the current one
+    assert!(device.set_pin(pin(), Some(pin())).is_err());
+    device.set_pin(cstr(b"654321\0"), Some(pin())).unwrap();
+    assert!(!authenticator.pin_change_required());
+}
+
+#[test]
+fn requires_pin() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    assert!(device.toggle_always_uv(None).is_err());
+    assert!(device.enable_enterprise_attestation(None).is_err());
+    assert!(device.set_min_pin_length(8, None).is_err());
+    assert_eq!(option(&mut device, "alwaysUv"), Some(false));
+    assert_eq!(option(&mut device, "ep"), Some(false));
+    assert_eq!(authenticator.min_pin_length(), 4);
+}
+
+#[test]
+fn rejects_devices_without_configuration() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    device.force_mode(DeviceMode::FidoU2F);
+
+    for err in vec![
+        device.toggle_always_uv(None),
+        device.set_min_pin_length(6, None),
+        device.force_pin_change(None),
+        device.set_min_pin_length_relying_parties(&[relying_party_id()], None),
+        device.enable_enterprise_attestation(None),
+    ]
+    .into_iter()
+    .map(Result::unwrap_err)
+    {
+        assert_eq!(err.to_string(), "FIDO_ERR_UNSUPPORTED_OPTION");
+        assert_eq!(err.kind(), FidoErrorKind::Unsupported);
+    }
+}
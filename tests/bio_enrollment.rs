@@ -0,0 +1,146 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+use std::time::Duration;
+
+const SAMPLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[test]
+fn reports_sensor_info() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    let info = device.bio_sensor_info().unwrap();
+    assert_eq!(info.sensor_type, 1);
+    assert_eq!(info.max_samples, 4);
+    assert!(device.bio_templates(pin()).unwrap().is_empty());
+}
+
+#[test]
+fn enrolls_fingerprint_with_feedback() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    let mut samples = Vec::new();
+    let template = device
+        .enroll_fingerprint(
+            Some(cstr(b"Right index finger\0")),
+            SAMPLE_TIMEOUT,
+            pin(),
+            |sample| samples.push(sample),
+        )
+        .unwrap();
+    assert_eq!(
+        samples,
+        (0..4)
+            .rev()
+            .map(|remaining_samples| BioSample {
+                status: BioSampleStatus::Good,
+                remaining_samples,
+            })
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(template.name.as_deref(), Some("Right index finger"));
+    assert_eq!(authenticator.fingerprint_count(), 1);
+    assert_eq!(device.bio_templates(pin()).unwrap(), vec![template]);
+}
+
+#[test]
+fn enrolls_fingerprint_step_by_step() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    let mut enrollment = device.begin_bio_enrollment(SAMPLE_TIMEOUT, pin()).unwrap();
+    let id = enrollment.template_id().to_vec();
+    assert!(!id.is_empty());
+    assert_eq!(enrollment.last_sample().remaining_samples, 3);
+    while !enrollment.is_complete() {
+        device
+            .continue_bio_enrollment(&mut enrollment, SAMPLE_TIMEOUT)
+            .unwrap();
+        assert_eq!(enrollment.last_sample().status, BioSampleStatus::Good);
+        assert_eq!(enrollment.template_id(), &id[..]);
+    }
+
+    let templates = device.bio_templates(pin()).unwrap();
+    assert_eq!(templates.len(), 1);
+    assert_eq!(templates[0].id, id);
+    assert_eq!(templates[0].name, None);
+}
+
+#[test]
+fn cancels_enrollment() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    let mut enrollment = device.begin_bio_enrollment(SAMPLE_TIMEOUT, pin()).unwrap();
+    device
+        .continue_bio_enrollment(&mut enrollment, SAMPLE_TIMEOUT)
+        .unwrap();
+    assert!(!enrollment.is_complete());
+    device.cancel_bio_enrollment().unwrap();
+    assert_eq!(authenticator.fingerprint_count(), 0);
+
+    // The enrollment cannot be continued anymore
+    assert!(device
+        .continue_bio_enrollment(&mut enrollment, SAMPLE_TIMEOUT)
+        .is_err());
+    assert_eq!(authenticator.fingerprint_count(), 0);
+}
+
+#[test]
+fn renames_and_deletes_templates() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    let right = device
+        .enroll_fingerprint(None, SAMPLE_TIMEOUT, pin(), |_| {})
+        .unwrap();
+    let left = device
+        .enroll_fingerprint(None, SAMPLE_TIMEOUT, pin(), |_| {})
+        .unwrap();
+    assert_ne!(right.id, left.id);
+    assert_eq!(authenticator.fingerprint_count(), 2);
+
+    device
+        .rename_bio_template(&left.id, cstr(b"Left index finger\0"), pin())
+        .unwrap();
+    let templates = device.bio_templates(pin()).unwrap();
+    let renamed = templates
+        .iter()
+        .find(|template| template.id == left.id)
+        .unwrap();
+    assert_eq!(renamed.name.as_deref(), Some("Left index finger"));
+
+    device.delete_bio_template(&right.id, pin()).unwrap();
+    let templates = device.bio_templates(pin()).unwrap();
+    assert_eq!(templates.len(), 1);
+    assert_eq!(templates[0].id, left.id);
+
+    // Deleting the template again fails
+    assert!(device.delete_bio_template(&right.id, pin()).is_err());
+    assert_eq!(authenticator.fingerprint_count(), 1);
+}
+
+#[test]
+fn requires_pin() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    let wrong_pin = cstr(b"4321\0");
+    assert_eq!(
+        device.bio_templates(wrong_pin).unwrap_err().to_string(),
+        "FIDO_ERR_PIN_INVALID"
+    );
+    assert!(device
+        .enroll_fingerprint(None, SAMPLE_TIMEOUT, wrong_pin, |_| {})
+        .is_err());
+    assert_eq!(authenticator.fingerprint_count(), 0);
+}
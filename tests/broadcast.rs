@@ -0,0 +1,76 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+use std::time::Duration;
+
+#[test]
+fn creates_credential_on_first_device() {
+    let fido = Fido::new(false);
+    let slow = VirtualAuthenticator::new();
+    slow.set_user_presence_delay(Duration::from_secs(10));
+    let fast = VirtualAuthenticator::new();
+
+    let devices = vec![open(&fido, &slow), open(&fido, &fast)];
+    let broadcast = fido
+        .broadcast_credential_creation(devices, credential_data(), None)
+        .unwrap();
+    assert_eq!(broadcast.index, 1);
+    assert!(broadcast.errors.is_empty());
+    broadcast.value.verify().unwrap();
+    assert_eq!(fast.credential_count(), 1);
+    // The request on the other device was cancelled
+    assert_eq!(slow.credential_count(), 0);
+}
+
+#[test]
+fn asserts_on_the_device_holding_the_credential() {
+    let fido = Fido::new(false);
+    let holder = VirtualAuthenticator::new();
+    let other = VirtualAuthenticator::new();
+    let credential =
+        make_credential(&fido, &mut open(&fido, &holder), credential_data(), None).unwrap();
+    let id = credential.as_ref().id;
+
+    let devices = vec![open(&fido, &other), open(&fido, &holder)];
+    let ids = [id];
+    let broadcast = fido
+        .broadcast_assertion_verification(devices, assertion_data(Some(&ids)), None)
+        .unwrap();
+    assert_eq!(broadcast.index, 1);
+    assert!(broadcast
+        .value
+        .verify_one(credential.as_ref().public_key().unwrap()));
+}
+
+#[test]
+fn collects_errors_of_every_device() {
+    let fido = Fido::new(false);
+    let devices = vec![
+        open(&fido, &VirtualAuthenticator::new()),
+        open(&fido, &VirtualAuthenticator::new()),
+    ];
+    let unknown: &[u8] = &[0; 32];
+    let ids = [unknown];
+    let err = fido
+        .broadcast_assertion_verification(devices, assertion_data(Some(&ids)), None)
+        .err()
+        .unwrap();
+    let mut indices = err
+        .errors
+        .iter()
+        .map(|(index, _)| *index)
+        .collect::<Vec<_>>();
+    indices.sort_unstable();
+    assert_eq!(indices, [0, 1]);
+    assert!(err
+        .errors
+        .iter()
+        .all(|(_, err)| err.to_string() == "FIDO_ERR_NO_CREDENTIALS"));
+
+    let err = fido
+        .broadcast_credential_creation(Vec::new(), credential_data(), None)
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "no devices to broadcast to");
+}
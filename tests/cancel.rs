@@ -0,0 +1,48 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+use std::{thread, time::Duration};
+
+#[test]
+fn cancels_pending_requests() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    // The authenticator waits for user presence until the request is cancelled
+    authenticator.set_user_presence_delay(Duration::from_secs(10));
+    let cancel = device.cancel_handle();
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        cancel.cancel().unwrap();
+    });
+    let err = make_credential(&fido, &mut device, credential_data(), Some(pin()))
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), FidoErrorKind::Cancelled);
+    canceller.join().unwrap();
+    assert_eq!(authenticator.credential_count(), 0);
+
+    // The device remains usable afterwards
+    authenticator.set_user_presence_delay(Duration::from_secs(0));
+    make_credential(&fido, &mut device, credential_data(), Some(pin())).unwrap();
+    assert_eq!(authenticator.credential_count(), 1);
+}
+
+#[test]
+fn ignores_cancellation_without_pending_request() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    device.cancel_handle().cancel().unwrap();
+    make_credential(&fido, &mut device, credential_data(), None).unwrap();
+}
+
+#[test]
+fn outlives_the_device() {
+    let fido = Fido::new(false);
+    let cancel = open(&fido, &VirtualAuthenticator::new()).cancel_handle();
+    cancel.cancel().unwrap();
+}
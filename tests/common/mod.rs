@@ -0,0 +1,70 @@
+// Shared setup of the integration tests, which run against a virtual authenticator
+#![allow(dead_code)]
+
+use libfido2::*;
+use std::ffi::CStr;
+
+pub const CLIENT_DATA_HASH: [u8; 32] = [
+    0xf9, 0x64, 0x57, 0xe7, 0x2d, 0x97, 0xf6, 0xbb, 0xdd, 0xd7, 0xfb, 0x06, 0x37, 0x62, 0xea, 0x26,
+    0x20, 0x44, 0x8e, 0x69, 0x7c, 0x03, 0xf2, 0x31, 0x2f, 0x99, 0xdc, 0xaf, 0x3e, 0x8a, 0x91, 0x6b,
+];
+pub const USER_ID: [u8; 32] = [
+    0x78, 0x1c, 0x78, 0x60, 0xad, 0x88, 0xd2, 0x63, 0x32, 0x62, 0x2a, 0xf1, 0x74, 0x5d, 0xed, 0xb2,
+    0xe7, 0xa4, 0x2b, 0x44, 0x89, 0x29, 0x39, 0xc5, 0x56, 0x64, 0x01, 0x27, 0x0d, 0xbb, 0xc4, 0x49,
+];
+pub const RELYING_PARTY_ID: &str = "localhost";
+pub const PIN: &str = "1234";
+
+pub fn cstr(bytes: &'static [u8]) -> &'static CStr {
+    CStr::from_bytes_with_nul(bytes).unwrap()
+}
+
+pub fn relying_party_id() -> &'static CStr {
+    cstr(b"localhost\0")
+}
+
+pub fn pin() -> &'static CStr {
+    cstr(b"1234\0")
+}
+
+/// Opens a device connected to given authenticator.
+pub fn open(fido: &Fido, authenticator: &VirtualAuthenticator) -> Device {
+    fido.new_virtual_device(authenticator)
+        .expect("Unable to open virtual device")
+}
+
+/// Returns the parameters of a credential for `localhost`, which tests adjust as needed.
+pub fn credential_data() -> CredentialCreationData<'static> {
+    CredentialCreationData::with_defaults(
+        &CLIENT_DATA_HASH,
+        relying_party_id(),
+        cstr(b"Oost West, Thuis Best\0"),
+        &USER_ID,
+        cstr(b"John Doe\0"),
+    )
+}
+
+/// Returns the parameters of an assertion for `localhost` with given allowed credentials.
+pub fn assertion_data<'a>(allowed_ids: Option<&'a [&'a [u8]]>) -> AssertionCreationData<'a> {
+    AssertionCreationData::with_defaults(allowed_ids, &CLIENT_DATA_HASH, relying_party_id())
+}
+
+/// Creates a credential on the device.
+pub fn make_credential(
+    fido: &Fido,
+    device: &mut Device,
+    data: CredentialCreationData<'_>,
+    pin: Option<&CStr>,
+) -> Result<Credential, FidoError> {
+    device.request_credential_creation(fido.new_credential_creator(data)?, pin)
+}
+
+/// Requests an assertion from the device.
+pub fn get_assertion(
+    fido: &Fido,
+    device: &mut Device,
+    data: AssertionCreationData<'_>,
+    pin: Option<&CStr>,
+) -> Result<Assertion, FidoError> {
+    device.request_assertion_verification(fido.new_assertion_creator(data)?, pin)
+}
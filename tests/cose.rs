@@ -0,0 +1,120 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+
+const CREDENTIAL_TYPES: [CredentialType; 3] = [
+    CredentialType::ES256,
+    CredentialType::EDDSA,
+    CredentialType::RS256,
+];
+
+/// Creates a credential of given type, and returns it with its public key as attested.
+fn attested_key(
+    fido: &Fido,
+    device: &mut Device,
+    credential_type: CredentialType,
+) -> (Credential, Vec<u8>) {
+    let mut data = credential_data();
+    data.credential_type = credential_type;
+    let credential = make_credential(fido, device, data, None).unwrap();
+    let public_key = credential
+        .as_ref()
+        .authenticator_data()
+        .unwrap()
+        .attested_credential
+        .unwrap()
+        .public_key
+        .to_vec();
+    (credential, public_key)
+}
+
+#[test]
+fn round_trips_attested_keys() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    for &credential_type in CREDENTIAL_TYPES.iter() {
+        let (credential, attested) = attested_key(&fido, &mut device, credential_type);
+        let public_key = PublicKey::from_cose(&attested).unwrap();
+        assert_eq!(public_key.to_cose(), attested);
+        assert_eq!(
+            credential.as_ref().public_key().unwrap().to_cose(),
+            attested
+        );
+    }
+}
+
+#[test]
+fn verifies_assertions_with_reloaded_keys() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    let credentials = CREDENTIAL_TYPES
+        .iter()
+        .map(|&credential_type| attested_key(&fido, &mut device, credential_type))
+        .collect::<Vec<_>>();
+    for (index, (credential, attested)) in credentials.iter().enumerate() {
+        let assertion = get_assertion(
+            &fido,
+            &mut device,
+            assertion_data(Some(&[credential.as_ref().id])),
+            None,
+        )
+        .unwrap();
+        assert!(assertion.verify_one(PublicKey::from_cose(attested).unwrap()));
+
+        // Keys of other credentials do not verify it
+        let (_, other) = &credentials[(index + 1) % credentials.len()];
+        assert!(!assertion.verify_one(PublicKey::from_cose(other).unwrap()));
+    }
+}
+
+#[test]
+fn rejects_malformed_keys() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    let (_, attested) = attested_key(&fido, &mut device, CredentialType::ES256);
+
+    // Map with kty, alg, crv, x and y, where y is the last 35 bytes
+    let (without_y, _) = attested.split_at(attested.len() - 35);
+    let malformed = [
+        &attested[..attested.len() - 1],
+        &[&attested[..], &[0]].concat(),
+        &[&[0xa4], &without_y[1..]].concat(),
+        &[0xa1, 0x01],
+        &[],
+    ];
+    for data in malformed.iter() {
+        let err = PublicKey::from_cose(data).err().unwrap();
+        assert_eq!(err.to_string(), "FIDO_ERR_INVALID_ARGUMENT");
+    }
+}
+
+#[test]
+fn rejects_unsupported_algorithms() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    let (_, attested) = attested_key(&fido, &mut device, CredentialType::ES256);
+    assert_eq!(&attested[..7], [0xa5, 0x01, 0x02, 0x03, 0x26, 0x20, 0x01]);
+
+    // ES256 on P-384
+    let mut p384 = attested.clone();
+    p384[6] = 0x02;
+    // ES384 on P-256
+    let mut es384 = attested;
+    es384[4] = 0x38;
+    es384.insert(5, 0x22);
+    // EC2 key on P-384 with ES384, without coordinates
+    let incomplete = vec![0xa3, 0x01, 0x02, 0x03, 0x38, 0x22, 0x20, 0x02];
+
+    for data in &[p384, es384, incomplete] {
+        let err = PublicKey::from_cose(data).err().unwrap();
+        assert_eq!(err.to_string(), "FIDO_ERR_UNSUPPORTED_ALGORITHM");
+        assert_eq!(err.kind(), FidoErrorKind::Unsupported);
+    }
+}
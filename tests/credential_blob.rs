@@ -0,0 +1,141 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+
+const KEY_DERIVATION_CONTEXT: &[u8] = b"ssh-key-v1:alice";
+
+fn max_length(device: &mut Device) -> usize {
+    device
+        .request_cbor_data()
+        .unwrap()
+        .as_ref()
+        .max_credential_blob_length as usize
+}
+
+/// Creates a resident credential for given user with given blob.
+fn create(
+    fido: &Fido,
+    device: &mut Device,
+    user_id: &'static [u8],
+    blob: Option<&'static [u8]>,
+) -> Credential {
+    let mut data = credential_data();
+    data.user_id = user_id;
+    data.options = CredentialOptions::RESIDENT_KEY;
+    data.credential_blob = blob;
+    make_credential(fido, device, data, None).unwrap()
+}
+
+/// Requests an assertion with given credentials, which returns the credential blob if `request`.
+fn credential_blob(
+    fido: &Fido,
+    device: &mut Device,
+    allowed_ids: Option<&[&[u8]]>,
+    request: bool,
+) -> Option<Vec<u8>> {
+    let mut data = assertion_data(allowed_ids);
+    if request {
+        data.extensions = AssertionExtensions::CREDENTIAL_BLOB;
+    }
+    let assertion = get_assertion(fido, device, data, None).unwrap();
+    assert_eq!(assertion.len(), 1);
+    let blob = assertion.iter().next().unwrap().credential_blob;
+    blob.map(<[u8]>::to_vec)
+}
+
+#[test]
+fn returns_stored_blob() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    assert!(KEY_DERIVATION_CONTEXT.len() <= max_length(&mut device));
+
+    let credential = create(&fido, &mut device, b"alice", Some(KEY_DERIVATION_CONTEXT));
+    credential.verify().unwrap();
+    let credential = credential.as_ref();
+    assert!(credential.credential_blob_stored);
+
+    assert_eq!(
+        credential_blob(&fido, &mut device, Some(&[credential.id]), true).as_deref(),
+        Some(KEY_DERIVATION_CONTEXT)
+    );
+    // Discovered credentials return it too
+    assert_eq!(
+        credential_blob(&fido, &mut device, None, true).as_deref(),
+        Some(KEY_DERIVATION_CONTEXT)
+    );
+
+    // Without requesting it, the blob is not returned
+    assert_eq!(
+        credential_blob(&fido, &mut device, Some(&[credential.id]), false),
+        None
+    );
+}
+
+#[test]
+fn stores_blobs_up_to_maximum_length() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    let max_length = max_length(&mut device);
+
+    let largest: &'static [u8] = vec![0x42; max_length].leak();
+    let credential = create(&fido, &mut device, b"alice", Some(largest));
+    credential.verify().unwrap();
+    assert!(credential.as_ref().credential_blob_stored);
+    assert_eq!(
+        credential_blob(&fido, &mut device, Some(&[credential.as_ref().id]), true).as_deref(),
+        Some(largest)
+    );
+
+    // Blobs which are too large are not stored, but the credential is created
+    let too_large: &'static [u8] = vec![0x42; max_length + 1].leak();
+    let credential = create(&fido, &mut device, b"bob", Some(too_large));
+    assert!(!credential.as_ref().credential_blob_stored);
+    assert_eq!(authenticator.resident_credential_count(RELYING_PARTY_ID), 2);
+    assert_ne!(
+        credential_blob(&fido, &mut device, Some(&[credential.as_ref().id]), true).as_deref(),
+        Some(too_large)
+    );
+}
+
+#[test]
+fn reports_credentials_without_blob() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    let credential = create(&fido, &mut device, b"alice", None);
+    credential.verify().unwrap();
+    assert!(!credential.as_ref().credential_blob_stored);
+    assert!(
+        credential_blob(&fido, &mut device, Some(&[credential.as_ref().id]), true)
+            .unwrap_or_default()
+            .is_empty()
+    );
+}
+
+#[test]
+fn reports_other_extensions_if_blob_is_not_stored() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    let mut data = credential_data();
+    data.options = CredentialOptions::RESIDENT_KEY;
+    data.extensions = CredentialExtensions::HMAC_SECRET;
+    data.protection = Some(CredentialProtection::UserVerificationOptionalWithCredentialIdList);
+    let too_large = vec![0x42; max_length(&mut device) + 1];
+    data.credential_blob = Some(&too_large);
+    let credential = make_credential(&fido, &mut device, data, None).unwrap();
+    // libfido2 expects the requested blob to be stored
+    let err = credential.verify().err().unwrap();
+    assert_eq!(err.to_string(), "FIDO_ERR_INVALID_PARAM");
+    let credential = credential.as_ref();
+    assert!(!credential.credential_blob_stored);
+    assert_eq!(
+        credential.protection,
+        Some(CredentialProtection::UserVerificationOptionalWithCredentialIdList)
+    );
+}
@@ -0,0 +1,185 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+use sha2::{Digest, Sha256};
+use std::ffi::CString;
+
+// Relying party ID, relying party name and user ID of the resident credentials to create
+const ACCOUNTS: [(&str, &str, &[u8]); 3] = [
+    ("example.com", "Example", b"alice"),
+    ("example.com", "Example", b"bob"),
+    ("example.org", "Example Org", b"alice"),
+];
+
+/// Opens a device with a PIN and the resident credentials of `ACCOUNTS`.
+fn open_with_accounts(fido: &Fido, authenticator: &VirtualAuthenticator) -> Device {
+    let mut device = open(fido, authenticator);
+    for &(relying_party_id, relying_party_name, user_id) in ACCOUNTS.iter() {
+        let relying_party_id = CString::new(relying_party_id).unwrap();
+        let relying_party_name = CString::new(relying_party_name).unwrap();
+        let user_name = CString::new(user_id).unwrap();
+        let mut data = CredentialCreationData::with_defaults(
+            &CLIENT_DATA_HASH,
+            &relying_party_id,
+            &relying_party_name,
+            user_id,
+            &user_name,
+        );
+        data.options = CredentialOptions::RESIDENT_KEY;
+        make_credential(fido, &mut device, data, Some(pin())).unwrap();
+    }
+    device
+}
+
+#[test]
+fn counts_resident_credentials() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    let empty = device.credential_metadata(Some(pin())).unwrap();
+    assert_eq!(empty.existing, 0);
+    assert!(empty.remaining > 0);
+
+    let mut device = open_with_accounts(&fido, &authenticator);
+    let metadata = device.credential_metadata(Some(pin())).unwrap();
+    assert_eq!(metadata.existing, 3);
+    assert_eq!(metadata.remaining, empty.remaining - 3);
+}
+
+#[test]
+fn lists_relying_parties_and_credentials() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open_with_accounts(&fido, &authenticator);
+
+    let mut relying_parties = device.resident_relying_parties(Some(pin())).unwrap();
+    relying_parties.sort_by(|a, b| a.id.cmp(&b.id));
+    assert_eq!(relying_parties.len(), 2);
+    for (relying_party, &(id, name)) in relying_parties
+        .iter()
+        .zip(&[("example.com", "Example"), ("example.org", "Example Org")])
+    {
+        assert_eq!(relying_party.id, id);
+        assert_eq!(relying_party.name.as_deref(), Some(name));
+        assert_eq!(relying_party.id_hash, Sha256::digest(id).to_vec());
+    }
+
+    let relying_party_id = CString::new("example.com").unwrap();
+    let mut credentials = device
+        .resident_credentials(&relying_party_id, Some(pin()))
+        .unwrap();
+    credentials.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+    assert_eq!(credentials.len(), 2);
+    for (credential, user_id) in credentials.iter().zip(&[&b"alice"[..], b"bob"]) {
+        assert_eq!(credential.user_id, *user_id);
+        assert_eq!(
+            credential.user_name.as_deref().map(str::as_bytes),
+            Some(*user_id)
+        );
+        assert_eq!(credential.credential_type, CredentialType::ES256);
+        assert!(credential.public_key().is_ok());
+    }
+    assert_ne!(credentials[0].id, credentials[1].id);
+
+    // Credentials of relying parties without resident credentials are empty
+    let relying_party_id = CString::new("example.net").unwrap();
+    assert!(device
+        .resident_credentials(&relying_party_id, Some(pin()))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn listed_credentials_can_authenticate() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    let mut data = credential_data();
+    data.options = CredentialOptions::RESIDENT_KEY;
+    data.credential_type = CredentialType::EDDSA;
+    make_credential(&fido, &mut device, data, Some(pin())).unwrap();
+
+    let credential = device
+        .resident_credentials(relying_party_id(), Some(pin()))
+        .unwrap()
+        .remove(0);
+    assert_eq!(credential.credential_type, CredentialType::EDDSA);
+    let assertion = get_assertion(
+        &fido,
+        &mut device,
+        assertion_data(Some(&[&credential.id])),
+        Some(pin()),
+    )
+    .unwrap();
+    assert!(assertion.verify_one(credential.public_key().unwrap()));
+}
+
+#[test]
+fn deletes_resident_credentials() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open_with_accounts(&fido, &authenticator);
+
+    let relying_party_id = CString::new("example.com").unwrap();
+    let bob = device
+        .resident_credentials(&relying_party_id, Some(pin()))
+        .unwrap()
+        .into_iter()
+        .find(|credential| credential.user_id == b"bob")
+        .unwrap();
+    device
+        .delete_resident_credential(&bob.id, Some(pin()))
+        .unwrap();
+    assert_eq!(authenticator.resident_credential_count("example.com"), 1);
+    assert_eq!(authenticator.resident_credential_count("example.org"), 1);
+
+    // Deleting the credential again fails
+    assert_eq!(
+        device
+            .delete_resident_credential(&bob.id, Some(pin()))
+            .map_err(FidoError::kind),
+        Err(FidoErrorKind::Other)
+    );
+
+    // Relying parties without resident credentials are not listed anymore
+    let relying_party_id = CString::new("example.org").unwrap();
+    for credential in device
+        .resident_credentials(&relying_party_id, Some(pin()))
+        .unwrap()
+    {
+        device
+            .delete_resident_credential(&credential.id, Some(pin()))
+            .unwrap();
+    }
+    let relying_parties = device.resident_relying_parties(Some(pin())).unwrap();
+    assert_eq!(relying_parties.len(), 1);
+    assert_eq!(relying_parties[0].id, "example.com");
+    assert_eq!(device.credential_metadata(Some(pin())).unwrap().existing, 1);
+}
+
+#[test]
+fn requires_pin() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open_with_accounts(&fido, &authenticator);
+
+    assert!(device.credential_metadata(None).is_err());
+    assert!(device.resident_relying_parties(None).is_err());
+    let relying_party_id = CString::new("example.com").unwrap();
+    assert!(device
+        .resident_credentials(&relying_party_id, None)
+        .is_err());
+
+    let wrong_pin = CString::new("4321").unwrap();
+    assert_eq!(
+        device
+            .credential_metadata(Some(&wrong_pin))
+            .unwrap_err()
+            .to_string(),
+        "FIDO_ERR_PIN_INVALID"
+    );
+    assert_eq!(authenticator.resident_credential_count("example.com"), 2);
+}
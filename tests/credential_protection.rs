@@ -0,0 +1,153 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+
+/// Creates a resident credential for given user with given protection policy.
+fn create(
+    fido: &Fido,
+    device: &mut Device,
+    user_id: &'static [u8],
+    protection: Option<CredentialProtection>,
+) -> Credential {
+    let mut data = credential_data();
+    data.user_id = user_id;
+    data.options = CredentialOptions::RESIDENT_KEY;
+    data.protection = protection;
+    data.enforce_protection = protection.is_some();
+    let credential = make_credential(fido, device, data, Some(pin())).unwrap();
+    credential.verify().unwrap();
+    credential
+}
+
+#[test]
+fn reports_requested_protection() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    for &protection in &[
+        CredentialProtection::UserVerificationOptional,
+        CredentialProtection::UserVerificationOptionalWithCredentialIdList,
+        CredentialProtection::UserVerificationRequired,
+    ] {
+        let credential = create(&fido, &mut device, b"protected", Some(protection));
+        assert_eq!(credential.as_ref().protection, Some(protection));
+    }
+
+    // Credentials without protection do not report a policy
+    let credential = create(&fido, &mut device, b"unprotected", None);
+    assert_eq!(credential.as_ref().protection, None);
+}
+
+#[test]
+fn hides_protected_credentials_from_discovery() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    create(
+        &fido,
+        &mut device,
+        b"optional",
+        Some(CredentialProtection::UserVerificationOptionalWithCredentialIdList),
+    );
+    create(
+        &fido,
+        &mut device,
+        b"required",
+        Some(CredentialProtection::UserVerificationRequired),
+    );
+
+    assert!(get_assertion(&fido, &mut device, assertion_data(None), None).is_err());
+    let assertion = get_assertion(&fido, &mut device, assertion_data(None), Some(pin())).unwrap();
+    assert_eq!(assertion.len(), 2);
+
+    // Credentials without the ID list requirement are discoverable without the PIN
+    create(
+        &fido,
+        &mut device,
+        b"unprotected",
+        Some(CredentialProtection::UserVerificationOptional),
+    );
+    let assertion = get_assertion(&fido, &mut device, assertion_data(None), None).unwrap();
+    assert_eq!(assertion.len(), 1);
+    assert_eq!(
+        assertion.iter().next().unwrap().user_id,
+        Some(&b"unprotected"[..])
+    );
+}
+
+#[test]
+fn requires_user_verification_for_protected_credentials() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    let optional = create(
+        &fido,
+        &mut device,
+        b"optional",
+        Some(CredentialProtection::UserVerificationOptionalWithCredentialIdList),
+    );
+    let required = create(
+        &fido,
+        &mut device,
+        b"required",
+        Some(CredentialProtection::UserVerificationRequired),
+    );
+    let optional = optional.as_ref();
+    let required = required.as_ref();
+
+    // Credentials which only require the ID to be known can be used without the PIN
+    let assertion = get_assertion(
+        &fido,
+        &mut device,
+        assertion_data(Some(&[optional.id])),
+        None,
+    )
+    .unwrap();
+    assert!(assertion.verify_one(optional.public_key().unwrap()));
+
+    assert!(get_assertion(
+        &fido,
+        &mut device,
+        assertion_data(Some(&[required.id])),
+        None
+    )
+    .is_err());
+    let assertion = get_assertion(
+        &fido,
+        &mut device,
+        assertion_data(Some(&[required.id])),
+        Some(pin()),
+    )
+    .unwrap();
+    assert!(assertion.verify_one(required.public_key().unwrap()));
+}
+
+#[test]
+fn enforces_protection_on_devices_without_support() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    device.force_mode(DeviceMode::FidoU2F);
+
+    let mut data = credential_data();
+    data.protection = Some(CredentialProtection::UserVerificationRequired);
+    data.enforce_protection = true;
+    let err = make_credential(&fido, &mut device, data, None)
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "FIDO_ERR_UNSUPPORTED_EXTENSION");
+    assert_eq!(err.kind(), FidoErrorKind::Unsupported);
+    assert_eq!(authenticator.credential_count(), 0);
+
+    // Without enforcement, libfido2 rejects the extension since U2F cannot carry it
+    data.enforce_protection = false;
+    let err = make_credential(&fido, &mut device, data, None)
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "FIDO_ERR_UNSUPPORTED_OPTION");
+    assert_eq!(authenticator.credential_count(), 0);
+}
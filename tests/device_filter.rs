@@ -0,0 +1,103 @@
+use libfido2::*;
+use std::ffi::CString;
+
+const AAGUID: [u8; 16] = [
+    0xcb, 0x69, 0x48, 0x1e, 0x8f, 0xf7, 0x40, 0x39, 0x93, 0xec, 0x0a, 0x27, 0x29, 0xa1, 0x54, 0xa8,
+];
+
+fn yubikey(cbor_info: Option<CBORInfo>) -> DeviceInfo {
+    DeviceInfo {
+        path: CString::new("/dev/hidraw0").unwrap(),
+        product_id: 0x0407,
+        vendor_id: 0x1050,
+        manufacturer: "Yubico".to_owned(),
+        product: "YubiKey OTP+FIDO+CCID".to_owned(),
+        cbor_info,
+    }
+}
+
+fn cbor_info() -> CBORInfo {
+    CBORInfo {
+        aag_uid: Some(AAGUID.to_vec()),
+        pin_protocols: vec![2, 1],
+        extensions: vec!["credProtect".to_owned(), "hmac-secret".to_owned()],
+        ctap_versions: vec!["FIDO_2_0".to_owned(), "FIDO_2_1".to_owned()],
+        options: vec![("rk".to_owned(), true), ("uv".to_owned(), false)]
+            .into_iter()
+            .collect(),
+        max_credential_blob_length: 0,
+    }
+}
+
+#[test]
+fn matches_everything_by_default() {
+    assert!(DeviceFilter::default().matches(&yubikey(None)));
+    assert!(DeviceFilter::default().matches(&yubikey(Some(cbor_info()))));
+}
+
+#[test]
+fn matches_ids() {
+    let filter = DeviceFilter {
+        vendor_ids: vec![0x1050],
+        product_ids: vec![0x0402, 0x0407],
+        ..DeviceFilter::default()
+    };
+    assert!(filter.matches(&yubikey(None)));
+
+    let filter = DeviceFilter {
+        vendor_ids: vec![0x096e],
+        ..DeviceFilter::default()
+    };
+    assert!(!filter.matches(&yubikey(None)));
+}
+
+#[test]
+fn matches_product_patterns() {
+    let matches = |patterns: &[&str]| {
+        DeviceFilter {
+            products: patterns.iter().map(|p| p.to_string()).collect(),
+            ..DeviceFilter::default()
+        }
+        .matches(&yubikey(None))
+    };
+    assert!(matches(&["YubiKey OTP+FIDO+CCID"]));
+    assert!(matches(&["YubiKey*"]));
+    assert!(matches(&["*FIDO*"]));
+    assert!(matches(&["*"]));
+    assert!(matches(&["Security Key*", "*CCID"]));
+    assert!(!matches(&["Security Key*"]));
+    assert!(!matches(&["YubiKey"]));
+    assert!(!matches(&["*FIDO"]));
+}
+
+#[test]
+fn matches_cbor_info() {
+    let filter = DeviceFilter {
+        ctap_versions: vec!["FIDO_2_1".to_owned()],
+        options: vec!["rk".to_owned()],
+        aag_uids: vec![AAGUID],
+        ..DeviceFilter::default()
+    };
+    assert!(filter.matches(&yubikey(Some(cbor_info()))));
+    // Devices without CBOR info can not be checked
+    assert!(!filter.matches(&yubikey(None)));
+
+    // Options must be enabled, rather than only present
+    let filter = DeviceFilter {
+        options: vec!["uv".to_owned()],
+        ..DeviceFilter::default()
+    };
+    assert!(!filter.matches(&yubikey(Some(cbor_info()))));
+
+    let filter = DeviceFilter {
+        aag_uids: vec![[0; 16]],
+        ..DeviceFilter::default()
+    };
+    assert!(!filter.matches(&yubikey(Some(cbor_info()))));
+
+    let filter = DeviceFilter {
+        ctap_versions: vec!["FIDO_2_0".to_owned(), "FIDO_2_2".to_owned()],
+        ..DeviceFilter::default()
+    };
+    assert!(!filter.matches(&yubikey(Some(cbor_info()))));
+}
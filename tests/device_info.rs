@@ -0,0 +1,71 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+use std::ffi::CString;
+
+#[test]
+fn owns_cbor_info_of_the_device() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    let cbor_data = device.request_cbor_data().unwrap();
+    let cbor_info = CBORInfo::from(&cbor_data);
+    drop(cbor_data);
+
+    assert_eq!(
+        cbor_info.aag_uid.as_deref(),
+        Some(&authenticator.aag_uid()[..])
+    );
+    assert!(cbor_info.ctap_versions.iter().any(|v| v == "FIDO_2_0"));
+    assert_eq!(cbor_info.options.get("rk"), Some(&true));
+    assert_eq!(cbor_info.options.get("clientPin"), Some(&false));
+    assert!(cbor_info.pin_protocols.contains(&1));
+    assert!(cbor_info.extensions.iter().any(|e| e == "hmac-secret"));
+}
+
+#[test]
+fn rejects_paths_which_are_not_utf8() {
+    let mut info = DeviceInfo {
+        path: CString::new("/dev/hidraw0").unwrap(),
+        product_id: 0,
+        vendor_id: 0,
+        manufacturer: String::new(),
+        product: String::new(),
+        cbor_info: None,
+    };
+    assert_eq!(info.device_path().unwrap().to_str(), "/dev/hidraw0");
+
+    info.path = CString::new(b"/dev/\xffhidraw0".to_vec()).unwrap();
+    let err = info.device_path().err().unwrap();
+    assert_eq!(err.to_string(), "FIDO_ERR_INVALID_ARGUMENT");
+}
+
+#[test]
+fn round_trips_through_serde() {
+    let fido = Fido::new(false);
+    let mut device = open(&fido, &VirtualAuthenticator::with_pin(PIN));
+    let info = DeviceInfo {
+        path: CString::new("/dev/hidraw0").unwrap(),
+        product_id: 0x0407,
+        vendor_id: 0x1050,
+        manufacturer: "Yubico".to_owned(),
+        product: "YubiKey OTP+FIDO+CCID".to_owned(),
+        cbor_info: Some(CBORInfo::from(&device.request_cbor_data().unwrap())),
+    };
+
+    let json = serde_json::to_value(&info).unwrap();
+    assert_eq!(json["path"], "/dev/hidraw0");
+    assert_eq!(json["cbor_info"]["options"]["clientPin"], true);
+    let loaded: DeviceInfo = serde_json::from_value(json).unwrap();
+    assert_eq!(loaded, info);
+
+    // Information stored before the maximum credential blob length was added still loads
+    let mut json = serde_json::to_value(&info).unwrap();
+    json["cbor_info"]
+        .as_object_mut()
+        .unwrap()
+        .remove("max_credential_blob_length");
+    let loaded: DeviceInfo = serde_json::from_value(json).unwrap();
+    assert_eq!(loaded.cbor_info.unwrap().max_credential_blob_length, 0);
+}
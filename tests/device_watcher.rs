@@ -0,0 +1,86 @@
+use libfido2::*;
+use std::{collections::VecDeque, ffi::CString, thread, time::Duration};
+
+fn device(path: &str) -> DeviceInfo {
+    DeviceInfo {
+        path: CString::new(path).unwrap(),
+        product_id: 0x0407,
+        vendor_id: 0x1050,
+        manufacturer: "Yubico".to_owned(),
+        product: "YubiKey OTP+FIDO+CCID".to_owned(),
+        cbor_info: None,
+    }
+}
+
+/// Lists the given snapshots of connected devices one by one, and then keeps listing the last one.
+struct Snapshots(VecDeque<Vec<DeviceInfo>>);
+
+impl DeviceSource for Snapshots {
+    fn devices(&mut self) -> Vec<DeviceInfo> {
+        self.0.front().cloned().unwrap_or_default()
+    }
+
+    fn wait(&mut self) {
+        if self.0.len() > 1 {
+            self.0.pop_front();
+        } else {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+fn snapshots() -> Snapshots {
+    Snapshots(
+        vec![
+            vec![device("/dev/hidraw0")],
+            vec![device("/dev/hidraw0"), device("/dev/hidraw1")],
+            vec![device("/dev/hidraw1")],
+            vec![],
+        ]
+        .into(),
+    )
+}
+
+fn expected_events() -> Vec<DeviceEvent> {
+    vec![
+        // Devices which are already connected are reported first
+        DeviceEvent::Added(device("/dev/hidraw0")),
+        DeviceEvent::Added(device("/dev/hidraw1")),
+        DeviceEvent::Removed(CString::new("/dev/hidraw0").unwrap()),
+        DeviceEvent::Removed(CString::new("/dev/hidraw1").unwrap()),
+    ]
+}
+
+#[test]
+fn reports_changes_of_the_source() {
+    let events = DeviceWatcher::new(snapshots()).take(4).collect::<Vec<_>>();
+    assert_eq!(events, expected_events());
+}
+
+#[test]
+fn reports_changed_devices_as_replaced() {
+    let mut changed = device("/dev/hidraw0");
+    changed.product = "Security Key by Yubico".to_owned();
+    let source = Snapshots(vec![vec![device("/dev/hidraw0")], vec![changed.clone()]].into());
+
+    let events = DeviceWatcher::new(source).take(3).collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        [
+            DeviceEvent::Added(device("/dev/hidraw0")),
+            DeviceEvent::Removed(CString::new("/dev/hidraw0").unwrap()),
+            DeviceEvent::Added(changed),
+        ]
+    );
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn streams_changes_of_the_source() {
+    let mut stream = DeviceWatcher::new(snapshots()).into_stream();
+    let mut events = Vec::new();
+    for _ in 0..4 {
+        events.push(stream.next().await.unwrap());
+    }
+    assert_eq!(events, expected_events());
+}
@@ -0,0 +1,240 @@
+mod common;
+
+use ciborium::value::Value;
+use common::*;
+use libfido2::*;
+
+const AUTHENTICATOR_MAKE_CREDENTIAL: u8 = 0x01;
+const CTAP1_ERR_INVALID_PARAMETER: u8 = 0x02;
+const CTAP2_ERR_INVALID_OPTION: u8 = 0x2c;
+const COSE_ES256: i64 = -7;
+
+// Serial numbers of the batch and enterprise attestation certificates of virtual authenticators
+const BATCH_CERTIFICATE_SERIAL: [u8; 20] = [
+    0x0a, 0xc7, 0xbb, 0x0e, 0xb3, 0xc1, 0x00, 0x84, 0x3c, 0xa4, 0xfc, 0x7e, 0xad, 0xd2, 0x1e, 0x37,
+    0x4b, 0x57, 0x45, 0x0b,
+];
+const ENTERPRISE_CERTIFICATE_SERIAL: [u8; 20] = [
+    0x62, 0x1d, 0xcc, 0x51, 0xf0, 0x87, 0x6f, 0xa9, 0xa0, 0xa2, 0xfd, 0x17, 0x6a, 0xd1, 0x48, 0x48,
+    0x96, 0x76, 0xe0, 0x45,
+];
+const DEVICE_SERIAL: &str = "VA-0001";
+
+// Attestation returned by a raw authenticatorMakeCredential command
+struct RawAttestation {
+    auth_data: Vec<u8>,
+    x5c: Vec<u8>,
+    signature: Vec<u8>,
+    enterprise: bool,
+}
+
+fn int_map(entries: Vec<(i64, Value)>) -> Value {
+    Value::Map(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.into(), value))
+            .collect(),
+    )
+}
+
+fn text_map(entries: Vec<(&str, Value)>) -> Value {
+    Value::Map(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.into(), value))
+            .collect(),
+    )
+}
+
+fn field(map: &[(Value, Value)], key: Value) -> Option<&Value> {
+    map.iter()
+        .find(|(entry, _)| *entry == key)
+        .map(|(_, value)| value)
+}
+
+/// Sends authenticatorMakeCredential with given enterprise attestation mode,
+/// and returns the CTAP2 status with the attestation on success.
+fn make_credential_raw(device: &mut Device, mode: Option<u64>) -> (u8, Option<RawAttestation>) {
+    let mut parameters = vec![
+        (0x01, CLIENT_DATA_HASH.as_ref().into()),
+        (0x02, text_map(vec![("id", RELYING_PARTY_ID.into())])),
+        (
+            0x03,
+            text_map(vec![
+                ("id", USER_ID.as_ref().into()),
+                ("name", "John Doe".into()),
+            ]),
+        ),
+        (
+            0x04,
+            Value::Array(vec![text_map(vec![
+                ("alg", COSE_ES256.into()),
+                ("type", "public-key".into()),
+            ])]),
+        ),
+    ];
+    if let Some(mode) = mode {
+        parameters.push((0x0a, mode.into()));
+    }
+    let mut payload = Vec::new();
+    ciborium::ser::into_writer(&int_map(parameters), &mut payload).unwrap();
+
+    let response = device
+        .transact_cbor(AUTHENTICATOR_MAKE_CREDENTIAL, &payload)
+        .unwrap();
+    if response.status != 0x00 {
+        return (response.status, None);
+    }
+    let response: Value = ciborium::de::from_reader(&response.data[..]).unwrap();
+    let response = response.as_map().unwrap();
+    let statement = field(response, 0x03.into()).unwrap().as_map().unwrap();
+    let attestation = RawAttestation {
+        auth_data: field(response, 0x02.into())
+            .unwrap()
+            .as_bytes()
+            .unwrap()
+            .clone(),
+        x5c: field(statement, "x5c".into()).unwrap().as_array().unwrap()[0]
+            .as_bytes()
+            .unwrap()
+            .clone(),
+        signature: field(statement, "sig".into())
+            .unwrap()
+            .as_bytes()
+            .unwrap()
+            .clone(),
+        enterprise: field(response, 0x04.into()) == Some(&Value::Bool(true)),
+    };
+    (0x00, Some(attestation))
+}
+
+fn verified_credential(attestation: &RawAttestation) -> Credential {
+    let credential = Credential::from_attestation(CredentialAttestationData {
+        format: CredentialFormat::Fido2,
+        auth_data: &attestation.auth_data,
+        x509_certificate: Some(&attestation.x5c),
+        signature: &attestation.signature,
+        client_data_hash: &CLIENT_DATA_HASH,
+        relying_party_id: relying_party_id(),
+        credential_type: CredentialType::ES256,
+    })
+    .unwrap();
+    credential.verify().unwrap();
+    credential
+}
+
+#[test]
+fn identifies_devices_in_enterprise_attestations() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    authenticator.set_enterprise_attestation(true);
+    let mut device = open(&fido, &authenticator);
+
+    for &mode in &[
+        EnterpriseAttestation::VendorFacilitated,
+        EnterpriseAttestation::PlatformManaged,
+    ] {
+        let (status, attestation) = make_credential_raw(&mut device, Some(mode as u64));
+        assert_eq!(status, 0x00);
+        let attestation = attestation.unwrap();
+        assert!(attestation.enterprise);
+
+        let credential = verified_credential(&attestation);
+        let credential = credential.as_ref();
+        assert_eq!(
+            credential.certificate_serial_number(),
+            Some(&ENTERPRISE_CERTIFICATE_SERIAL[..])
+        );
+        assert_eq!(credential.device_serial_number(), Some(DEVICE_SERIAL));
+    }
+}
+
+#[test]
+fn uses_batch_attestation_by_default() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    authenticator.set_enterprise_attestation(true);
+    let mut device = open(&fido, &authenticator);
+
+    // Without a request, the shared certificate does not identify the device
+    let (_, attestation) = make_credential_raw(&mut device, None);
+    let attestation = attestation.unwrap();
+    assert!(!attestation.enterprise);
+    let credential = verified_credential(&attestation);
+    let credential = credential.as_ref();
+    assert_eq!(
+        credential.certificate_serial_number(),
+        Some(&BATCH_CERTIFICATE_SERIAL[..])
+    );
+    assert_eq!(credential.device_serial_number(), None);
+
+    let credential = make_credential(&fido, &mut device, credential_data(), None).unwrap();
+    let credential = credential.as_ref();
+    assert!(!credential.enterprise_attestation);
+    assert_eq!(
+        credential.certificate_serial_number(),
+        Some(&BATCH_CERTIFICATE_SERIAL[..])
+    );
+    assert_eq!(credential.device_serial_number(), None);
+
+    // Self attestation has no certificate at all
+    authenticator.set_self_attestation(true);
+    let credential = make_credential(&fido, &mut device, credential_data(), None).unwrap();
+    assert_eq!(credential.as_ref().certificate_serial_number(), None);
+    assert_eq!(credential.as_ref().device_serial_number(), None);
+}
+
+#[test]
+fn rejects_requests_unless_enabled() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    let (status, _) = make_credential_raw(&mut device, Some(1));
+    assert_eq!(status, CTAP1_ERR_INVALID_PARAMETER);
+
+    authenticator.set_enterprise_attestation(true);
+    let (status, _) = make_credential_raw(&mut device, Some(3));
+    assert_eq!(status, CTAP2_ERR_INVALID_OPTION);
+    assert_eq!(authenticator.credential_count(), 0);
+}
+
+#[cfg(not(fido2_1_14))]
+#[test]
+fn requires_libfido2_1_14_to_request() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    authenticator.set_enterprise_attestation(true);
+
+    let mut data = credential_data();
+    data.enterprise_attestation = Some(EnterpriseAttestation::VendorFacilitated);
+    let err = fido.new_credential_creator(data).err().unwrap();
+    assert_eq!(err.to_string(), "FIDO_ERR_UNSUPPORTED_OPTION");
+    assert_eq!(err.kind(), FidoErrorKind::Unsupported);
+}
+
+#[cfg(fido2_1_14)]
+#[test]
+fn requests_enterprise_attestation() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    let mut data = credential_data();
+    data.enterprise_attestation = Some(EnterpriseAttestation::VendorFacilitated);
+    assert!(make_credential(&fido, &mut device, data, Some(pin())).is_err());
+
+    device.enable_enterprise_attestation(Some(pin())).unwrap();
+    for &mode in &[
+        EnterpriseAttestation::VendorFacilitated,
+        EnterpriseAttestation::PlatformManaged,
+    ] {
+        let mut data = credential_data();
+        data.enterprise_attestation = Some(mode);
+        let credential = make_credential(&fido, &mut device, data, Some(pin())).unwrap();
+        credential.verify().unwrap();
+        let credential = credential.as_ref();
+        assert!(credential.enterprise_attestation);
+        assert_eq!(credential.device_serial_number(), Some(DEVICE_SERIAL));
+    }
+}
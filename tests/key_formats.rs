@@ -0,0 +1,181 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+
+// SubjectPublicKeyInfo up to the raw key, for P-256 (uncompressed point) and Ed25519 keys
+const P256_SPKI_PREFIX: [u8; 27] = [
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00, 0x04,
+];
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+const P384_PEM: &str = "-----BEGIN PUBLIC KEY-----
+MHYwEAYHKoZIzj0CAQYFK4EEACIDYgAEQ6qGMqsSpI4O4O/r+SDR6CBOXYyTuVne
+BIrZwfw2jPY4b6cN5rwJY3ih9SiM3heusyYBhFxyJ14qbyy9rHLT1POe5TsB4RDo
+KNvY6M24Y3xzpjXkBWJ7+NyaGzD+MQfc
+-----END PUBLIC KEY-----";
+
+fn create(fido: &Fido, device: &mut Device, credential_type: CredentialType) -> Credential {
+    let mut data = credential_data();
+    data.credential_type = credential_type;
+    make_credential(fido, device, data, None).unwrap()
+}
+
+fn assert_verifies(
+    fido: &Fido,
+    device: &mut Device,
+    credential: &Credential,
+    public_key: PublicKey,
+) {
+    let assertion = get_assertion(
+        fido,
+        device,
+        assertion_data(Some(&[credential.as_ref().id])),
+        None,
+    )
+    .unwrap();
+    assert!(assertion.verify_one(public_key));
+}
+
+#[test]
+fn round_trips_every_format() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    for &credential_type in &[
+        CredentialType::ES256,
+        CredentialType::EDDSA,
+        CredentialType::RS256,
+    ] {
+        let credential = create(&fido, &mut device, credential_type);
+        let public_key = credential.as_ref().public_key().unwrap();
+        let der = public_key.to_der();
+        let pem = public_key.to_pem();
+        let jwk = serde_json::to_string(&public_key.to_jwk()).unwrap();
+
+        for reloaded in [
+            PublicKey::from_der(&der).unwrap(),
+            PublicKey::from_pem(&pem).unwrap(),
+            PublicKey::from_jwk(&serde_json::from_str(&jwk).unwrap()).unwrap(),
+        ] {
+            assert_eq!(reloaded.to_der(), der);
+            assert_eq!(reloaded.to_pem(), pem);
+            assert_eq!(serde_json::to_string(&reloaded.to_jwk()).unwrap(), jwk);
+            assert_verifies(&fido, &mut device, &credential, reloaded);
+        }
+    }
+}
+
+#[test]
+fn encodes_standard_formats() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    // The DER encoding contains the same key as the COSE encoding
+    let credential = create(&fido, &mut device, CredentialType::ES256);
+    let public_key = credential.as_ref().public_key().unwrap();
+    let cose = public_key.to_cose();
+    let der = public_key.to_der();
+    assert_eq!(der[..27], P256_SPKI_PREFIX);
+    assert_eq!(der[27..59], cose[10..42]);
+    assert_eq!(der[59..], cose[45..]);
+    let jwk = serde_json::to_value(public_key.to_jwk()).unwrap();
+    assert_eq!(jwk["kty"], "EC");
+    assert_eq!(jwk["crv"], "P-256");
+    assert_eq!(jwk["alg"], "ES256");
+    assert_eq!(jwk["x"].as_str().unwrap().len(), 43);
+    assert!(jwk.get("n").is_none());
+
+    let credential = create(&fido, &mut device, CredentialType::EDDSA);
+    let public_key = credential.as_ref().public_key().unwrap();
+    let der = public_key.to_der();
+    assert_eq!(der[..12], ED25519_SPKI_PREFIX);
+    assert_eq!(der[12..], public_key.to_cose()[10..]);
+    let jwk = serde_json::to_value(public_key.to_jwk()).unwrap();
+    assert_eq!(jwk["kty"], "OKP");
+    assert_eq!(jwk["crv"], "Ed25519");
+    assert_eq!(jwk["alg"], "EdDSA");
+    assert!(jwk.get("y").is_none());
+
+    // PEM lines are at most 64 characters long
+    let pem = public_key.to_pem();
+    let lines = pem.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "-----BEGIN PUBLIC KEY-----");
+    assert_eq!(lines[lines.len() - 1], "-----END PUBLIC KEY-----");
+    assert!(lines.iter().all(|line| line.len() <= 64));
+}
+
+#[test]
+fn reads_pem_with_surrounding_text() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    let credential = create(&fido, &mut device, CredentialType::ES256);
+    let public_key = credential.as_ref().public_key().unwrap();
+    let pem = format!("Key of alice\n{}\nExpires never\n", public_key.to_pem());
+    assert_eq!(
+        PublicKey::from_pem(&pem).unwrap().to_der(),
+        public_key.to_der()
+    );
+}
+
+#[test]
+fn rejects_malformed_keys() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    let credential = create(&fido, &mut device, CredentialType::ES256);
+    let public_key = credential.as_ref().public_key().unwrap();
+    let der = public_key.to_der();
+    let mut jwk = public_key.to_jwk();
+    jwk.y = None;
+
+    for err in [
+        PublicKey::from_der(&der[..der.len() - 1]).err(),
+        PublicKey::from_der(&[&der[..], &[0]].concat()).err(),
+        PublicKey::from_pem("-----BEGIN PUBLIC KEY-----\n!!!\n-----END PUBLIC KEY-----").err(),
+        PublicKey::from_pem(&public_key.to_pem().replace("-----END PUBLIC KEY-----", "")).err(),
+        PublicKey::from_jwk(&jwk).err(),
+    ] {
+        assert_eq!(err.unwrap().to_string(), "FIDO_ERR_INVALID_ARGUMENT");
+    }
+}
+
+#[test]
+fn rejects_unsupported_keys() {
+    let err = PublicKey::from_pem(P384_PEM).err().unwrap();
+    assert_eq!(err.kind(), FidoErrorKind::Unsupported);
+
+    let jwk = Jwk {
+        kty: "EC".to_owned(),
+        crv: Some("P-384".to_owned()),
+        alg: None,
+        x: None,
+        y: None,
+        n: None,
+        e: None,
+    };
+    assert_eq!(
+        PublicKey::from_jwk(&jwk).err().unwrap().kind(),
+        FidoErrorKind::Unsupported
+    );
+
+    // The algorithm must match the key type
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    let credential = create(&fido, &mut device, CredentialType::ES256);
+    let mut jwk = credential.as_ref().public_key().unwrap().to_jwk();
+    jwk.alg = Some("ES384".to_owned());
+    assert_eq!(
+        PublicKey::from_jwk(&jwk).err().unwrap().to_string(),
+        "FIDO_ERR_UNSUPPORTED_ALGORITHM"
+    );
+}
@@ -0,0 +1,211 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+
+const CERTIFICATE: &[u8] = b"-----BEGIN CERTIFICATE-----\nMIIB...\n-----END CERTIFICATE-----\n";
+
+/// Creates a resident credential for given user, and returns its large blob key.
+fn large_blob_key(fido: &Fido, device: &mut Device, user_id: &'static [u8]) -> Vec<u8> {
+    let mut data = credential_data();
+    data.user_id = user_id;
+    data.options = CredentialOptions::RESIDENT_KEY;
+    data.extensions = CredentialExtensions::LARGE_BLOB_KEY;
+    let credential = make_credential(fido, device, data, Some(pin())).unwrap();
+    let key = credential.as_ref().large_blob_key.unwrap().to_vec();
+    assert_eq!(key.len(), 32);
+    key
+}
+
+/// Returns data which does not compress, so it keeps its size on the device.
+fn incompressible(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_u32;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+#[test]
+fn stores_and_reads_blobs() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    let key = large_blob_key(&fido, &mut device, b"alice");
+
+    assert_eq!(device.large_blob(&key).unwrap(), None);
+    device
+        .set_large_blob(&key, CERTIFICATE, Some(pin()))
+        .unwrap();
+    assert_eq!(
+        device.large_blob(&key).unwrap().as_deref(),
+        Some(CERTIFICATE)
+    );
+
+    // Storing another blob replaces the previous one
+    let blob = incompressible(16);
+    device.set_large_blob(&key, &blob, Some(pin())).unwrap();
+    assert_eq!(device.large_blob(&key).unwrap(), Some(blob));
+}
+
+#[test]
+fn rejects_empty_blobs() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    let key = large_blob_key(&fido, &mut device, b"alice");
+
+    assert_eq!(
+        device
+            .set_large_blob(&key, &[], Some(pin()))
+            .unwrap_err()
+            .to_string(),
+        "FIDO_ERR_INVALID_ARGUMENT"
+    );
+    assert_eq!(device.large_blob(&key).unwrap(), None);
+}
+
+#[test]
+fn returns_key_in_assertions() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    let key = large_blob_key(&fido, &mut device, b"alice");
+
+    let mut data = assertion_data(None);
+    data.extensions = AssertionExtensions::LARGE_BLOB_KEY;
+    let assertion = get_assertion(&fido, &mut device, data, Some(pin())).unwrap();
+    assert_eq!(
+        assertion.iter().next().unwrap().large_blob_key,
+        Some(&key[..])
+    );
+
+    // The key is only returned on request
+    let assertion = get_assertion(&fido, &mut device, assertion_data(None), Some(pin())).unwrap();
+    assert_eq!(assertion.iter().next().unwrap().large_blob_key, None);
+
+    // Credentials without a large blob key do not have one
+    let mut data = credential_data();
+    data.options = CredentialOptions::RESIDENT_KEY;
+    data.user_id = b"bob";
+    let credential = make_credential(&fido, &mut device, data, Some(pin())).unwrap();
+    assert_eq!(credential.as_ref().large_blob_key, None);
+}
+
+#[test]
+fn keeps_blobs_of_credentials_apart() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    let alice = large_blob_key(&fido, &mut device, b"alice");
+    let bob = large_blob_key(&fido, &mut device, b"bob");
+    assert_ne!(alice, bob);
+
+    device
+        .set_large_blob(&alice, CERTIFICATE, Some(pin()))
+        .unwrap();
+    let blob = incompressible(32);
+    device.set_large_blob(&bob, &blob, Some(pin())).unwrap();
+    assert_eq!(
+        device.large_blob(&alice).unwrap().as_deref(),
+        Some(CERTIFICATE)
+    );
+    assert_eq!(device.large_blob(&bob).unwrap().as_ref(), Some(&blob));
+
+    device.remove_large_blob(&alice, Some(pin())).unwrap();
+    assert_eq!(device.large_blob(&alice).unwrap(), None);
+    assert_eq!(device.large_blob(&bob).unwrap(), Some(blob));
+}
+
+#[test]
+fn transfers_blobs_in_fragments() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    let key = large_blob_key(&fido, &mut device, b"alice");
+
+    // Larger than the maximum message size of the device
+    let blob = incompressible(1500);
+    device.set_large_blob(&key, &blob, Some(pin())).unwrap();
+    assert_eq!(device.large_blob(&key).unwrap(), Some(blob));
+
+    // The array of the device is limited to 2048 bytes
+    assert_eq!(
+        device
+            .set_large_blob(&key, &incompressible(4096), Some(pin()))
+            .unwrap_err()
+            .to_string(),
+        "FIDO_ERR_LARGEBLOB_STORAGE_FULL"
+    );
+}
+
+#[test]
+fn removes_blobs() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    let key = large_blob_key(&fido, &mut device, b"alice");
+
+    device
+        .set_large_blob(&key, CERTIFICATE, Some(pin()))
+        .unwrap();
+    device.remove_large_blob(&key, Some(pin())).unwrap();
+    assert_eq!(device.large_blob(&key).unwrap(), None);
+    assert_eq!(device.large_blob_array().unwrap(), [0x80]);
+    assert_eq!(
+        device
+            .remove_large_blob(&key, Some(pin()))
+            .unwrap_err()
+            .to_string(),
+        "FIDO_ERR_NOTFOUND"
+    );
+}
+
+#[test]
+fn replaces_serialized_array() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    let key = large_blob_key(&fido, &mut device, b"alice");
+
+    assert_eq!(device.large_blob_array().unwrap(), [0x80]);
+    device
+        .set_large_blob(&key, CERTIFICATE, Some(pin()))
+        .unwrap();
+    let array = device.large_blob_array().unwrap();
+    // An array with one entry
+    assert_eq!(array[0], 0x81);
+
+    device.remove_large_blob(&key, Some(pin())).unwrap();
+    device.set_large_blob_array(&array, Some(pin())).unwrap();
+    assert_eq!(device.large_blob_array().unwrap(), array);
+    assert_eq!(
+        device.large_blob(&key).unwrap().as_deref(),
+        Some(CERTIFICATE)
+    );
+}
+
+#[test]
+fn requires_pin_for_writing() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    let key = large_blob_key(&fido, &mut device, b"alice");
+
+    assert!(device.set_large_blob(&key, CERTIFICATE, None).is_err());
+    assert!(device.set_large_blob_array(&[0x80], None).is_err());
+    device
+        .set_large_blob(&key, CERTIFICATE, Some(pin()))
+        .unwrap();
+    assert!(device.remove_large_blob(&key, None).is_err());
+
+    // Reading does not require the PIN
+    assert_eq!(
+        device.large_blob(&key).unwrap().as_deref(),
+        Some(CERTIFICATE)
+    );
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+use std::ffi::CStr;
+
+/// Opens a device which reports its minimum PIN length to `localhost`.
+fn open_allowed(fido: &Fido, authenticator: &VirtualAuthenticator) -> Device {
+    let mut device = open(fido, authenticator);
+    device
+        .set_min_pin_length_relying_parties(&[relying_party_id()], Some(pin()))
+        .unwrap();
+    device
+}
+
+fn register(
+    fido: &Fido,
+    device: &mut Device,
+    relying_party_id: &'static CStr,
+    extensions: CredentialExtensions,
+) -> Credential {
+    let mut data = credential_data();
+    data.relying_party_id = relying_party_id;
+    data.extensions = extensions;
+    make_credential(fido, device, data, Some(pin())).unwrap()
+}
+
+#[test]
+fn reports_min_pin_length_to_allowed_relying_parties() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open_allowed(&fido, &authenticator);
+
+    let credential = register(
+        &fido,
+        &mut device,
+        relying_party_id(),
+        CredentialExtensions::MIN_PIN_LENGTH,
+    );
+    assert_eq!(credential.as_ref().min_pin_length, Some(4));
+    // libfido2 expects a minimum PIN length which was set in advance
+    let err = credential.verify().err().unwrap();
+    assert_eq!(err.to_string(), "FIDO_ERR_INVALID_PARAM");
+
+    // Raising the minimum PIN length is reported too
+    device.set_min_pin_length(6, Some(pin())).unwrap();
+    device.set_pin(cstr(b"123456\0"), Some(pin())).unwrap();
+    let mut data = credential_data();
+    data.extensions = CredentialExtensions::MIN_PIN_LENGTH;
+    let credential = make_credential(&fido, &mut device, data, Some(cstr(b"123456\0"))).unwrap();
+    assert_eq!(credential.as_ref().min_pin_length, Some(6));
+}
+
+#[test]
+fn hides_min_pin_length_from_other_relying_parties() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open_allowed(&fido, &authenticator);
+
+    let credential = register(
+        &fido,
+        &mut device,
+        cstr(b"example.org\0"),
+        CredentialExtensions::MIN_PIN_LENGTH,
+    );
+    assert_eq!(credential.as_ref().min_pin_length, None);
+
+    // Without the extension, allowed relying parties do not learn it either
+    let credential = register(
+        &fido,
+        &mut device,
+        relying_party_id(),
+        CredentialExtensions::empty(),
+    );
+    assert_eq!(credential.as_ref().min_pin_length, None);
+    credential.verify().unwrap();
+}
+
+#[test]
+fn reads_min_pin_length_from_attestation() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open_allowed(&fido, &authenticator);
+
+    let credential = register(
+        &fido,
+        &mut device,
+        relying_party_id(),
+        CredentialExtensions::MIN_PIN_LENGTH,
+    );
+    let credential = credential.as_ref();
+    let attested = Credential::from_attestation(CredentialAttestationData {
+        format: credential.format.to_str().unwrap().parse().unwrap(),
+        auth_data: credential.raw_auth_data,
+        x509_certificate: Some(credential.x509_certificate),
+        signature: credential.signature,
+        client_data_hash: &CLIENT_DATA_HASH,
+        relying_party_id: relying_party_id(),
+        credential_type: credential.credential_type,
+    })
+    .unwrap();
+    attested.verify().unwrap();
+    assert_eq!(attested.as_ref().min_pin_length, Some(4));
+}
+
+#[test]
+fn combines_with_other_extensions() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open_allowed(&fido, &authenticator);
+
+    // A credential blob which is too large is not stored
+    let too_large = [0x42; 1024];
+    let mut data = credential_data();
+    data.options = CredentialOptions::RESIDENT_KEY;
+    data.extensions = CredentialExtensions::MIN_PIN_LENGTH | CredentialExtensions::HMAC_SECRET;
+    data.credential_blob = Some(&too_large);
+    let credential = make_credential(&fido, &mut device, data, Some(pin())).unwrap();
+    let credential = credential.as_ref();
+    assert_eq!(credential.min_pin_length, Some(4));
+    assert!(!credential.credential_blob_stored);
+}
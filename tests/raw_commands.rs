@@ -0,0 +1,50 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+
+const CTAPHID_PING: u8 = 0x01;
+const CTAPHID_ERROR: u8 = 0x3f;
+const CTAP1_ERR_INVALID_COMMAND: u8 = 0x01;
+const AUTHENTICATOR_GET_INFO: u8 = 0x04;
+
+#[test]
+fn sends_ctaphid_messages() {
+    let fido = Fido::new(false);
+    let mut device = open(&fido, &VirtualAuthenticator::new());
+
+    let ping = device.transact_hid(CTAPHID_PING, b"ping").unwrap();
+    assert_eq!(ping.status, CTAPHID_PING);
+    assert_eq!(ping.data, b"ping");
+
+    // Messages longer than a frame are split into continuation frames
+    let payload = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+    assert_eq!(
+        device.transact_hid(CTAPHID_PING, &payload).unwrap().data,
+        payload
+    );
+
+    let unknown = device.transact_hid(0x3e, &[]).unwrap();
+    assert_eq!(unknown.status, CTAPHID_ERROR);
+    assert_eq!(unknown.data, [CTAP1_ERR_INVALID_COMMAND]);
+}
+
+#[test]
+fn sends_ctap2_commands() {
+    let fido = Fido::new(false);
+    let mut device = open(&fido, &VirtualAuthenticator::new());
+
+    let info = device.transact_cbor(AUTHENTICATOR_GET_INFO, &[]).unwrap();
+    assert_eq!(info.status, 0x00);
+    // The response is a CBOR map, which lists the supported versions
+    assert_eq!(info.data[0] & 0xe0, 0xa0);
+    assert!(info.data.windows(8).any(|window| window == b"FIDO_2_0"));
+
+    // Errors of the authenticator are returned as status
+    let unknown = device.transact_cbor(0x7f, &[]).unwrap();
+    assert_eq!(unknown.status, CTAP1_ERR_INVALID_COMMAND);
+    assert!(unknown.data.is_empty());
+
+    // The device remains usable for regular requests
+    make_credential(&fido, &mut device, credential_data(), None).unwrap();
+}
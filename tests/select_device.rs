@@ -0,0 +1,50 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+use std::time::Duration;
+
+#[test]
+fn selects_the_touched_device() {
+    let fido = Fido::new(false);
+    let slow = VirtualAuthenticator::new();
+    slow.set_user_presence_delay(Duration::from_secs(10));
+    let fast = VirtualAuthenticator::new();
+    fast.set_user_presence_delay(Duration::from_millis(200));
+
+    let devices = vec![open(&fido, &slow), open(&fido, &fast)];
+    let mut selected = fido
+        .select_device_from(devices, Some(Duration::from_secs(5)))
+        .unwrap();
+
+    // Identify the selected device by letting it blink
+    selected.wink().unwrap();
+    assert_eq!(fast.wink_count(), 1);
+    assert_eq!(slow.wink_count(), 0);
+}
+
+#[test]
+fn times_out_without_touch() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    authenticator.set_user_presence_delay(Duration::from_secs(10));
+
+    let err = fido
+        .select_device_from(
+            vec![open(&fido, &authenticator)],
+            Some(Duration::from_millis(200)),
+        )
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), FidoErrorKind::Timeout);
+}
+
+#[test]
+fn fails_without_devices() {
+    let fido = Fido::new(false);
+    let err = fido
+        .select_device_from(Vec::new(), Some(Duration::from_millis(200)))
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "FIDO_ERR_INVALID_ARGUMENT");
+}
@@ -0,0 +1,219 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+use std::ffi::CStr;
+
+// Attestation as it arrives at the relying party
+struct Attestation {
+    format: String,
+    auth_data: Vec<u8>,
+    x5c: Option<Vec<u8>>,
+    signature: Vec<u8>,
+    credential_type: CredentialType,
+}
+
+impl Attestation {
+    fn from_credential(credential: &Credential) -> Self {
+        let credential = credential.as_ref();
+        Attestation {
+            format: credential.format.to_str().unwrap().to_owned(),
+            auth_data: credential.raw_auth_data.to_vec(),
+            x5c: Some(credential.x509_certificate)
+                .filter(|x5c| !x5c.is_empty())
+                .map(<[u8]>::to_vec),
+            signature: credential.signature.to_vec(),
+            credential_type: credential.credential_type,
+        }
+    }
+
+    fn data<'a>(&'a self, relying_party_id: &'a CStr) -> CredentialAttestationData<'a> {
+        CredentialAttestationData {
+            format: self.format.parse().unwrap(),
+            auth_data: &self.auth_data,
+            x509_certificate: self.x5c.as_deref(),
+            signature: &self.signature,
+            client_data_hash: &CLIENT_DATA_HASH,
+            relying_party_id,
+            credential_type: self.credential_type,
+        }
+    }
+}
+
+/// Registers a credential of given type, and returns its attestation.
+fn register(
+    fido: &Fido,
+    device: &mut Device,
+    credential_type: CredentialType,
+) -> (Credential, Attestation) {
+    let mut data = credential_data();
+    data.credential_type = credential_type;
+    let credential = make_credential(fido, device, data, None).unwrap();
+    let attestation = Attestation::from_credential(&credential);
+    (credential, attestation)
+}
+
+#[test]
+fn verifies_attestations() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    for &credential_type in &[
+        CredentialType::ES256,
+        CredentialType::EDDSA,
+        CredentialType::RS256,
+    ] {
+        let (credential, attestation) = register(&fido, &mut device, credential_type);
+        assert_eq!(attestation.format, "packed");
+        assert!(attestation.x5c.is_some());
+
+        let verified = Credential::from_attestation(attestation.data(relying_party_id())).unwrap();
+        verified.verify().unwrap();
+        let verified = verified.as_ref();
+        let credential = credential.as_ref();
+        assert_eq!(verified.id, credential.id);
+        assert_eq!(verified.public_key, credential.public_key);
+        assert_eq!(verified.x509_certificate, credential.x509_certificate);
+        assert_eq!(verified.credential_type, credential_type);
+    }
+}
+
+#[test]
+fn verifies_self_attestations() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    authenticator.set_self_attestation(true);
+    let mut device = open(&fido, &authenticator);
+
+    for &credential_type in &[CredentialType::ES256, CredentialType::EDDSA] {
+        let (credential, attestation) = register(&fido, &mut device, credential_type);
+        credential.verify_self().unwrap();
+        assert_eq!(attestation.format, "packed");
+        assert_eq!(attestation.x5c, None);
+
+        let verified = Credential::from_attestation(attestation.data(relying_party_id())).unwrap();
+        // Self attestation has to be accepted explicitly
+        assert!(verified.verify().is_err());
+        verified.verify_self().unwrap();
+        assert!(verified.as_ref().x509_certificate.is_empty());
+        assert_eq!(verified.as_ref().public_key, credential.as_ref().public_key);
+    }
+}
+
+#[test]
+fn rejects_mismatched_certificates() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    let (_, with_certificate) = register(&fido, &mut device, CredentialType::ES256);
+    authenticator.set_self_attestation(true);
+    let (_, self_attested) = register(&fido, &mut device, CredentialType::ES256);
+
+    // Attestations signed with a certificate are not self attestations, and vice versa
+    let mut without_certificate = with_certificate.data(relying_party_id());
+    without_certificate.x509_certificate = None;
+    let credential = Credential::from_attestation(without_certificate).unwrap();
+    assert!(credential.verify_self().is_err());
+
+    let mut with_other_certificate = self_attested.data(relying_party_id());
+    with_other_certificate.x509_certificate = with_certificate.x5c.as_deref();
+    let credential = Credential::from_attestation(with_other_certificate).unwrap();
+    assert!(credential.verify().is_err());
+}
+
+#[test]
+fn rejects_tampered_attestations() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    let (_, attestation) = register(&fido, &mut device, CredentialType::ES256);
+
+    // Another relying party
+    let other = cstr(b"example.org\0");
+    let credential = Credential::from_attestation(attestation.data(other)).unwrap();
+    assert!(credential.verify().is_err());
+
+    // Another client data hash
+    let mut data = attestation.data(relying_party_id());
+    let client_data_hash = [0; 32];
+    data.client_data_hash = &client_data_hash;
+    let credential = Credential::from_attestation(data).unwrap();
+    assert!(credential.verify().is_err());
+
+    // Another sign count
+    let mut tampered = attestation.auth_data.clone();
+    tampered[36] ^= 1;
+    let mut data = attestation.data(relying_party_id());
+    data.auth_data = &tampered;
+    let credential = Credential::from_attestation(data).unwrap();
+    assert!(credential.verify().is_err());
+
+    // Another signature
+    let mut signature = attestation.signature.clone();
+    let last = signature.len() - 1;
+    signature[last] ^= 1;
+    let mut data = attestation.data(relying_party_id());
+    data.signature = &signature;
+    let credential = Credential::from_attestation(data).unwrap();
+    assert!(credential.verify().is_err());
+}
+
+#[test]
+fn rejects_malformed_attestations() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    let (_, attestation) = register(&fido, &mut device, CredentialType::ES256);
+
+    let mut data = attestation.data(relying_party_id());
+    data.credential_type = CredentialType::EDDSA;
+    assert!(Credential::from_attestation(data).is_err());
+
+    let truncated = &attestation.auth_data[..attestation.auth_data.len() - 1];
+    let mut data = attestation.data(relying_party_id());
+    data.auth_data = truncated;
+    assert!(Credential::from_attestation(data).is_err());
+
+    // The `none` format is not supported
+    assert_eq!(
+        "none".parse::<CredentialFormat>(),
+        Err(InvalidCredentialFormatError)
+    );
+}
+
+#[test]
+fn reports_extension_outputs() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    device
+        .set_min_pin_length_relying_parties(&[relying_party_id()], Some(pin()))
+        .unwrap();
+
+    let mut data = credential_data();
+    data.options = CredentialOptions::RESIDENT_KEY;
+    data.extensions = CredentialExtensions::HMAC_SECRET | CredentialExtensions::MIN_PIN_LENGTH;
+    data.protection = Some(CredentialProtection::UserVerificationRequired);
+    data.credential_blob = Some(b"backup-key/v1");
+    let credential = make_credential(&fido, &mut device, data, Some(pin())).unwrap();
+    let attestation = Attestation::from_credential(&credential);
+
+    let verified = Credential::from_attestation(attestation.data(relying_party_id())).unwrap();
+    verified.verify().unwrap();
+    let verified = verified.as_ref();
+    assert_eq!(
+        verified.protection,
+        Some(CredentialProtection::UserVerificationRequired)
+    );
+    assert!(verified.credential_blob_stored);
+    assert_eq!(verified.min_pin_length, Some(4));
+    assert_eq!(
+        verified
+            .authenticator_data()
+            .unwrap()
+            .extensions
+            .get("hmac-secret"),
+        Some(&CborValue::Bool(true))
+    );
+}
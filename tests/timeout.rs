@@ -0,0 +1,72 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+use std::time::{Duration, Instant};
+
+#[test]
+fn times_out_requests_of_the_device() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    device
+        .set_timeout(Some(Duration::from_millis(200)))
+        .unwrap();
+    assert_eq!(device.timeout(), Some(Duration::from_millis(200)));
+
+    authenticator.set_user_presence_delay(Duration::from_secs(10));
+    let start = Instant::now();
+    let err = make_credential(&fido, &mut device, credential_data(), None)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), FidoErrorKind::Timeout);
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // The request is cancelled on the device, so it does not create the credential later on
+    authenticator.set_user_presence_delay(Duration::from_secs(0));
+    assert_eq!(authenticator.credential_count(), 0);
+    make_credential(&fido, &mut device, credential_data(), None).unwrap();
+    assert_eq!(authenticator.credential_count(), 1);
+}
+
+#[test]
+fn overrides_the_timeout_per_request() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    device.set_timeout(Some(Duration::from_secs(30))).unwrap();
+
+    authenticator.set_user_presence_delay(Duration::from_secs(10));
+    let mut data = credential_data();
+    data.timeout = Some(Duration::from_millis(200));
+    let start = Instant::now();
+    let err = make_credential(&fido, &mut device, data, None)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), FidoErrorKind::Timeout);
+    assert!(start.elapsed() < Duration::from_secs(5));
+    // The timeout of the device itself is unchanged
+    assert_eq!(device.timeout(), Some(Duration::from_secs(30)));
+
+    authenticator.set_user_presence_delay(Duration::from_secs(0));
+    let credential = make_credential(&fido, &mut device, credential_data(), None).unwrap();
+    let id = credential.as_ref().id;
+    authenticator.set_user_presence_delay(Duration::from_secs(10));
+    let ids = [id];
+    let mut data = assertion_data(Some(&ids));
+    data.options = AssertionOptions::USER_PRESENCE;
+    data.timeout = Some(Duration::from_millis(200));
+    let err = get_assertion(&fido, &mut device, data, None).err().unwrap();
+    assert_eq!(err.kind(), FidoErrorKind::Timeout);
+}
+
+#[test]
+fn waits_indefinitely_by_default() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    assert_eq!(device.timeout(), None);
+
+    authenticator.set_user_presence_delay(Duration::from_millis(500));
+    make_credential(&fido, &mut device, credential_data(), None).unwrap();
+}
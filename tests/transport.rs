@@ -0,0 +1,89 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Records the calls to a wrapped transport.
+struct Recorder<T> {
+    inner: T,
+    calls: Arc<Mutex<Vec<&'static str>>>,
+    fail_open: bool,
+}
+
+impl<T> Recorder<T> {
+    fn new(inner: T) -> (Self, Arc<Mutex<Vec<&'static str>>>) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Recorder {
+            inner,
+            calls: calls.clone(),
+            fail_open: false,
+        };
+        (recorder, calls)
+    }
+
+    fn record(&self, call: &'static str) {
+        self.calls.lock().unwrap().push(call);
+    }
+}
+
+impl<T: Transport> Transport for Recorder<T> {
+    fn open(&self) -> io::Result<()> {
+        self.record("open");
+        if self.fail_open {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "unplugged"));
+        }
+        self.inner.open()
+    }
+
+    fn close(&self) {
+        self.record("close");
+        self.inner.close()
+    }
+
+    fn read(&self, frame: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        self.record("read");
+        self.inner.read(frame, timeout)
+    }
+
+    fn write(&self, frame: &[u8]) -> io::Result<usize> {
+        assert_eq!(frame.len(), 64);
+        self.record("write");
+        self.inner.write(frame)
+    }
+}
+
+#[test]
+fn exchanges_frames_through_the_transport() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let (transport, calls) = Recorder::new(authenticator.transport());
+    let mut device = fido.new_device_with_transport(transport).unwrap();
+    assert_eq!(device.mode(), DeviceMode::Fido2);
+
+    let credential = make_credential(&fido, &mut device, credential_data(), None).unwrap();
+    credential.verify().unwrap();
+    assert_eq!(authenticator.credential_count(), 1);
+
+    let recorded = calls.lock().unwrap().clone();
+    assert_eq!(recorded.first(), Some(&"open"));
+    assert!(recorded.contains(&"write"));
+    assert!(recorded.contains(&"read"));
+    assert!(!recorded.contains(&"close"));
+
+    drop(device);
+    assert_eq!(calls.lock().unwrap().last(), Some(&"close"));
+}
+
+#[test]
+fn reports_failure_to_open() {
+    let fido = Fido::new(false);
+    let (mut transport, calls) = Recorder::new(VirtualAuthenticator::new().transport());
+    transport.fail_open = true;
+    assert!(fido.new_device_with_transport(transport).is_err());
+    assert_eq!(*calls.lock().unwrap(), ["open"]);
+}
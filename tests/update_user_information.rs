@@ -0,0 +1,140 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+
+/// Creates a resident credential for John Doe, and returns it as stored on the device.
+fn resident_credential(fido: &Fido, device: &mut Device) -> ResidentCredential {
+    let mut data = credential_data();
+    data.options = CredentialOptions::RESIDENT_KEY;
+    data.user_display_name = Some(cstr(b"John\0"));
+    make_credential(fido, device, data, Some(pin())).unwrap();
+    stored_credential(device)
+}
+
+fn stored_credential(device: &mut Device) -> ResidentCredential {
+    let mut credentials = device
+        .resident_credentials(relying_party_id(), Some(pin()))
+        .unwrap();
+    assert_eq!(credentials.len(), 1);
+    credentials.remove(0)
+}
+
+#[test]
+fn renames_user() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    let credential = resident_credential(&fido, &mut device);
+    assert_eq!(credential.user_name.as_deref(), Some("John Doe"));
+    assert_eq!(credential.user_display_name.as_deref(), Some("John"));
+
+    device
+        .update_user_information(
+            &credential.id,
+            UserInformation {
+                id: &credential.user_id,
+                name: cstr(b"Jane Doe\0"),
+                display_name: Some(cstr(b"Jane\0")),
+            },
+            Some(pin()),
+        )
+        .unwrap();
+    let updated = stored_credential(&mut device);
+    assert_eq!(updated.user_name.as_deref(), Some("Jane Doe"));
+    assert_eq!(updated.user_display_name.as_deref(), Some("Jane"));
+
+    // The credential itself is unchanged
+    assert_eq!(updated.id, credential.id);
+    assert_eq!(updated.user_id, credential.user_id);
+    assert_eq!(updated.public_key, credential.public_key);
+}
+
+#[test]
+fn removes_display_name() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    let credential = resident_credential(&fido, &mut device);
+
+    device
+        .update_user_information(
+            &credential.id,
+            UserInformation {
+                id: &credential.user_id,
+                name: cstr(b"John Doe\0"),
+                display_name: None,
+            },
+            Some(pin()),
+        )
+        .unwrap();
+    let updated = stored_credential(&mut device);
+    assert_eq!(updated.user_name.as_deref(), Some("John Doe"));
+    assert_eq!(updated.user_display_name, None);
+}
+
+#[test]
+fn rejects_other_users_and_unknown_credentials() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    let credential = resident_credential(&fido, &mut device);
+
+    // The user ID must match the stored one
+    assert!(device
+        .update_user_information(
+            &credential.id,
+            UserInformation {
+                id: b"someone else",
+                name: cstr(b"Jane Doe\0"),
+                display_name: None,
+            },
+            Some(pin()),
+        )
+        .is_err());
+
+    assert_eq!(
+        device
+            .update_user_information(
+                &[0; 16],
+                UserInformation {
+                    id: &credential.user_id,
+                    name: cstr(b"Jane Doe\0"),
+                    display_name: None,
+                },
+                Some(pin()),
+            )
+            .unwrap_err()
+            .to_string(),
+        "FIDO_ERR_NO_CREDENTIALS"
+    );
+
+    // Neither request changed the credential
+    let stored = stored_credential(&mut device);
+    assert_eq!(stored.user_name.as_deref(), Some("John Doe"));
+    assert_eq!(stored.user_display_name.as_deref(), Some("John"));
+}
+
+#[test]
+fn requires_pin() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+    let credential = resident_credential(&fido, &mut device);
+
+    assert!(device
+        .update_user_information(
+            &credential.id,
+            UserInformation {
+                id: &credential.user_id,
+                name: cstr(b"Jane Doe\0"),
+                display_name: None,
+            },
+            None,
+        )
+        .is_err());
+    assert_eq!(
+        stored_credential(&mut device).user_name.as_deref(),
+        Some("John Doe")
+    );
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn registers_and_authenticates_every_key_type() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    assert_eq!(device.mode(), DeviceMode::Fido2);
+
+    for &credential_type in &[
+        CredentialType::ES256,
+        CredentialType::EDDSA,
+        CredentialType::RS256,
+    ] {
+        let mut data = credential_data();
+        data.credential_type = credential_type;
+        let credential = make_credential(&fido, &mut device, data, None).unwrap();
+        credential.verify().unwrap();
+        let credential = credential.as_ref();
+        assert_eq!(credential.credential_type, credential_type);
+
+        let assertion = get_assertion(
+            &fido,
+            &mut device,
+            assertion_data(Some(&[credential.id])),
+            None,
+        )
+        .unwrap();
+        assert!(assertion.verify_one(credential.public_key().unwrap()));
+    }
+    assert_eq!(authenticator.credential_count(), 3);
+    assert_eq!(authenticator.resident_credential_count(RELYING_PARTY_ID), 0);
+    assert_eq!(authenticator.sign_count(), 6);
+}
+
+#[test]
+fn discovers_resident_credentials_with_pin() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    device.set_pin(pin(), None).unwrap();
+    assert!(authenticator.has_pin());
+    assert_eq!(device.retry_count().unwrap(), 8);
+
+    let mut data = credential_data();
+    data.options = CredentialOptions::RESIDENT_KEY;
+    let credential = make_credential(&fido, &mut device, data, Some(pin())).unwrap();
+    credential.verify().unwrap();
+    assert_eq!(authenticator.resident_credential_count(RELYING_PARTY_ID), 1);
+
+    // Without an allow list, the authenticator looks up the resident credential itself
+    let assertion = get_assertion(&fido, &mut device, assertion_data(None), Some(pin())).unwrap();
+    let statement = assertion.iter().next().unwrap();
+    assert_eq!(statement.user_id, Some(&USER_ID[..]));
+    assert!(assertion.verify_one(credential.as_ref().public_key().unwrap()));
+}
+
+#[test]
+fn counts_wrong_pins() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::with_pin(PIN);
+    let mut device = open(&fido, &authenticator);
+
+    let wrong_pin = cstr(b"4321\0");
+    assert!(make_credential(&fido, &mut device, credential_data(), Some(wrong_pin)).is_err());
+    assert_eq!(authenticator.pin_retries(), 7);
+    assert_eq!(device.retry_count().unwrap(), 7);
+
+    make_credential(&fido, &mut device, credential_data(), Some(pin())).unwrap();
+    assert_eq!(authenticator.pin_retries(), 8);
+}
+
+#[test]
+fn requires_user_presence() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    authenticator.set_user_presence(false);
+    let err = make_credential(&fido, &mut device, credential_data(), None)
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "FIDO_ERR_OPERATION_DENIED");
+    assert_eq!(authenticator.credential_count(), 0);
+}
+
+#[test]
+fn serves_other_devices_while_waiting_for_user_presence() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+
+    authenticator.set_user_presence_delay(Duration::from_millis(500));
+    let other = thread::spawn({
+        let authenticator = authenticator.clone();
+        move || {
+            thread::sleep(Duration::from_millis(100));
+            let fido = Fido::new(false);
+            let started = Instant::now();
+            let info = open(&fido, &authenticator).request_cbor_data().unwrap();
+            // The user declines the pending request in the meantime
+            authenticator.set_user_presence(false);
+            let versions: Vec<String> = info
+                .as_ref()
+                .ctap_versions
+                .iter()
+                .map(|v| v.to_string())
+                .collect();
+            (started.elapsed(), versions)
+        }
+    });
+    let err = make_credential(&fido, &mut device, credential_data(), None)
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "FIDO_ERR_OPERATION_DENIED");
+
+    let (elapsed, versions) = other.join().unwrap();
+    assert!(elapsed < Duration::from_millis(300));
+    assert_eq!(versions, ["FIDO_2_0", "FIDO_2_1"]);
+}
+
+#[test]
+fn shares_state_between_devices() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let credential = make_credential(
+        &fido,
+        &mut open(&fido, &authenticator),
+        credential_data(),
+        None,
+    )
+    .unwrap();
+
+    // A clone of the authenticator still holds the credential
+    let mut device = open(&fido, &authenticator.clone());
+    let id = credential.as_ref().id;
+    let assertion = get_assertion(&fido, &mut device, assertion_data(Some(&[id])), None).unwrap();
+    assert!(assertion.verify_one(credential.as_ref().public_key().unwrap()));
+}
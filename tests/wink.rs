@@ -0,0 +1,57 @@
+mod common;
+
+use common::*;
+use libfido2::*;
+use std::{io, time::Duration};
+
+// Offset of the capabilities in the response to CTAPHID_INIT
+const CAPABILITIES_OFFSET: usize = 23;
+const CTAPHID_INIT_RESPONSE: u8 = 0x86;
+
+/// Hides the wink capability of the wrapped transport.
+struct WithoutWink<T>(T);
+
+impl<T: Transport> Transport for WithoutWink<T> {
+    fn read(&self, frame: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        let n = self.0.read(frame, timeout)?;
+        if frame[4] == CTAPHID_INIT_RESPONSE {
+            frame[CAPABILITIES_OFFSET] &= !CTAPHIDCapabilities::WINK.bits();
+        }
+        Ok(n)
+    }
+
+    fn write(&self, frame: &[u8]) -> io::Result<usize> {
+        self.0.write(frame)
+    }
+}
+
+#[test]
+fn winks() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = open(&fido, &authenticator);
+    assert!(device
+        .ctap_hid_info()
+        .capabilities
+        .contains(CTAPHIDCapabilities::WINK));
+
+    device.wink().unwrap();
+    device.wink().unwrap();
+    assert_eq!(authenticator.wink_count(), 2);
+
+    // Winking does not interfere with regular requests
+    make_credential(&fido, &mut device, credential_data(), None).unwrap();
+}
+
+#[test]
+fn requires_the_wink_capability() {
+    let fido = Fido::new(false);
+    let authenticator = VirtualAuthenticator::new();
+    let mut device = fido
+        .new_device_with_transport(WithoutWink(authenticator.transport()))
+        .unwrap();
+
+    let err = device.wink().err().unwrap();
+    assert_eq!(err.kind(), FidoErrorKind::Unsupported);
+    assert_eq!(authenticator.wink_count(), 0);
+}